@@ -0,0 +1,53 @@
+use crate::api::twitch::ApiClientCredentials;
+use crate::HTTP_CLIENT;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Token of the application itself, not tied to any user.
+#[derive(Debug, Clone)]
+pub struct TwitchAppAccessToken {
+    pub access_token: String,
+    pub valid_until: DateTime<Utc>,
+}
+
+impl TwitchAppAccessToken {
+    pub fn is_expired(&self) -> bool {
+        self.valid_until <= Utc::now()
+    }
+}
+
+#[derive(Deserialize)]
+struct TwitchAppAccessTokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+#[derive(Serialize)]
+struct ClientCredentialsFormData<'a> {
+    client_id: &'a str,
+    client_secret: &'a str,
+    grant_type: &'static str,
+}
+
+/// Get a new app access token through the client credentials grant flow.
+pub async fn get_app_access_token(
+    client_credentials: &ApiClientCredentials,
+) -> Result<TwitchAppAccessToken, reqwest::Error> {
+    let resp = HTTP_CLIENT
+        .post(client_credentials.oauth2_endpoint("token"))
+        .form(&ClientCredentialsFormData {
+            client_id: &client_credentials.client_id,
+            client_secret: &client_credentials.client_secret,
+            grant_type: "client_credentials",
+        })
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TwitchAppAccessTokenResponse>()
+        .await?;
+
+    Ok(TwitchAppAccessToken {
+        access_token: resp.access_token,
+        valid_until: Utc::now() + Duration::seconds(resp.expires_in),
+    })
+}
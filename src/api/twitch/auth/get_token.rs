@@ -16,7 +16,7 @@ pub async fn get_token(
     code: &str,
 ) -> Result<TwitchUserAccessToken, GetTokenError> {
     let resp = crate::HTTP_CLIENT
-        .post("https://id.twitch.tv/oauth2/token")
+        .post(client_credentials.oauth2_endpoint("token"))
         .query(&[
             ("client_id", client_credentials.client_id.as_str()),
             ("client_secret", client_credentials.client_secret.as_str()),
@@ -1,7 +1,9 @@
+mod app_token;
 mod get_token;
 mod refresh;
 mod revoke;
 
+pub use app_token::{get_app_access_token, TwitchAppAccessToken};
 pub use get_token::{get_token, GetTokenError};
 pub use refresh::{refresh_token, RefreshTokenError};
 pub use revoke::{revoke_token, RevokeTokenError};
@@ -39,3 +41,65 @@ impl From<TwitchUserAccessTokenResponse> for TwitchUserAccessToken {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::twitch::user::get_user_for_authorization;
+    use crate::testing::{self, MockTwitch};
+
+    #[tokio::test]
+    async fn authorization_code_flow() {
+        let twitch = MockTwitch::start().await;
+        let credentials = twitch.credentials();
+
+        let token = get_token(&credentials, testing::AUTHORIZATION_CODE)
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, testing::USER_ACCESS_TOKEN);
+        assert!(token.scope.contains("user:write:chat"));
+
+        let user = get_user_for_authorization(&credentials, &token.access_token)
+            .await
+            .unwrap();
+        assert_eq!(user.basics.id, testing::USER_ID);
+        assert_eq!(user.basics.login, testing::USER_LOGIN);
+
+        let refreshed = refresh_token(&credentials, &token.refresh_token)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.refresh_token, testing::REFRESH_TOKEN);
+
+        revoke_token(&credentials, &refreshed.access_token)
+            .await
+            .unwrap();
+        assert_eq!(
+            twitch.state().revoked_tokens,
+            [testing::USER_ACCESS_TOKEN.to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_codes_are_rejected() {
+        let twitch = MockTwitch::start().await;
+        let credentials = twitch.credentials();
+
+        assert!(matches!(
+            get_token(&credentials, "wrong-code").await,
+            Err(GetTokenError::InvalidAuthorizationCode(_))
+        ));
+        assert!(matches!(
+            refresh_token(&credentials, "wrong-refresh-token").await,
+            Err(RefreshTokenError::InvalidRefreshToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn app_access_token() {
+        let twitch = MockTwitch::start().await;
+
+        let token = get_app_access_token(&twitch.credentials()).await.unwrap();
+        assert_eq!(token.access_token, testing::APP_ACCESS_TOKEN);
+        assert!(!token.is_expired());
+    }
+}
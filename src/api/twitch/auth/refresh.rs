@@ -26,7 +26,7 @@ pub async fn refresh_token(
     refresh_token: &str,
 ) -> Result<TwitchUserAccessToken, RefreshTokenError> {
     let resp = HTTP_CLIENT
-        .post("https://id.twitch.tv/oauth2/token")
+        .post(client_credentials.oauth2_endpoint("token"))
         .form(&RefreshFormData {
             client_id: &client_credentials.client_id,
             client_secret: &client_credentials.client_secret,
@@ -23,7 +23,7 @@ pub async fn revoke_token(
     access_token: &str,
 ) -> Result<(), RevokeTokenError> {
     HTTP_CLIENT
-        .post("https://id.twitch.tv/oauth2/revoke")
+        .post(client_credentials.oauth2_endpoint("revoke"))
         .form(&RevokeFormData {
             client_id: &client_credentials.client_id,
             token: access_token,
@@ -0,0 +1,103 @@
+use crate::api::twitch::ApiClientCredentials;
+use serde::Serialize;
+
+/// Final status of a channel point reward redemption
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RedemptionStatus {
+    Fulfilled,
+    /// The points are refunded to the redeemer
+    Canceled,
+}
+
+#[derive(Serialize)]
+struct UpdateRedemptionBody {
+    status: RedemptionStatus,
+}
+
+/// Complete a redemption that is still in the reward's request queue. Twitch only allows this for
+/// rewards created with the same client ID, and responds with 403 Forbidden otherwise.
+pub async fn update_redemption_status(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    broadcaster_id: &str,
+    reward_id: &str,
+    redemption_id: &str,
+    status: RedemptionStatus,
+) -> Result<(), reqwest::Error> {
+    crate::HTTP_CLIENT
+        .patch(client_credentials.helix_endpoint("channel_points/custom_rewards/redemptions"))
+        .query(&[
+            ("id", redemption_id),
+            ("broadcaster_id", broadcaster_id),
+            ("reward_id", reward_id),
+        ])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&UpdateRedemptionBody { status })
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockTwitch};
+
+    #[tokio::test]
+    async fn fulfill_and_cancel() {
+        let twitch = MockTwitch::start().await;
+        let credentials = twitch.credentials();
+        let token = testing::USER_ACCESS_TOKEN;
+
+        update_redemption_status(
+            &credentials,
+            token,
+            "1000",
+            "reward-1",
+            "redemption-1",
+            RedemptionStatus::Fulfilled,
+        )
+        .await
+        .unwrap();
+        update_redemption_status(
+            &credentials,
+            token,
+            "1000",
+            "reward-1",
+            "redemption-2",
+            RedemptionStatus::Canceled,
+        )
+        .await
+        .unwrap();
+        let updates = twitch.state().redemption_updates.clone();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].broadcaster_id, "1000");
+        assert_eq!(updates[0].reward_id, "reward-1");
+        assert_eq!(updates[0].redemption_id, "redemption-1");
+        assert_eq!(updates[0].status, "FULFILLED");
+        assert_eq!(updates[1].status, "CANCELED");
+    }
+
+    #[tokio::test]
+    async fn reward_of_other_client_is_forbidden() {
+        let twitch = MockTwitch::start().await;
+
+        let result = update_redemption_status(
+            &twitch.credentials(),
+            testing::USER_ACCESS_TOKEN,
+            "1000",
+            testing::FOREIGN_REWARD_ID,
+            "redemption-1",
+            RedemptionStatus::Fulfilled,
+        )
+        .await;
+        assert_eq!(
+            result.unwrap_err().status(),
+            Some(http::StatusCode::FORBIDDEN)
+        );
+        assert!(twitch.state().redemption_updates.is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::api::twitch::{rate_limit_reset, ApiClientCredentials};
+use crate::HTTP_CLIENT;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SendChatMessageError {
+    #[error("Rate limit exceeded")]
+    RateLimited { reset_at: Option<DateTime<Utc>> },
+    #[error("Access token is invalid or lacks the required scope")]
+    Unauthorized(reqwest::Error),
+    #[error("{0}")]
+    Other(#[from] reqwest::Error),
+}
+
+#[derive(Serialize)]
+struct SendChatMessageBody<'a> {
+    broadcaster_id: &'a str,
+    sender_id: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_parent_message_id: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct HelixSendChatMessageResponse {
+    // we expect a list of size 1
+    data: (SentChatMessage,),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SentChatMessage {
+    pub message_id: String,
+    pub is_sent: bool,
+    pub drop_reason: Option<DropReason>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DropReason {
+    pub code: String,
+    pub message: String,
+}
+
+/// Send a chat message as `sender_id` to the chat of `broadcaster_id`. If `reply_parent_message_id`
+/// is given, the message is sent as a reply to that message.
+///
+/// Note that Twitch may still decide to drop the message (e.g. because of AutoMod),
+/// check [`SentChatMessage::is_sent`].
+pub async fn send_chat_message(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    broadcaster_id: &str,
+    sender_id: &str,
+    message: &str,
+    reply_parent_message_id: Option<&str>,
+) -> Result<SentChatMessage, SendChatMessageError> {
+    let response = HTTP_CLIENT
+        .post(client_credentials.helix_endpoint("chat/messages"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&SendChatMessageBody {
+            broadcaster_id,
+            sender_id,
+            message,
+            reply_parent_message_id,
+        })
+        .send()
+        .await?;
+
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(SendChatMessageError::RateLimited {
+            reset_at: rate_limit_reset(&response),
+        });
+    }
+
+    Ok(response
+        .error_for_status()
+        .map_err(|err| {
+            if err.status() == Some(StatusCode::UNAUTHORIZED) {
+                SendChatMessageError::Unauthorized(err)
+            } else {
+                SendChatMessageError::Other(err)
+            }
+        })?
+        .json::<HelixSendChatMessageResponse>()
+        .await?
+        .data
+        .0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockTwitch};
+
+    #[tokio::test]
+    async fn sends_chat_message() {
+        let twitch = MockTwitch::start().await;
+
+        let sent = send_chat_message(
+            &twitch.credentials(),
+            testing::USER_ACCESS_TOKEN,
+            "1000",
+            "2000",
+            "pong",
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(sent.is_sent);
+
+        let state = twitch.state();
+        assert_eq!(state.chat_messages.len(), 1);
+        assert_eq!(state.chat_messages[0].broadcaster_id, "1000");
+        assert_eq!(state.chat_messages[0].sender_id, "2000");
+        assert_eq!(state.chat_messages[0].message, "pong");
+    }
+}
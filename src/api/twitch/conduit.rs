@@ -0,0 +1,145 @@
+use crate::api::twitch::ApiClientCredentials;
+use crate::HTTP_CLIENT;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Conduit {
+    pub id: String,
+    pub shard_count: usize,
+}
+
+#[derive(Deserialize)]
+struct HelixConduitsResponse {
+    data: Vec<Conduit>,
+}
+
+#[derive(Deserialize)]
+struct HelixConduitResponse {
+    // we expect a list of size 1
+    data: (Conduit,),
+}
+
+#[derive(Serialize)]
+struct CreateConduitBody {
+    shard_count: usize,
+}
+
+#[derive(Serialize)]
+struct UpdateConduitBody<'a> {
+    id: &'a str,
+    shard_count: usize,
+}
+
+#[derive(Serialize)]
+struct UpdateShardsBody<'a> {
+    conduit_id: &'a str,
+    shards: &'a [ShardUpdate<'a>],
+}
+
+#[derive(Serialize)]
+pub struct ShardUpdate<'a> {
+    pub id: String,
+    pub transport: ShardTransport<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ShardTransport<'a> {
+    Websocket { session_id: &'a str },
+    Webhook { callback: &'a str, secret: &'a str },
+}
+
+#[derive(Deserialize)]
+struct HelixUpdateShardsResponse {
+    #[serde(default)]
+    errors: Vec<ShardError>,
+}
+
+/// A shard Twitch refused to update, e.g. because the websocket session does not exist (anymore).
+#[derive(Debug, Deserialize)]
+pub struct ShardError {
+    pub id: String,
+    pub message: String,
+    pub code: String,
+}
+
+/// All conduits of the client. Requires an app access token.
+pub async fn get_conduits(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+) -> Result<Vec<Conduit>, reqwest::Error> {
+    Ok(HTTP_CLIENT
+        .get(client_credentials.helix_endpoint("eventsub/conduits"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<HelixConduitsResponse>()
+        .await?
+        .data)
+}
+
+pub async fn create_conduit(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    shard_count: usize,
+) -> Result<Conduit, reqwest::Error> {
+    Ok(HTTP_CLIENT
+        .post(client_credentials.helix_endpoint("eventsub/conduits"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .json(&CreateConduitBody { shard_count })
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<HelixConduitResponse>()
+        .await?
+        .data
+        .0)
+}
+
+/// Change the number of shards of a conduit. Shards above the new count are removed.
+pub async fn update_conduit(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    conduit_id: &str,
+    shard_count: usize,
+) -> Result<Conduit, reqwest::Error> {
+    Ok(HTTP_CLIENT
+        .patch(client_credentials.helix_endpoint("eventsub/conduits"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .json(&UpdateConduitBody {
+            id: conduit_id,
+            shard_count,
+        })
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<HelixConduitResponse>()
+        .await?
+        .data
+        .0)
+}
+
+/// Point shards of a conduit to new transports. Shards that could not be updated are returned,
+/// the others were updated even if this list is not empty.
+pub async fn update_conduit_shards(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    conduit_id: &str,
+    shards: &[ShardUpdate<'_>],
+) -> Result<Vec<ShardError>, reqwest::Error> {
+    Ok(HTTP_CLIENT
+        .patch(client_credentials.helix_endpoint("eventsub/conduits/shards"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .json(&UpdateShardsBody { conduit_id, shards })
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<HelixUpdateShardsResponse>()
+        .await?
+        .errors)
+}
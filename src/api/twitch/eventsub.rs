@@ -0,0 +1,259 @@
+use crate::api::twitch::ApiClientCredentials;
+use crate::HTTP_CLIENT;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EventSubRequestError {
+    /// e.g. the subscription already exists (409), or a user did not authorize it (403)
+    #[error("Twitch responded with {status}: {message}")]
+    Status { status: StatusCode, message: String },
+    #[error("{0}")]
+    Other(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    /// e.g. `enabled`, or why Twitch disabled the subscription
+    pub status: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+    pub condition: BTreeMap<String, String>,
+    pub transport: SubscriptionTransport,
+}
+
+impl Subscription {
+    /// The channel the subscription's events happen in, if its condition names one
+    pub fn broadcaster_id(&self) -> Option<&str> {
+        ["broadcaster_user_id", "to_broadcaster_user_id"]
+            .iter()
+            .find_map(|key| self.condition.get(*key))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionTransport {
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conduit_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HelixSubscriptionsResponse {
+    data: Vec<Subscription>,
+    pagination: Pagination,
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HelixCreateSubscriptionResponse {
+    // we expect a list of size 1
+    data: (Subscription,),
+}
+
+#[derive(Serialize)]
+struct CreateSubscriptionBody<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    version: &'a str,
+    condition: &'a BTreeMap<String, String>,
+    transport: SubscriptionTransport,
+}
+
+#[derive(Deserialize)]
+struct HelixErrorResponse {
+    message: String,
+}
+
+async fn check_response(
+    response: reqwest::Response,
+) -> Result<reqwest::Response, EventSubRequestError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .json::<HelixErrorResponse>()
+        .await
+        .map(|body| body.message)
+        .unwrap_or_default();
+    Err(EventSubRequestError::Status { status, message })
+}
+
+/// All subscriptions of the client whose condition mentions `user_id`. Requires an app access
+/// token.
+pub async fn get_subscriptions_for_user(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    user_id: &str,
+) -> Result<Vec<Subscription>, EventSubRequestError> {
+    let mut subscriptions = vec![];
+    let mut cursor = None;
+    loop {
+        let mut request = HTTP_CLIENT
+            .get(client_credentials.helix_endpoint("eventsub/subscriptions"))
+            .query(&[("user_id", user_id)]);
+        if let Some(cursor) = &cursor {
+            request = request.query(&[("after", cursor)]);
+        }
+        let response = request
+            .header("Client-Id", &client_credentials.client_id)
+            .header("Authorization", format!("Bearer {}", app_access_token))
+            .send()
+            .await?;
+        let page = check_response(response)
+            .await?
+            .json::<HelixSubscriptionsResponse>()
+            .await?;
+        subscriptions.extend(page.data);
+        match page.pagination.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(subscriptions),
+        }
+    }
+}
+
+/// Subscribe to `kind` events matching `condition`, delivered through the conduit.
+pub async fn create_subscription(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    kind: &str,
+    version: &str,
+    condition: &BTreeMap<String, String>,
+    conduit_id: &str,
+) -> Result<Subscription, EventSubRequestError> {
+    let response = HTTP_CLIENT
+        .post(client_credentials.helix_endpoint("eventsub/subscriptions"))
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .json(&CreateSubscriptionBody {
+            kind,
+            version,
+            condition,
+            transport: SubscriptionTransport {
+                method: "conduit".to_owned(),
+                conduit_id: Some(conduit_id.to_owned()),
+            },
+        })
+        .send()
+        .await?;
+    Ok(check_response(response)
+        .await?
+        .json::<HelixCreateSubscriptionResponse>()
+        .await?
+        .data
+        .0)
+}
+
+pub async fn delete_subscription(
+    client_credentials: &ApiClientCredentials,
+    app_access_token: &str,
+    subscription_id: &str,
+) -> Result<(), EventSubRequestError> {
+    let response = HTTP_CLIENT
+        .delete(client_credentials.helix_endpoint("eventsub/subscriptions"))
+        .query(&[("id", subscription_id)])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", app_access_token))
+        .send()
+        .await?;
+    check_response(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockTwitch};
+
+    fn broadcaster_condition(broadcaster_id: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([("broadcaster_user_id".to_owned(), broadcaster_id.to_owned())])
+    }
+
+    #[tokio::test]
+    async fn subscriptions_lifecycle() {
+        let twitch = MockTwitch::start().await;
+        let credentials = twitch.credentials();
+        let token = testing::APP_ACCESS_TOKEN;
+        let condition = broadcaster_condition("1000");
+
+        let mut created = vec![];
+        for kind in ["channel.ban", "channel.unban", "stream.online"] {
+            created.push(
+                create_subscription(&credentials, token, kind, "1", &condition, "conduit-1")
+                    .await
+                    .unwrap(),
+            );
+        }
+        create_subscription(
+            &credentials,
+            token,
+            "channel.ban",
+            "1",
+            &broadcaster_condition("2000"),
+            "conduit-1",
+        )
+        .await
+        .unwrap();
+        assert_eq!(created[0].broadcaster_id(), Some("1000"));
+        assert_eq!(
+            created[0].transport.conduit_id.as_deref(),
+            Some("conduit-1")
+        );
+
+        // spans more than one page
+        let subscriptions = get_subscriptions_for_user(&credentials, token, "1000")
+            .await
+            .unwrap();
+        assert_eq!(subscriptions.len(), 3);
+
+        let duplicate = create_subscription(
+            &credentials,
+            token,
+            "channel.ban",
+            "1",
+            &condition,
+            "conduit-1",
+        )
+        .await;
+        assert!(matches!(
+            duplicate,
+            Err(EventSubRequestError::Status {
+                status: StatusCode::CONFLICT,
+                ..
+            })
+        ));
+
+        delete_subscription(&credentials, token, &created[0].id)
+            .await
+            .unwrap();
+        let subscriptions = get_subscriptions_for_user(&credentials, token, "1000")
+            .await
+            .unwrap();
+        assert!(subscriptions.iter().all(|s| s.id != created[0].id));
+        assert_eq!(subscriptions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn requests_need_a_valid_token() {
+        let twitch = MockTwitch::start().await;
+
+        let result = get_subscriptions_for_user(&twitch.credentials(), "invalid", "1000").await;
+        assert!(matches!(
+            result,
+            Err(EventSubRequestError::Status {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+    }
+}
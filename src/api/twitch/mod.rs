@@ -1,6 +1,13 @@
 pub mod auth;
+pub mod channel_points;
+pub mod chat;
+pub mod conduit;
+pub mod eventsub;
+pub mod moderation;
+pub mod stream;
 pub mod user;
 
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -8,4 +15,48 @@ pub struct ApiClientCredentials {
     pub client_id: String,
     pub client_secret: String,
     pub redirect_uri: String,
+    /// Base URL of the Helix API, only changed to test against a mock server
+    #[serde(default = "ApiClientCredentials::default_helix_url")]
+    pub helix_url: String,
+    /// Base URL of the Twitch OAuth2 endpoints
+    #[serde(default = "ApiClientCredentials::default_oauth2_url")]
+    pub oauth2_url: String,
+    /// Where bots connect to for receiving EventSub notifications through websockets
+    #[serde(default = "ApiClientCredentials::default_eventsub_websocket_url")]
+    pub eventsub_websocket_url: String,
+}
+
+impl ApiClientCredentials {
+    fn default_helix_url() -> String {
+        "https://api.twitch.tv/helix".to_owned()
+    }
+
+    fn default_oauth2_url() -> String {
+        "https://id.twitch.tv/oauth2".to_owned()
+    }
+
+    fn default_eventsub_websocket_url() -> String {
+        "wss://eventsub.wss.twitch.tv/ws".to_owned()
+    }
+
+    /// URL of a Helix endpoint, e.g. `helix_endpoint("users")`
+    pub fn helix_endpoint(&self, path: &str) -> String {
+        format!("{}/{path}", self.helix_url.trim_end_matches('/'))
+    }
+
+    /// URL of an OAuth2 endpoint, e.g. `oauth2_endpoint("token")`
+    pub fn oauth2_endpoint(&self, path: &str) -> String {
+        format!("{}/{path}", self.oauth2_url.trim_end_matches('/'))
+    }
+}
+
+/// When the rate limit bucket is refilled, according to the `Ratelimit-Reset` header of a
+/// Helix response.
+fn rate_limit_reset(response: &reqwest::Response) -> Option<DateTime<Utc>> {
+    response
+        .headers()
+        .get("Ratelimit-Reset")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
 }
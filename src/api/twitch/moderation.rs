@@ -0,0 +1,231 @@
+use crate::api::twitch::{rate_limit_reset, ApiClientCredentials};
+use crate::HTTP_CLIENT;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ModerationRequestError {
+    #[error("Rate limit exceeded")]
+    RateLimited { reset_at: Option<DateTime<Utc>> },
+    /// e.g. the user is already banned, or can't be banned since they are a moderator
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Access token is invalid: {0}")]
+    Unauthorized(String),
+    /// The token lacks the required scope, or its user is not a moderator in the channel
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Twitch responded with {status}: {message}")]
+    Status { status: StatusCode, message: String },
+    #[error("{0}")]
+    Other(#[from] reqwest::Error),
+}
+
+impl ModerationRequestError {
+    /// Whether the request might succeed if it is sent again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ModerationRequestError::RateLimited { .. } => true,
+            ModerationRequestError::Status { status, .. } => status.is_server_error(),
+            ModerationRequestError::Other(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HelixErrorResponse {
+    message: String,
+}
+
+async fn check_response(response: reqwest::Response) -> Result<(), ModerationRequestError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(ModerationRequestError::RateLimited {
+            reset_at: rate_limit_reset(&response),
+        });
+    }
+
+    let message = response
+        .json::<HelixErrorResponse>()
+        .await
+        .map(|body| body.message)
+        .unwrap_or_default();
+    Err(match status {
+        StatusCode::BAD_REQUEST => ModerationRequestError::BadRequest(message),
+        StatusCode::UNAUTHORIZED => ModerationRequestError::Unauthorized(message),
+        StatusCode::FORBIDDEN => ModerationRequestError::Forbidden(message),
+        StatusCode::NOT_FOUND => ModerationRequestError::NotFound(message),
+        status => ModerationRequestError::Status { status, message },
+    })
+}
+
+#[derive(Serialize)]
+struct BanUserBody<'a> {
+    data: BanUserData<'a>,
+}
+
+#[derive(Serialize)]
+struct BanUserData<'a> {
+    user_id: &'a str,
+    /// Timeout length in seconds. The user is banned permanently if this is absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u32>,
+    reason: &'a str,
+}
+
+/// Ban `user_id` from the chat of `broadcaster_id`, or time them out if `duration_seconds` is given.
+pub async fn ban_user(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    broadcaster_id: &str,
+    moderator_id: &str,
+    user_id: &str,
+    duration_seconds: Option<u32>,
+    reason: &str,
+) -> Result<(), ModerationRequestError> {
+    let response = HTTP_CLIENT
+        .post(client_credentials.helix_endpoint("moderation/bans"))
+        .query(&[
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&BanUserBody {
+            data: BanUserData {
+                user_id,
+                duration: duration_seconds,
+                reason,
+            },
+        })
+        .send()
+        .await?;
+    check_response(response).await
+}
+
+/// Lift a ban or timeout of `user_id` in the chat of `broadcaster_id`.
+pub async fn unban_user(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    broadcaster_id: &str,
+    moderator_id: &str,
+    user_id: &str,
+) -> Result<(), ModerationRequestError> {
+    let response = HTTP_CLIENT
+        .delete(client_credentials.helix_endpoint("moderation/bans"))
+        .query(&[
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+            ("user_id", user_id),
+        ])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+    check_response(response).await
+}
+
+pub async fn delete_chat_message(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    broadcaster_id: &str,
+    moderator_id: &str,
+    message_id: &str,
+) -> Result<(), ModerationRequestError> {
+    let response = HTTP_CLIENT
+        .delete(client_credentials.helix_endpoint("moderation/chat"))
+        .query(&[
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+            ("message_id", message_id),
+        ])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+    check_response(response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockTwitch};
+
+    #[tokio::test]
+    async fn ban_timeout_unban_and_delete() {
+        let twitch = MockTwitch::start().await;
+        let credentials = twitch.credentials();
+        let token = testing::USER_ACCESS_TOKEN;
+
+        ban_user(&credentials, token, "1000", "2000", "3000", None, "spam")
+            .await
+            .unwrap();
+        ban_user(
+            &credentials,
+            token,
+            "1000",
+            "2000",
+            "3001",
+            Some(600),
+            "caps",
+        )
+        .await
+        .unwrap();
+        {
+            let state = twitch.state();
+            assert_eq!(state.bans.len(), 2);
+            assert_eq!(state.bans[0].broadcaster_id, "1000");
+            assert_eq!(state.bans[0].user_id, "3000");
+            assert_eq!(state.bans[0].moderator_id, "2000");
+            assert_eq!(state.bans[0].duration, None);
+            assert_eq!(state.bans[1].duration, Some(600));
+            assert_eq!(state.bans[1].reason, "caps");
+        }
+
+        let banned_again =
+            ban_user(&credentials, token, "1000", "2000", "3000", None, "spam").await;
+        assert!(matches!(
+            banned_again,
+            Err(ModerationRequestError::BadRequest(_))
+        ));
+
+        unban_user(&credentials, token, "1000", "2000", "3000")
+            .await
+            .unwrap();
+        delete_chat_message(&credentials, token, "1000", "2000", "message-1")
+            .await
+            .unwrap();
+        let state = twitch.state();
+        assert_eq!(state.unbans, [("1000".to_owned(), "3000".to_owned())]);
+        assert_eq!(state.deleted_messages, ["message-1".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn invalid_token_is_unauthorized() {
+        let twitch = MockTwitch::start().await;
+
+        let result = ban_user(
+            &twitch.credentials(),
+            "invalid",
+            "1000",
+            "2000",
+            "3000",
+            None,
+            "",
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(ModerationRequestError::Unauthorized(_))
+        ));
+        assert!(!result.unwrap_err().is_transient());
+    }
+}
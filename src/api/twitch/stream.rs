@@ -0,0 +1,40 @@
+use crate::api::twitch::ApiClientCredentials;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct HelixGetStreamsResponse {
+    data: Vec<Stream>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stream {
+    pub id: String,
+    pub user_id: String,
+    pub game_id: String,
+    pub game_name: String,
+    pub title: String,
+    pub viewer_count: u64,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Get the current stream of a user, or `None` if they are not live.
+pub async fn get_stream(
+    client_credentials: &ApiClientCredentials,
+    access_token: &str,
+    user_id: &str,
+) -> Result<Option<Stream>, reqwest::Error> {
+    Ok(crate::HTTP_CLIENT
+        .get(client_credentials.helix_endpoint("streams"))
+        .query(&[("user_id", user_id)])
+        .header("Client-Id", &client_credentials.client_id)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<HelixGetStreamsResponse>()
+        .await?
+        .data
+        .into_iter()
+        .next())
+}
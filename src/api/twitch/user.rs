@@ -28,7 +28,7 @@ pub async fn get_user_for_authorization(
     access_token: &str,
 ) -> Result<UserDetails, reqwest::Error> {
     Ok(crate::HTTP_CLIENT
-        .get("https://api.twitch.tv/helix/users")
+        .get(client_credentials.helix_endpoint("users"))
         .header("Client-Id", &client_credentials.client_id)
         .header("Authorization", format!("Bearer {}", access_token))
         .send()
@@ -0,0 +1,170 @@
+use crate::models::banphrase::{self, Operator};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Upper bound for the compiled size of banphrase regexes, to keep a single banphrase
+/// from using excessive amounts of memory.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+enum Matcher {
+    Contains(String),
+    Exact(String),
+    Regex(Regex),
+}
+
+/// A banphrase, prepared for being matched against chat messages.
+pub struct CompiledBanphrase {
+    pub model: banphrase::Model,
+    matcher: Matcher,
+}
+
+impl CompiledBanphrase {
+    pub fn compile(model: banphrase::Model) -> Result<CompiledBanphrase, regex::Error> {
+        let matcher = match model.operator {
+            Operator::Contains => Matcher::Contains(normalize(&model.phrase, &model).into_owned()),
+            Operator::Exact => Matcher::Exact(normalize(model.phrase.trim(), &model).into_owned()),
+            Operator::Regex => Matcher::Regex(build_regex(&model.phrase, model.case_sensitive)?),
+        };
+        Ok(CompiledBanphrase { model, matcher })
+    }
+
+    /// Check that `pattern` can be used as the phrase of a regex banphrase. The compiled
+    /// size depends on the case sensitivity, so this uses the same settings as `compile`.
+    pub fn validate_regex(pattern: &str, case_sensitive: bool) -> Result<(), regex::Error> {
+        build_regex(pattern, case_sensitive).map(|_| ())
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = normalize(text, &self.model);
+        match &self.matcher {
+            Matcher::Contains(phrase) => text.contains(phrase.as_str()),
+            Matcher::Exact(phrase) => text.trim() == phrase,
+            Matcher::Regex(regex) => regex.is_match(&text),
+        }
+    }
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// Apply the normalizations configured for the banphrase. Case is left untouched
+/// for regexes, since they are compiled to be case-insensitive instead.
+fn normalize<'a>(text: &'a str, banphrase: &banphrase::Model) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    if banphrase.remove_accents {
+        text = Cow::Owned(text.nfd().filter(|c| !is_combining_mark(*c)).collect());
+    }
+    if !banphrase.case_sensitive && banphrase.operator != Operator::Regex {
+        text = Cow::Owned(text.to_lowercase());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::commands::Permission;
+    use banphrase::Action;
+
+    fn banphrase(phrase: &str, operator: Operator) -> banphrase::Model {
+        banphrase::Model {
+            id: 1,
+            broadcaster_id: "1".to_owned(),
+            name: "test".to_owned(),
+            phrase: phrase.to_owned(),
+            operator,
+            action: Action::Delete,
+            timeout_seconds: 300,
+            case_sensitive: false,
+            remove_accents: false,
+            exempt_permission: Permission::Moderator,
+            sub_immunity: false,
+            enabled: true,
+        }
+    }
+
+    fn compile(model: banphrase::Model) -> CompiledBanphrase {
+        CompiledBanphrase::compile(model).unwrap()
+    }
+
+    #[test]
+    fn contains() {
+        let phrase = compile(banphrase("bad word", Operator::Contains));
+        assert!(phrase.is_match("this is a BAD Word here"));
+        assert!(!phrase.is_match("bad  word"));
+        assert!(!phrase.is_match("badword"));
+    }
+
+    #[test]
+    fn exact_ignores_surrounding_whitespace() {
+        let phrase = compile(banphrase(" bad word ", Operator::Exact));
+        assert!(phrase.is_match("bad word"));
+        assert!(phrase.is_match("  Bad Word\t"));
+        assert!(!phrase.is_match("a bad word"));
+        assert!(!phrase.is_match("bad  word"));
+    }
+
+    #[test]
+    fn regex() {
+        let phrase = compile(banphrase(r"^b[a4]d\b", Operator::Regex));
+        assert!(phrase.is_match("B4D idea"));
+        assert!(phrase.is_match("bad"));
+        assert!(!phrase.is_match("a bad idea"));
+        assert!(!phrase.is_match("badger"));
+    }
+
+    #[test]
+    fn case_sensitive() {
+        for operator in [Operator::Contains, Operator::Exact, Operator::Regex] {
+            let phrase = compile(banphrase::Model {
+                case_sensitive: true,
+                ..banphrase("Bad", operator)
+            });
+            assert!(phrase.is_match("Bad"), "{operator:?}");
+            assert!(!phrase.is_match("bad"), "{operator:?}");
+        }
+    }
+
+    #[test]
+    fn removes_accents() {
+        for operator in [Operator::Contains, Operator::Exact, Operator::Regex] {
+            let phrase = compile(banphrase::Model {
+                remove_accents: true,
+                ..banphrase("cafe", operator)
+            });
+            assert!(phrase.is_match("café"), "{operator:?}");
+            assert!(phrase.is_match("CAFÉ"), "{operator:?}");
+            // decomposed, i.e. "e" followed by a combining acute accent
+            assert!(phrase.is_match("cafe\u{301}"), "{operator:?}");
+
+            let phrase = compile(banphrase("cafe", operator));
+            assert!(!phrase.is_match("café"), "{operator:?}");
+        }
+    }
+
+    #[test]
+    fn accents_in_the_phrase_are_removed_too() {
+        let phrase = compile(banphrase::Model {
+            remove_accents: true,
+            ..banphrase("café", Operator::Contains)
+        });
+        assert!(phrase.is_match("cafe"));
+        assert!(phrase.is_match("café"));
+    }
+
+    #[test]
+    fn validate_regex() {
+        assert!(CompiledBanphrase::validate_regex(r"^b[a4]d\b", false).is_ok());
+        assert!(CompiledBanphrase::validate_regex("(unclosed", false).is_err());
+        assert!(CompiledBanphrase::validate_regex(r"\w{1000}", true).is_err());
+        // compiles to fewer states case-insensitively, so the case sensitivity matters
+        assert!(CompiledBanphrase::validate_regex(r"\p{Lu}{40}", true).is_err());
+        assert!(CompiledBanphrase::validate_regex(r"\p{Lu}{40}", false).is_ok());
+    }
+}
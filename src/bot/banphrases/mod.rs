@@ -0,0 +1,274 @@
+mod matcher;
+
+pub use matcher::CompiledBanphrase;
+
+use crate::bot::commands::{is_subscriber, Permission};
+use crate::bot::moderation::ModerationError;
+use crate::bot::BotContext;
+use crate::models::banphrase::{self, Action};
+use crate::models::moderation_log::Source;
+use dashmap::DashMap;
+use sea_orm::ConnectionTrait;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+/// Banphrases are cached for this long before they are reloaded from the database,
+/// so changes made through the web API take effect after at most this time.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The enabled banphrases of a channel, ordered from most to least severe.
+pub struct BanphraseSet {
+    phrases: Vec<CompiledBanphrase>,
+}
+
+impl BanphraseSet {
+    pub async fn load(
+        broadcaster_id: &str,
+        db: &impl ConnectionTrait,
+    ) -> anyhow::Result<BanphraseSet> {
+        let mut phrases = banphrase::find_enabled_for_channel(broadcaster_id, db)
+            .await?
+            .into_iter()
+            .filter_map(|model| {
+                let id = model.id;
+                CompiledBanphrase::compile(model)
+                    .map_err(|e| tracing::warn!("Skipping banphrase {id} with invalid regex: {e}"))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        phrases.sort_by(|a, b| {
+            b.model
+                .action
+                .cmp(&a.model.action)
+                .then(b.model.timeout_seconds.cmp(&a.model.timeout_seconds))
+        });
+        Ok(BanphraseSet { phrases })
+    }
+
+    /// The most severe banphrase matching `text`, regardless of any exemptions.
+    pub fn find_match(&self, text: &str) -> Option<&banphrase::Model> {
+        self.phrases
+            .iter()
+            .find(|phrase| phrase.is_match(text))
+            .map(|phrase| &phrase.model)
+    }
+
+    /// The most severe banphrase matching the chat message that the chatter is not exempt from.
+    pub fn check_message(
+        &self,
+        message: &ChannelChatMessageV1Payload,
+    ) -> Option<&banphrase::Model> {
+        let permission = Permission::of_chatter(message);
+        let subscriber = is_subscriber(message);
+        self.phrases
+            .iter()
+            .filter(|phrase| permission < phrase.model.exempt_permission)
+            .filter(|phrase| !(phrase.model.sub_immunity && subscriber))
+            .find(|phrase| phrase.is_match(&message.message.text))
+            .map(|phrase| &phrase.model)
+    }
+}
+
+/// Caches the banphrases of channels, reloading them from the database after [`RELOAD_INTERVAL`].
+pub struct BanphraseCache {
+    entries: DashMap<String, (Instant, Arc<BanphraseSet>)>,
+    reload_interval: Duration,
+}
+
+impl Default for BanphraseCache {
+    fn default() -> Self {
+        Self::with_reload_interval(RELOAD_INTERVAL)
+    }
+}
+
+impl BanphraseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_reload_interval(reload_interval: Duration) -> Self {
+        BanphraseCache {
+            entries: DashMap::new(),
+            reload_interval,
+        }
+    }
+
+    /// The banphrases of a channel. If reloading them fails, the banphrases loaded last are kept
+    /// until the next reload, so a database hiccup does not disable them.
+    pub async fn get(
+        &self,
+        broadcaster_id: &str,
+        db: &impl ConnectionTrait,
+    ) -> anyhow::Result<Arc<BanphraseSet>> {
+        let cached = self
+            .entries
+            .get(broadcaster_id)
+            .map(|entry| entry.value().clone());
+        if let Some((loaded_at, banphrases)) = &cached {
+            if loaded_at.elapsed() < self.reload_interval {
+                return Ok(banphrases.clone());
+            }
+        }
+
+        let banphrases = match BanphraseSet::load(broadcaster_id, db).await {
+            Ok(banphrases) => Arc::new(banphrases),
+            Err(e) => {
+                let Some((_, banphrases)) = cached else {
+                    return Err(e);
+                };
+                tracing::error!(
+                    "Failed to reload banphrases of channel {broadcaster_id}, keeping the previous ones: {e:#}"
+                );
+                banphrases
+            }
+        };
+        self.entries.insert(
+            broadcaster_id.to_owned(),
+            (Instant::now(), banphrases.clone()),
+        );
+        Ok(banphrases)
+    }
+}
+
+/// Checks all chat messages of the bot's channel against its banphrases and punishes
+/// the chatters of matching messages, until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let cache = BanphraseCache::new();
+
+    loop {
+        let message = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            message = chat_messages.recv() => message,
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Banphrase checker lagged behind, skipped {skipped} chat messages");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if message.chatter_user_id == bot.bot_user_id {
+            continue;
+        }
+
+        let banphrases = match cache.get(bot.broadcaster_user_id.as_str(), bot.db).await {
+            Ok(banphrases) => banphrases,
+            Err(e) => {
+                tracing::error!("Failed to load banphrases: {e:#}");
+                continue;
+            }
+        };
+        let Some(banphrase) = banphrases.check_message(&message).cloned() else {
+            continue;
+        };
+        tracing::info!(
+            "Message {} by {} matched banphrase {} ({}), action: {:?}",
+            message.message_id,
+            message.chatter_user_login,
+            banphrase.id,
+            banphrase.name,
+            banphrase.action
+        );
+
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            if let Err(e) = punish(&bot, &message, &banphrase).await {
+                tracing::error!(
+                    "Failed to punish {} for banphrase {}: {e:#}",
+                    message.chatter_user_login,
+                    banphrase.id
+                );
+            }
+        });
+    }
+}
+
+async fn punish(
+    bot: &BotContext,
+    message: &ChannelChatMessageV1Payload,
+    banphrase: &banphrase::Model,
+) -> Result<(), ModerationError> {
+    let moderation = bot.moderation(Source::Filter, bot.bot_user_id.as_str());
+    let reason = format!("Matched banphrase: {}", banphrase.name);
+    match banphrase.action {
+        Action::Delete => {
+            moderation
+                .delete_message(
+                    message.message_id.as_str(),
+                    Some(message.chatter_user_id.as_str()),
+                )
+                .await
+        }
+        Action::Timeout => {
+            let duration = Duration::from_secs(banphrase.timeout_seconds.max(1) as u64);
+            moderation
+                .timeout(message.chatter_user_id.as_str(), duration, &reason)
+                .await
+        }
+        Action::Ban => {
+            moderation
+                .ban(message.chatter_user_id.as_str(), &reason)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::banphrase::Operator;
+    use sea_orm::{DatabaseBackend, DbErr, MockDatabase};
+
+    fn banphrase() -> banphrase::Model {
+        banphrase::Model {
+            id: 1,
+            broadcaster_id: "1".to_owned(),
+            name: "test".to_owned(),
+            phrase: "bad".to_owned(),
+            operator: Operator::Contains,
+            action: Action::Delete,
+            timeout_seconds: 300,
+            case_sensitive: false,
+            remove_accents: false,
+            exempt_permission: Permission::Moderator,
+            sub_immunity: false,
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_previous_banphrases_if_reload_fails() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[banphrase()]])
+            .append_query_errors([
+                DbErr::Custom("connection lost".to_owned()),
+                DbErr::Custom("connection lost".to_owned()),
+            ])
+            .into_connection();
+        // reload on every lookup
+        let cache = BanphraseCache::with_reload_interval(Duration::ZERO);
+        let loaded = cache.get("1", &db).await.unwrap();
+        assert!(loaded.find_match("bad").is_some());
+
+        let reloaded = cache.get("1", &db).await.unwrap();
+        assert!(Arc::ptr_eq(&loaded, &reloaded));
+        // still kept while reloading keeps failing
+        assert!(Arc::ptr_eq(&loaded, &cache.get("1", &db).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn fails_if_nothing_was_loaded_before() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_errors([DbErr::Custom("connection lost".to_owned())])
+            .into_connection();
+        assert!(BanphraseCache::new().get("1", &db).await.is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::api;
+use crate::api::twitch::chat::SendChatMessageError;
+use crate::bot::commands::Permission;
+use crate::bot::BotContext;
+use crate::models::special_authorization_state::Kind;
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+/// Twitch rejects chat messages longer than this many characters
+const MAX_MESSAGE_LENGTH: usize = 500;
+/// Messages queued beyond this are dropped
+const QUEUE_CAPACITY: usize = 500;
+/// Needed by the bot authorization to send chat messages through Helix
+const SEND_CHAT_MESSAGE_SCOPE: &str = "user:write:chat";
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+/// Messages per [`RATE_LIMIT_WINDOW`] if the bot is a moderator or the broadcaster of the channel
+const MODERATOR_RATE_LIMIT: usize = 100;
+/// Messages per [`RATE_LIMIT_WINDOW`] if the bot is a regular chatter in the channel
+const USER_RATE_LIMIT: usize = 20;
+/// Regular chatters can additionally only send one message per second
+const USER_MESSAGE_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to back off if Twitch tells us we are rate limited, but not until when
+const DEFAULT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct OutgoingMessage {
+    text: String,
+    reply_parent_message_id: Option<String>,
+}
+
+/// Handle to queue chat messages to be sent to the channel of a bot.
+#[derive(Clone)]
+pub struct ChatSender {
+    queue: mpsc::Sender<OutgoingMessage>,
+}
+
+/// Receiving end of the [`ChatSender`], consumed by [`run`].
+pub struct ChatQueue {
+    receiver: mpsc::Receiver<OutgoingMessage>,
+}
+
+pub fn queue() -> (ChatSender, ChatQueue) {
+    let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+    (ChatSender { queue: sender }, ChatQueue { receiver })
+}
+
+impl ChatSender {
+    pub fn send(&self, text: impl Into<String>) {
+        self.enqueue(OutgoingMessage {
+            text: text.into(),
+            reply_parent_message_id: None,
+        });
+    }
+
+    pub fn reply(&self, parent_message_id: impl Into<String>, text: impl Into<String>) {
+        self.enqueue(OutgoingMessage {
+            text: text.into(),
+            reply_parent_message_id: Some(parent_message_id.into()),
+        });
+    }
+
+    fn enqueue(&self, message: OutgoingMessage) {
+        match self.queue.try_send(message) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(message)) => {
+                tracing::warn!("Chat queue is full, dropping message: {}", message.text);
+            }
+            Err(mpsc::error::TrySendError::Closed(message)) => {
+                tracing::warn!(
+                    "Chat sender has shut down, dropping message: {}",
+                    message.text
+                );
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct RateLimiter {
+    /// Send times of the messages within the last [`RATE_LIMIT_WINDOW`], oldest first
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    /// How long to wait until the next message may be sent.
+    fn delay(&mut self, is_moderator: bool, now: Instant) -> Duration {
+        while let Some(oldest) = self.sent.front() {
+            if now.duration_since(*oldest) < RATE_LIMIT_WINDOW {
+                break;
+            }
+            self.sent.pop_front();
+        }
+
+        let limit = if is_moderator {
+            MODERATOR_RATE_LIMIT
+        } else {
+            USER_RATE_LIMIT
+        };
+        let mut delay = Duration::ZERO;
+        if self.sent.len() >= limit {
+            let blocking = self.sent[self.sent.len() - limit];
+            delay = (blocking + RATE_LIMIT_WINDOW).saturating_duration_since(now);
+        }
+        if !is_moderator {
+            if let Some(last) = self.sent.back() {
+                delay = delay.max((*last + USER_MESSAGE_INTERVAL).saturating_duration_since(now));
+            }
+        }
+        delay
+    }
+
+    fn record(&mut self, at: Instant) {
+        self.sent.push_back(at);
+    }
+}
+
+fn truncate(text: String) -> String {
+    if text.chars().count() <= MAX_MESSAGE_LENGTH {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(MAX_MESSAGE_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Sends the queued messages of a bot to its channel, respecting the Twitch rate limits,
+/// until `shutdown_signal` fires.
+///
+/// The bot's own chat messages are watched to find out whether it is a moderator in
+/// the channel, which entitles it to higher rate limits.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut queue: ChatQueue,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let mut is_moderator = bot.bot_user_id == bot.broadcaster_user_id;
+    let mut watching_chat = true;
+    let mut rate_limiter = RateLimiter::default();
+    let mut pending: Option<OutgoingMessage> = None;
+
+    loop {
+        let message = match pending.take() {
+            Some(message) => message,
+            None => tokio::select! {
+                _ = shutdown_signal.cancelled() => break,
+                message = queue.receiver.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                chat_message = chat_messages.recv(), if watching_chat => {
+                    match chat_message {
+                        Ok(chat_message) if chat_message.chatter_user_id == bot.bot_user_id => {
+                            let permission = Permission::of_chatter(&chat_message);
+                            is_moderator = permission >= Permission::Moderator;
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => watching_chat = false,
+                    }
+                    continue;
+                }
+            },
+        };
+
+        let delay = rate_limiter.delay(is_moderator, Instant::now());
+        if !delay.is_zero() {
+            tokio::select! {
+                _ = shutdown_signal.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+        rate_limiter.record(Instant::now());
+
+        let access_token = match bot.bot_authorization().await {
+            Ok(authorization) if authorization.has_scope(Kind::Bot, SEND_CHAT_MESSAGE_SCOPE) => {
+                authorization.twitch_access_token
+            }
+            Ok(_) => {
+                tracing::error!(
+                    "Can't send chat message `{}`: bot account {} needs to authorize again",
+                    message.text,
+                    bot.bot_user_id
+                );
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Can't send chat message `{}`: {e:#}", message.text);
+                continue;
+            }
+        };
+        let result = api::twitch::chat::send_chat_message(
+            &bot.config.twitch_api,
+            &access_token,
+            bot.broadcaster_user_id.as_str(),
+            bot.bot_user_id.as_str(),
+            &truncate(message.text.clone()),
+            message.reply_parent_message_id.as_deref(),
+        )
+        .await;
+        match result {
+            Ok(sent) => {
+                if let Some(reason) = sent.drop_reason.filter(|_| !sent.is_sent) {
+                    tracing::warn!(
+                        "Twitch dropped chat message `{}`: {} ({})",
+                        message.text,
+                        reason.message,
+                        reason.code
+                    );
+                }
+            }
+            Err(SendChatMessageError::RateLimited { reset_at }) => {
+                let backoff = reset_at
+                    .and_then(|reset_at| (reset_at - Utc::now()).to_std().ok())
+                    .unwrap_or(DEFAULT_RATE_LIMIT_BACKOFF);
+                tracing::warn!("Rate limited while sending chat message, retrying in {backoff:?}");
+                pending = Some(message);
+                tokio::select! {
+                    _ = shutdown_signal.cancelled() => break,
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to send chat message `{}`: {e:#}", message.text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send `count` messages as fast as the limiter allows, returning when the last one was sent.
+    fn send(
+        limiter: &mut RateLimiter,
+        is_moderator: bool,
+        count: usize,
+        mut now: Instant,
+    ) -> Instant {
+        for _ in 0..count {
+            now += limiter.delay(is_moderator, now);
+            limiter.record(now);
+        }
+        now
+    }
+
+    #[test]
+    fn users_send_one_message_per_second() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        assert_eq!(limiter.delay(false, start), Duration::ZERO);
+        limiter.record(start);
+        assert_eq!(limiter.delay(false, start), USER_MESSAGE_INTERVAL);
+        assert_eq!(
+            limiter.delay(false, start + Duration::from_millis(400)),
+            Duration::from_millis(600)
+        );
+        assert_eq!(
+            limiter.delay(false, start + USER_MESSAGE_INTERVAL),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn user_bucket_refills_after_window() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let last = send(&mut limiter, false, USER_RATE_LIMIT, start);
+        assert_eq!(
+            last,
+            start + USER_MESSAGE_INTERVAL * (USER_RATE_LIMIT as u32 - 1)
+        );
+
+        // the next message has to wait until the first one left the window
+        let next = last + USER_MESSAGE_INTERVAL;
+        assert_eq!(limiter.delay(false, next), start + RATE_LIMIT_WINDOW - next);
+        assert_eq!(
+            limiter.delay(false, start + RATE_LIMIT_WINDOW),
+            Duration::ZERO
+        );
+        limiter.record(start + RATE_LIMIT_WINDOW);
+        assert_eq!(limiter.sent.len(), USER_RATE_LIMIT);
+    }
+
+    #[test]
+    fn moderators_send_bursts() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        assert_eq!(send(&mut limiter, true, MODERATOR_RATE_LIMIT, start), start);
+
+        assert_eq!(limiter.delay(true, start), RATE_LIMIT_WINDOW);
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            limiter.delay(true, later),
+            RATE_LIMIT_WINDOW - Duration::from_secs(10)
+        );
+        // the whole bucket is refilled at once, since all messages were sent at the same time
+        let refilled = start + RATE_LIMIT_WINDOW;
+        assert_eq!(
+            send(&mut limiter, true, MODERATOR_RATE_LIMIT, refilled),
+            refilled
+        );
+    }
+
+    #[test]
+    fn truncates_long_messages() {
+        assert_eq!(truncate("short".to_owned()), "short");
+        let truncated = truncate("ä".repeat(MAX_MESSAGE_LENGTH + 1));
+        assert_eq!(truncated.chars().count(), MAX_MESSAGE_LENGTH);
+        assert!(truncated.ends_with('…'));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::bot::commands::variables::format_duration;
+use crate::bot::commands::{Command, CommandContext, CommandHandler, CommandRegistry, Permission};
+use crate::models::{stream_session, user, user_channel_points};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(super) fn register(registry: &mut CommandRegistry) -> anyhow::Result<()> {
+    registry.register(Command {
+        name: "ping".to_owned(),
+        aliases: vec!["pajbot".to_owned()],
+        min_permission: Permission::Everyone,
+        global_cooldown: Duration::from_secs(5),
+        user_cooldown: Duration::from_secs(15),
+        handler: Arc::new(Ping),
+    })?;
+    registry.register(Command {
+        name: "points".to_owned(),
+        aliases: vec![],
+        min_permission: Permission::Everyone,
+        global_cooldown: Duration::from_secs(2),
+        user_cooldown: Duration::from_secs(10),
+        handler: Arc::new(Points),
+    })?;
+    let stream_command = |name: &str, handler: Arc<dyn CommandHandler>| Command {
+        name: name.to_owned(),
+        aliases: vec![],
+        min_permission: Permission::Everyone,
+        global_cooldown: Duration::from_secs(5),
+        user_cooldown: Duration::from_secs(15),
+        handler,
+    };
+    registry.register(stream_command("uptime", Arc::new(Uptime)))?;
+    registry.register(stream_command("downtime", Arc::new(Downtime)))?;
+    registry.register(stream_command("laststream", Arc::new(LastStream)))?;
+    Ok(())
+}
+
+struct Ping;
+
+#[async_trait]
+impl CommandHandler for Ping {
+    async fn execute(&self, _ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        Ok(Some(format!(
+            "pajbot3 v{} is up and running",
+            env!("CARGO_PKG_VERSION")
+        )))
+    }
+}
+
+/// `!points [login]`: the points of the chatter, or of another user
+struct Points;
+
+#[async_trait]
+impl CommandHandler for Points {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let broadcaster_id = ctx.bot.broadcaster_user_id.as_str();
+        let (user_id, display_name) = match ctx.args.split_whitespace().next() {
+            Some(login) => {
+                let login = login.trim_start_matches('@');
+                match user::find_by_login(login, ctx.bot.db).await? {
+                    Some(user) => (user.id, user.display_name),
+                    None => return Ok(Some(format!("{login} has no points"))),
+                }
+            }
+            None => (
+                ctx.message.chatter_user_id.to_string(),
+                ctx.message.chatter_user_name.to_string(),
+            ),
+        };
+        let points = user_channel_points::get_points(broadcaster_id, &user_id, ctx.bot.db).await?;
+        let rank = user_channel_points::rank(broadcaster_id, points, ctx.bot.db).await?;
+        Ok(Some(format!(
+            "{display_name} has {points} points (rank {rank})"
+        )))
+    }
+}
+
+/// `!uptime`: how long the stream has been live
+struct Uptime;
+
+#[async_trait]
+impl CommandHandler for Uptime {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let started_at = ctx
+            .bot
+            .stream_session
+            .borrow()
+            .as_ref()
+            .map(|session| session.started_at);
+        Ok(Some(match started_at {
+            Some(started_at) => format!(
+                "The stream has been live for {}",
+                format_duration(Utc::now() - started_at)
+            ),
+            None => "The stream is offline".to_owned(),
+        }))
+    }
+}
+
+/// `!downtime`: how long the stream has been offline
+struct Downtime;
+
+#[async_trait]
+impl CommandHandler for Downtime {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        if ctx.bot.is_live() {
+            return Ok(Some("The stream is live".to_owned()));
+        }
+        let last =
+            stream_session::find_last_ended(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?;
+        Ok(Some(match last.and_then(|session| session.ended_at) {
+            Some(ended_at) => format!(
+                "The stream has been offline for {}",
+                format_duration(Utc::now() - ended_at)
+            ),
+            None => "The stream has not been live since the bot joined".to_owned(),
+        }))
+    }
+}
+
+/// `!laststream`: when the last stream took place, with its final title and category
+struct LastStream;
+
+#[async_trait]
+impl CommandHandler for LastStream {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let Some(session) =
+            stream_session::find_last_ended(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?
+        else {
+            return Ok(Some(
+                "The stream has not been live since the bot joined".to_owned(),
+            ));
+        };
+        let ended_at = session.ended_at.unwrap_or_else(Utc::now);
+        let mut response = format!(
+            "The last stream started {} ago and lasted {}",
+            format_duration(Utc::now() - session.started_at),
+            format_duration(ended_at - session.started_at)
+        );
+        if let Some(change) = stream_session::latest_change(session.id, ctx.bot.db).await? {
+            response.push_str(&format!(
+                ": \"{}\" in {}",
+                change.title, change.category_name
+            ));
+        }
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use twitch_types::UserId;
+
+/// Above this many tracked cooldowns, expired entries are pruned on the next check.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Tracks when commands become usable again, both channel-wide and per user.
+#[derive(Default)]
+pub struct Cooldowns {
+    global: HashMap<String, Instant>,
+    per_user: HashMap<(String, UserId), Instant>,
+}
+
+impl Cooldowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether the command identified by `key` may be run by `user_id` right now.
+    /// If so, both cooldowns are started and `true` is returned.
+    pub fn try_start(
+        &mut self,
+        key: &str,
+        user_id: &UserId,
+        global_cooldown: Duration,
+        user_cooldown: Duration,
+    ) -> bool {
+        self.try_start_at(key, user_id, global_cooldown, user_cooldown, Instant::now())
+    }
+
+    fn try_start_at(
+        &mut self,
+        key: &str,
+        user_id: &UserId,
+        global_cooldown: Duration,
+        user_cooldown: Duration,
+        now: Instant,
+    ) -> bool {
+        self.prune(now);
+
+        let user_key = (key.to_owned(), user_id.clone());
+        let global_ready = self.global.get(key).is_none_or(|ready| *ready <= now);
+        let user_ready = self
+            .per_user
+            .get(&user_key)
+            .is_none_or(|ready| *ready <= now);
+        if !(global_ready && user_ready) {
+            return false;
+        }
+
+        if !global_cooldown.is_zero() {
+            self.global.insert(key.to_owned(), now + global_cooldown);
+        }
+        if !user_cooldown.is_zero() {
+            self.per_user.insert(user_key, now + user_cooldown);
+        }
+        true
+    }
+
+    fn prune(&mut self, now: Instant) {
+        if self.global.len() > PRUNE_THRESHOLD {
+            self.global.retain(|_, ready| *ready > now);
+        }
+        if self.per_user.len() > PRUNE_THRESHOLD {
+            self.per_user.retain(|_, ready| *ready > now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBAL: Duration = Duration::from_secs(5);
+    const USER: Duration = Duration::from_secs(15);
+
+    #[test]
+    fn global_cooldown_applies_to_everyone() {
+        let mut cooldowns = Cooldowns::new();
+        let (alice, bob) = (UserId::from("1"), UserId::from("2"));
+        let start = Instant::now();
+
+        assert!(cooldowns.try_start_at("ping", &alice, GLOBAL, Duration::ZERO, start));
+        assert!(!cooldowns.try_start_at("ping", &bob, GLOBAL, Duration::ZERO, start));
+        assert!(!cooldowns.try_start_at("ping", &bob, GLOBAL, Duration::ZERO, start + GLOBAL / 2));
+        assert!(cooldowns.try_start_at("ping", &bob, GLOBAL, Duration::ZERO, start + GLOBAL));
+    }
+
+    #[test]
+    fn user_cooldown_outlasts_global_cooldown() {
+        let mut cooldowns = Cooldowns::new();
+        let (alice, bob) = (UserId::from("1"), UserId::from("2"));
+        let start = Instant::now();
+
+        assert!(cooldowns.try_start_at("ping", &alice, GLOBAL, USER, start));
+        // the global cooldown expired, but alice's own did not
+        assert!(!cooldowns.try_start_at("ping", &alice, GLOBAL, USER, start + GLOBAL));
+        assert!(cooldowns.try_start_at("ping", &bob, GLOBAL, USER, start + GLOBAL));
+        assert!(cooldowns.try_start_at("ping", &alice, GLOBAL, USER, start + USER));
+    }
+
+    #[test]
+    fn commands_have_separate_cooldowns() {
+        let mut cooldowns = Cooldowns::new();
+        let alice = UserId::from("1");
+        let start = Instant::now();
+
+        assert!(cooldowns.try_start_at("ping", &alice, GLOBAL, USER, start));
+        assert!(cooldowns.try_start_at("points", &alice, GLOBAL, USER, start));
+        assert!(!cooldowns.try_start_at("points", &alice, GLOBAL, USER, start));
+    }
+
+    #[test]
+    fn rejected_invocation_does_not_extend_cooldown() {
+        let mut cooldowns = Cooldowns::new();
+        let alice = UserId::from("1");
+        let start = Instant::now();
+
+        assert!(cooldowns.try_start_at("ping", &alice, Duration::ZERO, USER, start));
+        assert!(!cooldowns.try_start_at("ping", &alice, Duration::ZERO, USER, start + USER / 2));
+        assert!(cooldowns.try_start_at("ping", &alice, Duration::ZERO, USER, start + USER));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::bot::commands::{variables, Command, CommandContext, CommandHandler};
+use crate::bot::BotContext;
+use crate::models::command;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Look up a custom text command defined by the broadcaster.
+pub(super) async fn resolve(bot: &BotContext, trigger: &str) -> anyhow::Result<Option<Command>> {
+    let Some(model) =
+        command::find_by_trigger(bot.broadcaster_user_id.as_str(), trigger, bot.db).await?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Command {
+        name: model.trigger.clone(),
+        aliases: vec![],
+        min_permission: model.min_permission,
+        global_cooldown: Duration::from_secs(model.global_cooldown_seconds.max(0) as u64),
+        user_cooldown: Duration::from_secs(model.user_cooldown_seconds.max(0) as u64),
+        handler: Arc::new(TextCommand { model }),
+    }))
+}
+
+struct TextCommand {
+    model: command::Model,
+}
+
+#[async_trait]
+impl CommandHandler for TextCommand {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let count = command::increment_use_count(self.model.id, ctx.bot.db).await?;
+        let response = variables::render(
+            &self.model.response,
+            &variables::TemplateValues::of_command(ctx, count),
+        );
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,203 @@
+mod builtin;
+mod cooldown;
+mod custom;
+mod parse;
+mod permission;
+pub mod variables;
+
+pub use cooldown::Cooldowns;
+pub use parse::{parse_command, ParsedCommand};
+pub use permission::{is_subscriber, Permission};
+
+use crate::bot::BotContext;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+/// Information about a single invocation of a command.
+pub struct CommandContext {
+    pub bot: Arc<BotContext>,
+    /// The chat message that triggered the command
+    pub message: Arc<ChannelChatMessageV1Payload>,
+    /// Permission level of the chatter that invoked the command
+    pub permission: Permission,
+    /// The command prefix of the channel, for responses mentioning other commands
+    pub prefix: String,
+    /// The trigger as typed by the chatter, without the prefix (may be an alias)
+    pub trigger: String,
+    /// Everything after the trigger
+    pub args: String,
+}
+
+#[async_trait]
+pub trait CommandHandler: Send + Sync {
+    /// Run the command. The returned message (if any) is the response to the invocation.
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>>;
+}
+
+pub struct Command {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub min_permission: Permission,
+    /// Minimum time between two invocations of this command in the channel
+    pub global_cooldown: Duration,
+    /// Minimum time between two invocations of this command by the same chatter
+    pub user_cooldown: Duration,
+    pub handler: Arc<dyn CommandHandler>,
+}
+
+/// Lookup table from command triggers (names and aliases) to commands.
+#[derive(Default)]
+pub struct CommandRegistry {
+    by_trigger: HashMap<String, Arc<Command>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry containing all commands that ship with the bot.
+    pub fn with_builtins() -> anyhow::Result<Self> {
+        let mut registry = Self::new();
+        builtin::register(&mut registry)?;
+        crate::bot::minigames::register(&mut registry)?;
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, command: Command) -> anyhow::Result<()> {
+        let command = Arc::new(command);
+        for trigger in std::iter::once(&command.name).chain(command.aliases.iter()) {
+            let trigger = trigger.to_lowercase();
+            if self.by_trigger.contains_key(&trigger) {
+                anyhow::bail!(
+                    "Command trigger `{trigger}` of command `{}` is already in use",
+                    command.name
+                );
+            }
+            self.by_trigger.insert(trigger, command.clone());
+        }
+        Ok(())
+    }
+
+    /// Triggers are matched case-insensitively.
+    pub fn resolve(&self, trigger: &str) -> Option<Arc<Command>> {
+        self.by_trigger.get(&trigger.to_lowercase()).cloned()
+    }
+
+    /// Look up a command in this registry, falling back to the custom commands of the channel.
+    pub async fn resolve_with_custom(
+        &self,
+        bot: &BotContext,
+        trigger: &str,
+    ) -> anyhow::Result<Option<Arc<Command>>> {
+        if let Some(command) = self.resolve(trigger) {
+            return Ok(Some(command));
+        }
+        Ok(custom::resolve(bot, trigger).await?.map(Arc::new))
+    }
+}
+
+/// Consumes chat messages of one channel and runs the commands invoked by them
+/// until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    prefix: String,
+    registry: CommandRegistry,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let mut cooldowns = Cooldowns::new();
+
+    loop {
+        let message = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            message = chat_messages.recv() => message,
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Command handler lagged behind, skipped {skipped} chat messages");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let Some(parsed) = parse_command(&prefix, &message.message.text) else {
+            continue;
+        };
+        let command = match registry.resolve_with_custom(&bot, parsed.trigger).await {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::error!("Failed to look up command `{}`: {e:#}", parsed.trigger);
+                continue;
+            }
+        };
+
+        let permission = Permission::of_chatter(&message);
+        if permission < command.min_permission {
+            tracing::debug!(
+                "{} is not allowed to use command `{}` (has {permission:?}, needs {:?})",
+                message.chatter_user_login,
+                command.name,
+                command.min_permission
+            );
+            continue;
+        }
+
+        if !cooldowns.try_start(
+            &command.name,
+            &message.chatter_user_id,
+            command.global_cooldown,
+            command.user_cooldown,
+        ) {
+            tracing::debug!(
+                "Command `{}` invoked by {} is on cooldown",
+                command.name,
+                message.chatter_user_login
+            );
+            continue;
+        }
+
+        let ctx = CommandContext {
+            bot: bot.clone(),
+            trigger: parsed.trigger.to_owned(),
+            args: parsed.args.to_owned(),
+            message: message.clone(),
+            permission,
+            prefix: prefix.clone(),
+        };
+        // Run handlers in their own task so a slow command does not hold up the channel
+        tokio::spawn(async move {
+            match command.handler.execute(&ctx).await {
+                Ok(Some(response)) => {
+                    ctx.bot
+                        .chat
+                        .reply(ctx.message.message_id.to_string(), response);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!("Command `{}` failed: {e:#}", command.name);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triggers_are_case_insensitive() {
+        let registry = CommandRegistry::with_builtins().unwrap();
+        assert_eq!(registry.resolve("PING").unwrap().name, "ping");
+        assert_eq!(registry.resolve("PajBot").unwrap().name, "ping");
+        assert!(registry.resolve("pong").is_none());
+    }
+}
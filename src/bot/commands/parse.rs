@@ -0,0 +1,79 @@
+/// Chatterino and other clients append this invisible character to messages
+/// to get around Twitch's duplicate message detection.
+const DUPLICATE_BYPASS_CHARACTER: char = '\u{E0000}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedCommand<'a> {
+    /// The command name, without the prefix, e.g. `ping` for `!ping`
+    pub trigger: &'a str,
+    /// Everything after the trigger, with surrounding whitespace removed
+    pub args: &'a str,
+}
+
+/// Split a chat message into the command trigger and its arguments, if it starts with `prefix`.
+pub fn parse_command<'a>(prefix: &str, text: &'a str) -> Option<ParsedCommand<'a>> {
+    let text = text
+        .trim_end_matches(|c: char| c.is_whitespace() || c == DUPLICATE_BYPASS_CHARACTER)
+        .trim_start();
+    let rest = text.strip_prefix(prefix)?;
+
+    let (trigger, args) = match rest.split_once(char::is_whitespace) {
+        Some((trigger, args)) => (trigger, args.trim_start()),
+        None => (rest, ""),
+    };
+    if trigger.is_empty() {
+        return None;
+    }
+
+    Some(ParsedCommand { trigger, args })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<'a>(prefix: &str, text: &'a str) -> Option<(&'a str, &'a str)> {
+        parse_command(prefix, text).map(|command| (command.trigger, command.args))
+    }
+
+    #[test]
+    fn splits_trigger_and_args() {
+        assert_eq!(parse("!", "!ping"), Some(("ping", "")));
+        assert_eq!(
+            parse("!", "!duel someone 100"),
+            Some(("duel", "someone 100"))
+        );
+    }
+
+    #[test]
+    fn needs_prefix() {
+        assert_eq!(parse("!", "ping"), None);
+        assert_eq!(parse("!", "hello !ping"), None);
+        assert_eq!(parse("!", "!"), None);
+        assert_eq!(parse("!", "! ping"), None);
+    }
+
+    #[test]
+    fn prefix_can_be_longer() {
+        assert_eq!(parse("pb!", "pb!ping"), Some(("ping", "")));
+        assert_eq!(parse("pb!", "!ping"), None);
+    }
+
+    #[test]
+    fn ignores_surrounding_whitespace() {
+        assert_eq!(parse("!", "  !ping  "), Some(("ping", "")));
+        assert_eq!(parse("!", "!echo \t  a  b \t"), Some(("echo", "a  b")));
+    }
+
+    #[test]
+    fn ignores_duplicate_bypass_character() {
+        assert_eq!(parse("!", "!ping \u{E0000}"), Some(("ping", "")));
+        assert_eq!(parse("!", "!echo hi \u{E0000}"), Some(("echo", "hi")));
+    }
+
+    #[test]
+    fn keeps_case() {
+        // triggers are matched case-insensitively when they are resolved
+        assert_eq!(parse("!", "!PiNg Hello"), Some(("PiNg", "Hello")));
+    }
+}
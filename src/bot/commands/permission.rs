@@ -0,0 +1,64 @@
+use sea_orm::{DeriveActiveEnum, EnumIter};
+use serde::{Deserialize, Serialize};
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+/// Permission levels a chatter can have in a channel, ordered from lowest to highest.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    DeriveActiveEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Permission {
+    #[sea_orm(string_value = "everyone")]
+    Everyone,
+    #[sea_orm(string_value = "subscriber")]
+    Subscriber,
+    #[sea_orm(string_value = "vip")]
+    Vip,
+    #[sea_orm(string_value = "moderator")]
+    Moderator,
+    #[sea_orm(string_value = "broadcaster")]
+    Broadcaster,
+}
+
+impl Permission {
+    /// Determine the permission level of the sender of a chat message, based on their badges.
+    pub fn of_chatter(message: &ChannelChatMessageV1Payload) -> Permission {
+        if message.chatter_user_id == message.broadcaster_user_id {
+            return Permission::Broadcaster;
+        }
+
+        message
+            .badges
+            .iter()
+            .map(|badge| match badge.set_id.as_str() {
+                "broadcaster" => Permission::Broadcaster,
+                "moderator" | "lead_moderator" => Permission::Moderator,
+                "vip" => Permission::Vip,
+                "subscriber" | "founder" => Permission::Subscriber,
+                _ => Permission::Everyone,
+            })
+            .max()
+            .unwrap_or(Permission::Everyone)
+    }
+}
+
+/// Whether the sender of a chat message is subscribed to the channel. This is tracked separately
+/// from [`Permission`], since e.g. a moderator can also be a subscriber.
+pub fn is_subscriber(message: &ChannelChatMessageV1Payload) -> bool {
+    message
+        .badges
+        .iter()
+        .any(|badge| matches!(badge.set_id.as_str(), "subscriber" | "founder"))
+}
@@ -0,0 +1,228 @@
+use crate::bot::commands::CommandContext;
+use crate::bot::BotContext;
+use chrono::Utc;
+
+/// The values a template is rendered with.
+pub struct TemplateValues<'a> {
+    pub bot: &'a BotContext,
+    /// Display name of the chatter the template is rendered for, if there is one
+    pub user: Option<&'a str>,
+    pub args: &'a str,
+    /// How often the command has been used, if the template belongs to a command
+    pub count: Option<i32>,
+}
+
+impl<'a> TemplateValues<'a> {
+    /// Values for the response to a command invocation.
+    pub fn of_command(ctx: &'a CommandContext, count: i32) -> Self {
+        TemplateValues {
+            bot: &ctx.bot,
+            user: Some(&ctx.message.chatter_user_name),
+            args: &ctx.args,
+            count: Some(count),
+        }
+    }
+}
+
+/// Render a template like a command response, replacing `$(name)` variables with their values.
+///
+/// Supported variables:
+/// - `$(user)`: display name of the chatter who used the command, not for timers
+/// - `$(args)`: everything typed after the command
+/// - `$(count)`: how often the command has been used, only for commands
+/// - `$(uptime)`: how long the stream has been live
+///
+/// Unknown variables are left untouched.
+pub fn render(template: &str, values: &TemplateValues<'_>) -> String {
+    substitute(template, |name| resolve(name, values))
+}
+
+/// Replace every `$(name)` in `template` with `lookup(name)`, leaving variables it returns `None`
+/// for and a trailing `$(` without a closing parenthesis as they are.
+fn substitute(template: &str, mut lookup: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("$(") {
+        output.push_str(&rest[..start]);
+        let variable = &rest[start..];
+        let Some(end) = variable.find(')') else {
+            rest = variable;
+            break;
+        };
+
+        match lookup(&variable[2..end]) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&variable[..=end]),
+        }
+        rest = &variable[end + 1..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+fn resolve(name: &str, values: &TemplateValues<'_>) -> Option<String> {
+    Some(match name {
+        "user" => values.user?.to_owned(),
+        "args" => values.args.to_owned(),
+        "count" => values.count?.to_string(),
+        "uptime" => uptime(values.bot),
+        _ => return None,
+    })
+}
+
+fn uptime(bot: &BotContext) -> String {
+    match bot.stream_session.borrow().as_ref() {
+        Some(session) => format_duration(Utc::now() - session.started_at),
+        None => "offline".to_owned(),
+    }
+}
+
+/// Format a duration in a human readable way, e.g. `1d 2h 3m 4s`. Zero-valued units are omitted.
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let total_seconds = duration.num_seconds().max(0);
+    let units = [
+        (total_seconds / 86400, "d"),
+        (total_seconds / 3600 % 24, "h"),
+        (total_seconds / 60 % 60, "m"),
+        (total_seconds % 60, "s"),
+    ];
+
+    let formatted = units
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if formatted.is_empty() {
+        "0s".to_owned()
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::stream_session;
+    use crate::testing::MockTwitch;
+    use sea_orm::{DatabaseBackend, MockDatabase};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "user" => Some("Chatter".to_owned()),
+            "args" => Some("some args".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn replaces_known_variables() {
+        assert_eq!(
+            substitute("hi $(user), you said $(args)!", lookup),
+            "hi Chatter, you said some args!"
+        );
+    }
+
+    #[test]
+    fn unterminated_variable_is_kept_once() {
+        assert_eq!(substitute("hi $(user", lookup), "hi $(user");
+        assert_eq!(substitute("$(user) $(", lookup), "Chatter $(");
+    }
+
+    #[test]
+    fn unknown_variable_is_kept() {
+        assert_eq!(substitute("$(nope) $(user)", lookup), "$(nope) Chatter");
+    }
+
+    #[test]
+    fn adjacent_variables() {
+        assert_eq!(substitute("$(user)$(args)", lookup), "Chattersome args");
+        assert_eq!(
+            substitute("$(user)$(nope)$(user)", lookup),
+            "Chatter$(nope)Chatter"
+        );
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(chrono::Duration::seconds(0)), "0s");
+        assert_eq!(
+            format_duration(chrono::Duration::seconds(93_784)),
+            "1d 2h 3m 4s"
+        );
+        assert_eq!(format_duration(chrono::Duration::seconds(3_600)), "1h");
+    }
+
+    async fn bot(
+        twitch: &MockTwitch,
+        stream_session: Option<stream_session::Model>,
+    ) -> std::sync::Arc<BotContext> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        twitch.bot_context(db, stream_session).await.0
+    }
+
+    #[tokio::test]
+    async fn resolves_command_values() {
+        let twitch = MockTwitch::start().await;
+        let bot = bot(&twitch, None).await;
+        let values = TemplateValues {
+            bot: &bot,
+            user: Some("Chatter"),
+            args: "some args",
+            count: Some(3),
+        };
+        assert_eq!(
+            render("$(user) used this $(count) times: $(args)", &values),
+            "Chatter used this 3 times: some args"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_values_stay_literal() {
+        let twitch = MockTwitch::start().await;
+        let bot = bot(&twitch, None).await;
+        // as for timers
+        let values = TemplateValues {
+            bot: &bot,
+            user: None,
+            args: "",
+            count: None,
+        };
+        assert_eq!(
+            render("$(user) $(count) [$(args)]", &values),
+            "$(user) $(count) []"
+        );
+    }
+
+    #[tokio::test]
+    async fn uptime() {
+        let twitch = MockTwitch::start().await;
+        let values = |bot| TemplateValues {
+            bot,
+            user: None,
+            args: "",
+            count: None,
+        };
+
+        let offline = bot(&twitch, None).await;
+        assert_eq!(render("$(uptime)", &values(&offline)), "offline");
+
+        let live = bot(
+            &twitch,
+            Some(stream_session::Model {
+                id: 1,
+                broadcaster_id: "1000".to_owned(),
+                twitch_stream_id: "stream".to_owned(),
+                started_at: Utc::now() - chrono::Duration::hours(2),
+                ended_at: None,
+                peak_viewers: 0,
+                chat_messages: 0,
+            }),
+        )
+        .await;
+        let uptime = render("$(uptime)", &values(&live));
+        assert!(uptime.starts_with("2h"), "{uptime}");
+    }
+}
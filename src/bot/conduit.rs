@@ -0,0 +1,218 @@
+use crate::api::twitch::auth::{self, TwitchAppAccessToken};
+use crate::api::twitch::conduit::{self, ShardTransport, ShardUpdate};
+use crate::Config;
+use anyhow::Context;
+use std::collections::BTreeSet;
+use tokio::sync::Mutex;
+
+/// The EventSub conduit all bots of this process receive their events through. Every websocket
+/// connection is one shard of the conduit, so the shard count grows and shrinks with the number
+/// of running bots.
+pub struct ConduitManager {
+    config: &'static Config,
+    conduit_id: String,
+    state: Mutex<State>,
+}
+
+struct State {
+    token: TwitchAppAccessToken,
+    shard_count: usize,
+    /// Shards currently used by a websocket connection
+    leased: BTreeSet<usize>,
+}
+
+impl ConduitManager {
+    /// Look up the conduit of the client, creating it if there is none yet.
+    pub async fn start(config: &'static Config) -> anyhow::Result<Self> {
+        let token = app_access_token(config).await?;
+        let conduits = conduit::get_conduits(&config.twitch_api, &token.access_token)
+            .await
+            .context("when getting conduits")?;
+        if conduits.len() > 1 {
+            tracing::warn!(
+                "There are {} EventSub conduits, only the first one is used",
+                conduits.len()
+            );
+        }
+        let conduit = match conduits.into_iter().next() {
+            Some(conduit) => conduit,
+            None => {
+                tracing::info!("There is no EventSub conduit yet, creating one");
+                conduit::create_conduit(&config.twitch_api, &token.access_token, 1)
+                    .await
+                    .context("when creating conduit")?
+            }
+        };
+        tracing::info!(
+            "Using EventSub conduit {} with {} shards",
+            conduit.id,
+            conduit.shard_count
+        );
+
+        let manager = Self {
+            config,
+            conduit_id: conduit.id,
+            state: Mutex::new(State {
+                token,
+                shard_count: conduit.shard_count,
+                leased: BTreeSet::new(),
+            }),
+        };
+        // shards left over by a previous run that did not shut down cleanly
+        manager.shrink(&mut *manager.state.lock().await).await?;
+        Ok(manager)
+    }
+
+    pub fn conduit_id(&self) -> &str {
+        &self.conduit_id
+    }
+
+    /// App access token of the client, as needed to manage the subscriptions on the conduit.
+    pub async fn access_token(&self) -> anyhow::Result<String> {
+        self.token(&mut *self.state.lock().await).await
+    }
+
+    /// Reserve a shard for a new websocket connection, adding one to the conduit if all are in use.
+    pub async fn acquire_shard(&self) -> anyhow::Result<usize> {
+        let mut state = self.state.lock().await;
+        let shard_id = (0..)
+            .find(|shard_id| !state.leased.contains(shard_id))
+            .unwrap();
+        if shard_id >= state.shard_count {
+            self.resize(&mut state, shard_id + 1).await?;
+        }
+        state.leased.insert(shard_id);
+        Ok(shard_id)
+    }
+
+    /// Point the shard to a websocket session. Has to be done again for every new session, since
+    /// the shard is disabled when the previous session disconnects.
+    pub async fn assign_shard(&self, shard_id: usize, session_id: &str) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        self.update_shard(
+            &mut state,
+            shard_id,
+            ShardTransport::Websocket { session_id },
+        )
+        .await?;
+        tracing::debug!("Assigned shard {shard_id} to websocket session {session_id}");
+        Ok(())
+    }
+
+    /// Deliver all events to a webhook instead of websocket connections. The first shard is
+    /// reserved for it, so it is never removed. Twitch verifies the webhook before this returns,
+    /// so the web server has to be running already.
+    pub async fn assign_webhook(&self, callback_url: &str, secret: &str) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.leased.insert(0);
+        self.update_shard(
+            &mut state,
+            0,
+            ShardTransport::Webhook {
+                callback: callback_url,
+                secret,
+            },
+        )
+        .await?;
+        tracing::info!("Assigned the conduit to webhook {callback_url}");
+        Ok(())
+    }
+
+    async fn update_shard(
+        &self,
+        state: &mut State,
+        shard_id: usize,
+        transport: ShardTransport<'_>,
+    ) -> anyhow::Result<()> {
+        let token = self.token(state).await?;
+        let errors = conduit::update_conduit_shards(
+            &self.config.twitch_api,
+            &token,
+            &self.conduit_id,
+            &[ShardUpdate {
+                id: shard_id.to_string(),
+                transport,
+            }],
+        )
+        .await
+        .context("when updating conduit shard")?;
+        if let Some(error) = errors.first() {
+            anyhow::bail!(
+                "Twitch refused to update shard {}: {} ({})",
+                error.id,
+                error.message,
+                error.code
+            );
+        }
+        Ok(())
+    }
+
+    /// Give back a shard once its websocket connection has been closed for good.
+    pub async fn release_shard(&self, shard_id: usize) {
+        let mut state = self.state.lock().await;
+        state.leased.remove(&shard_id);
+        if let Err(e) = self.shrink(&mut state).await {
+            tracing::warn!("Failed to remove unused conduit shards: {e:#}");
+        }
+    }
+
+    /// Remove all shards that are not in use anymore. The conduit itself is kept, so the
+    /// subscriptions on it survive until the next start.
+    pub async fn shutdown(&self) {
+        let mut state = self.state.lock().await;
+        if !state.leased.is_empty() {
+            tracing::warn!(
+                "Conduit shards {:?} are still in use while shutting down",
+                state.leased
+            );
+        }
+        if let Err(e) = self.shrink(&mut state).await {
+            tracing::warn!("Failed to remove unused conduit shards: {e:#}");
+        }
+    }
+
+    /// Remove the unused shards at the end of the conduit. Unused shards in between are left
+    /// alone, they are handed out again first.
+    async fn shrink(&self, state: &mut State) -> anyhow::Result<()> {
+        // a conduit needs at least one shard
+        let needed = state.leased.last().map_or(1, |shard_id| shard_id + 1);
+        if needed < state.shard_count {
+            self.resize(state, needed).await?;
+        }
+        Ok(())
+    }
+
+    async fn resize(&self, state: &mut State, shard_count: usize) -> anyhow::Result<()> {
+        let token = self.token(state).await?;
+        let conduit = conduit::update_conduit(
+            &self.config.twitch_api,
+            &token,
+            &self.conduit_id,
+            shard_count,
+        )
+        .await
+        .context("when updating conduit shard count")?;
+        tracing::info!(
+            "Resized conduit {} from {} to {} shards",
+            self.conduit_id,
+            state.shard_count,
+            conduit.shard_count
+        );
+        state.shard_count = conduit.shard_count;
+        Ok(())
+    }
+
+    async fn token(&self, state: &mut State) -> anyhow::Result<String> {
+        if state.token.is_expired() {
+            tracing::info!("Refreshing conduit AppAccessToken");
+            state.token = app_access_token(self.config).await?;
+        }
+        Ok(state.token.access_token.clone())
+    }
+}
+
+async fn app_access_token(config: &'static Config) -> anyhow::Result<TwitchAppAccessToken> {
+    auth::get_app_access_token(&config.twitch_api)
+        .await
+        .context("when getting app access token")
+}
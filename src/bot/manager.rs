@@ -0,0 +1,178 @@
+use crate::bot::conduit::ConduitManager;
+use crate::bot::subscriptions;
+use crate::config::eventsub::EventSubTransport;
+use crate::models::bot;
+use crate::{Config, Events};
+use anyhow::Context;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// How often the bots are compared against the database even without a change notification.
+/// This also determines how quickly a bot that stopped unexpectedly is restarted.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a stopping bot is waited for before it is abandoned
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct RunningBot {
+    model: bot::Model,
+    shutdown_signal: CancellationToken,
+    join_handle: JoinHandle<anyhow::Result<()>>,
+}
+
+impl RunningBot {
+    async fn stop(self) {
+        self.shutdown_signal.cancel();
+        match tokio::time::timeout(STOP_TIMEOUT, self.join_handle).await {
+            Ok(Ok(Ok(()))) => {}
+            Ok(Ok(Err(e))) => tracing::warn!(
+                "Bot in channel {} ended with an error: {e:#}",
+                self.model.broadcaster_id
+            ),
+            Ok(Err(join_error)) => tracing::error!(
+                "Bot in channel {} ended abnormally: {join_error}",
+                self.model.broadcaster_id
+            ),
+            Err(_) => tracing::warn!(
+                "Bot in channel {} did not stop within {STOP_TIMEOUT:?}",
+                self.model.broadcaster_id
+            ),
+        }
+    }
+}
+
+/// Runs one bot for every row of the `bot` table, until `shutdown_signal` fires. Bots are started,
+/// stopped and restarted whenever `changes` is notified after the table was modified, and
+/// periodically after [`RECONCILE_INTERVAL`]. All bots share the EventSub conduit of `conduits`.
+pub async fn run(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    conduits: &'static ConduitManager,
+    events: Arc<Events>,
+    changes: &'static Notify,
+    shutdown_signal: CancellationToken,
+) -> anyhow::Result<()> {
+    if let EventSubTransport::Webhook {
+        callback_url,
+        secret,
+    } = &config.eventsub.transport
+    {
+        conduits
+            .assign_webhook(callback_url, secret)
+            .await
+            .context("Failed to set up EventSub webhook")?;
+    }
+
+    let mut running = HashMap::new();
+    let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            _ = interval.tick() => {}
+            _ = changes.notified() => {}
+        }
+
+        if let Err(e) = reconcile(
+            config,
+            db,
+            conduits,
+            &events,
+            &mut running,
+            &shutdown_signal,
+        )
+        .await
+        {
+            tracing::error!("Failed to update running bots: {e:#}");
+        }
+    }
+
+    futures::future::join_all(running.into_values().map(RunningBot::stop)).await;
+    conduits.shutdown().await;
+    Ok(())
+}
+
+async fn reconcile(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    conduits: &'static ConduitManager,
+    events: &Arc<Events>,
+    running: &mut HashMap<String, RunningBot>,
+    shutdown_signal: &CancellationToken,
+) -> anyhow::Result<()> {
+    let mut wanted = bot::Entity::find()
+        .all(db)
+        .await
+        .context("reconcile find bots")?
+        .into_iter()
+        .map(|model| (model.broadcaster_id.clone(), model))
+        .collect::<HashMap<_, _>>();
+
+    let outdated = running
+        .iter()
+        .filter(|(broadcaster_id, running_bot)| {
+            running_bot.join_handle.is_finished()
+                || wanted.get(*broadcaster_id) != Some(&running_bot.model)
+        })
+        .map(|(broadcaster_id, _)| broadcaster_id.clone())
+        .collect::<Vec<_>>();
+    for broadcaster_id in outdated {
+        let running_bot = running.remove(&broadcaster_id).unwrap();
+        if running_bot.join_handle.is_finished() {
+            tracing::warn!("Bot in channel {broadcaster_id} stopped unexpectedly, restarting it");
+        } else {
+            tracing::info!("Stopping bot in channel {broadcaster_id}");
+        }
+        let bot_id = running_bot.model.bot_id.clone();
+        running_bot.stop().await;
+
+        if !wanted.contains_key(&broadcaster_id) {
+            // nothing desired, so all subscriptions of the channel are deleted
+            match subscriptions::reconcile(config, db, conduits, &broadcaster_id, &bot_id, &[])
+                .await
+            {
+                Ok(report) => subscriptions::log_report(&broadcaster_id, &report),
+                Err(e) => tracing::warn!(
+                    "Failed to delete subscriptions of channel {broadcaster_id}: {e:#}"
+                ),
+            }
+        }
+    }
+
+    wanted.retain(|broadcaster_id, _| !running.contains_key(broadcaster_id));
+    for (broadcaster_id, model) in wanted {
+        tracing::info!("Starting bot {} in channel {broadcaster_id}", model.bot_id);
+        let bot_shutdown_signal = shutdown_signal.child_token();
+        match super::run(
+            config,
+            model.clone(),
+            db,
+            conduits,
+            events.clone(),
+            bot_shutdown_signal.clone(),
+        )
+        .await
+        {
+            Ok(join_handle) => {
+                running.insert(
+                    broadcaster_id,
+                    RunningBot {
+                        model,
+                        shutdown_signal: bot_shutdown_signal,
+                        join_handle,
+                    },
+                );
+            }
+            // retried with the next reconciliation
+            Err(e) => {
+                bot_shutdown_signal.cancel();
+                tracing::error!("Failed to start bot in channel {broadcaster_id}: {e:#}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use crate::bot::commands::{CommandContext, CommandHandler, Cooldowns};
+use crate::bot::minigames::{parse_bet, settle, BetLimits, Settlement};
+use crate::models::minigame_round::{Game, Stake};
+use crate::models::{minigame_settings, user, user_channel_points};
+use async_trait::async_trait;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use twitch_types::UserId;
+
+/// A duel waiting for the challenged user to accept
+struct PendingDuel {
+    /// Distinguishes duels between the same users, so an expiry can't cancel a newer duel
+    id: u64,
+    challenger_id: UserId,
+    challenger_name: String,
+    bet: i64,
+}
+
+/// Pending duels of a channel, by the challenged user
+#[derive(Default)]
+pub struct Duels {
+    pending: Mutex<HashMap<UserId, PendingDuel>>,
+    next_id: AtomicU64,
+    cooldowns: Mutex<Cooldowns>,
+}
+
+/// `!duel <user> <bet>`: challenge another chatter, the winner takes both bets
+pub struct Challenge(pub Arc<Duels>);
+
+#[async_trait]
+impl CommandHandler for Challenge {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let settings =
+            minigame_settings::find_or_default(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?;
+        if !settings.duel_enabled {
+            return Ok(None);
+        }
+        let (login, bet_arg) = ctx.args.split_once(' ').unwrap_or((&ctx.args, ""));
+        let login = login.trim_start_matches('@');
+        if login.is_empty() {
+            return Ok(Some(format!("Usage: {}duel <user> <bet>", ctx.prefix)));
+        }
+        let Some(opponent) = user::find_by_login(login, ctx.bot.db).await? else {
+            return Ok(Some(format!("{login} has no points to duel with")));
+        };
+        if opponent.id == ctx.message.chatter_user_id.as_str()
+            || opponent.id == ctx.bot.bot_user_id.as_str()
+        {
+            return Ok(Some(format!("You can't duel {}", opponent.display_name)));
+        }
+        let limits = BetLimits {
+            min: settings.duel_min_bet,
+            max: settings.duel_max_bet,
+        };
+        let bet = match parse_bet(ctx, bet_arg, limits).await? {
+            Ok(bet) => bet,
+            Err(response) => return Ok(Some(response)),
+        };
+        let opponent_points = user_channel_points::get_points(
+            ctx.bot.broadcaster_user_id.as_str(),
+            &opponent.id,
+            ctx.bot.db,
+        )
+        .await?;
+        if opponent_points < bet {
+            return Ok(Some(format!(
+                "{} only has {opponent_points} points",
+                opponent.display_name
+            )));
+        }
+
+        let opponent_id = UserId::from(opponent.id);
+        let id = {
+            let mut pending = self.0.pending.lock().unwrap();
+            if pending.contains_key(&opponent_id) {
+                return Ok(Some(format!(
+                    "{} already has a pending duel",
+                    opponent.display_name
+                )));
+            }
+            if !self.0.cooldowns.lock().unwrap().try_start(
+                "duel",
+                &ctx.message.chatter_user_id,
+                Duration::ZERO,
+                Duration::from_secs(settings.duel_cooldown_seconds as u64),
+            ) {
+                return Ok(None);
+            }
+            let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+            pending.insert(
+                opponent_id.clone(),
+                PendingDuel {
+                    id,
+                    challenger_id: ctx.message.chatter_user_id.clone(),
+                    challenger_name: ctx.message.chatter_user_name.to_string(),
+                    bet,
+                },
+            );
+            id
+        };
+
+        let accept_for = Duration::from_secs(settings.duel_accept_seconds as u64);
+        let duels = self.0.clone();
+        let bot = ctx.bot.clone();
+        let challenger_name = ctx.message.chatter_user_name.to_string();
+        let opponent_name = opponent.display_name.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(accept_for).await;
+            let mut pending = duels.pending.lock().unwrap();
+            if pending.get(&opponent_id).is_some_and(|duel| duel.id == id) {
+                pending.remove(&opponent_id);
+                bot.chat.send(format!(
+                    "{opponent_name} did not accept the duel of {challenger_name} in time"
+                ));
+            }
+        });
+
+        Ok(Some(format!(
+            "@{}, {} challenged you to a duel for {bet} points! Type {prefix}accept or {prefix}deny within {} seconds",
+            opponent.display_name,
+            ctx.message.chatter_user_name,
+            settings.duel_accept_seconds,
+            prefix = ctx.prefix
+        )))
+    }
+}
+
+/// `!accept`: fight the pending duel
+pub struct Accept(pub Arc<Duels>);
+
+#[async_trait]
+impl CommandHandler for Accept {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let Some(duel) = self
+            .0
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&ctx.message.chatter_user_id)
+        else {
+            return Ok(None);
+        };
+
+        let challenger_won = rand::thread_rng().gen_bool(0.5);
+        let (winner, loser) = if challenger_won {
+            (
+                duel.challenger_name.clone(),
+                ctx.message.chatter_user_name.to_string(),
+            )
+        } else {
+            (
+                ctx.message.chatter_user_name.to_string(),
+                duel.challenger_name.clone(),
+            )
+        };
+        let stakes = vec![
+            Stake {
+                user_id: duel.challenger_id.to_string(),
+                bet: duel.bet,
+                payout: if challenger_won { duel.bet * 2 } else { 0 },
+            },
+            Stake {
+                user_id: ctx.message.chatter_user_id.to_string(),
+                bet: duel.bet,
+                payout: if challenger_won { 0 } else { duel.bet * 2 },
+            },
+        ];
+        let outcome = format!("{winner} won against {loser}");
+        match settle(&ctx.bot, Game::Duel, outcome, stakes).await? {
+            Settlement::Settled => Ok(Some(format!(
+                "{winner} won the duel against {loser} and takes {} points",
+                duel.bet * 2
+            ))),
+            Settlement::CantAfford { user_id } => {
+                let name = if user_id == duel.challenger_id.as_str() {
+                    duel.challenger_name
+                } else {
+                    ctx.message.chatter_user_name.to_string()
+                };
+                Ok(Some(format!(
+                    "The duel is off, {name} no longer has {} points",
+                    duel.bet
+                )))
+            }
+        }
+    }
+}
+
+/// `!deny`: decline the pending duel
+pub struct Deny(pub Arc<Duels>);
+
+#[async_trait]
+impl CommandHandler for Deny {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let Some(duel) = self
+            .0
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&ctx.message.chatter_user_id)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(format!(
+            "{} declined the duel of {}",
+            ctx.message.chatter_user_name, duel.challenger_name
+        )))
+    }
+}
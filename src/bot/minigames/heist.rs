@@ -0,0 +1,194 @@
+use crate::bot::commands::{CommandContext, CommandHandler};
+use crate::bot::minigames::{parse_bet, BetLimits};
+use crate::bot::BotContext;
+use crate::models::minigame_round::{self, Game, Stake};
+use crate::models::{minigame_settings, user_channel_points};
+use anyhow::Context;
+use async_trait::async_trait;
+use rand::Rng;
+use sea_orm::TransactionTrait;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use twitch_types::UserId;
+
+struct Participant {
+    user_id: UserId,
+    display_name: String,
+    bet: i64,
+}
+
+#[derive(Default)]
+struct HeistState {
+    /// Participants of the heist that can currently be joined, if any
+    crew: Option<Vec<Participant>>,
+    /// When the next heist may start
+    ready_at: Option<Instant>,
+}
+
+/// `!heist <bet>`: start or join a heist. After the join window, each participant independently
+/// gets away with the loot or loses their bet, with odds depending on the size of the crew.
+#[derive(Default)]
+pub struct Heist {
+    state: Arc<Mutex<HeistState>>,
+}
+
+#[async_trait]
+impl CommandHandler for Heist {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let settings =
+            minigame_settings::find_or_default(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?;
+        if !settings.heist_enabled {
+            return Ok(None);
+        }
+        let limits = BetLimits {
+            min: settings.heist_min_bet,
+            max: settings.heist_max_bet,
+        };
+        let bet = match parse_bet(ctx, &ctx.args, limits).await? {
+            Ok(bet) => bet,
+            Err(response) => return Ok(Some(response)),
+        };
+        // the bet is only taken once the heist is carried out, so a restart of the bot loses nothing
+        let participant = Participant {
+            user_id: ctx.message.chatter_user_id.clone(),
+            display_name: ctx.message.chatter_user_name.to_string(),
+            bet,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(crew) = &mut state.crew {
+            if crew.iter().any(|p| p.user_id == participant.user_id) {
+                return Ok(Some("You already joined the heist".to_owned()));
+            }
+            crew.push(participant);
+            return Ok(Some(format!("You joined the heist with {bet} points")));
+        }
+        if let Some(remaining) = state
+            .ready_at
+            .and_then(|ready_at| ready_at.checked_duration_since(Instant::now()))
+        {
+            return Ok(Some(format!(
+                "The cops are still on high alert, the next heist can start in {} seconds",
+                remaining.as_secs() + 1
+            )));
+        }
+        state.crew = Some(vec![participant]);
+        drop(state);
+
+        let join_for = Duration::from_secs(settings.heist_join_seconds as u64);
+        tokio::spawn(run_heist(ctx.bot.clone(), self.state.clone(), join_for));
+        Ok(Some(format!(
+            "{} is starting a heist! Join with {}heist <bet> within {} seconds",
+            ctx.message.chatter_user_name, ctx.prefix, settings.heist_join_seconds
+        )))
+    }
+}
+
+/// Wait for the crew to assemble, then carry out the heist and announce the result.
+async fn run_heist(bot: Arc<BotContext>, state: Arc<Mutex<HeistState>>, join_for: Duration) {
+    tokio::time::sleep(join_for).await;
+    let crew = state.lock().unwrap().crew.take().unwrap_or_default();
+    let result = carry_out(&bot, crew).await;
+    // loaded again, in case the cooldown was changed while the crew assembled
+    let cooldown =
+        match minigame_settings::find_or_default(bot.broadcaster_user_id.as_str(), bot.db).await {
+            Ok(settings) => Duration::from_secs(settings.heist_cooldown_seconds as u64),
+            Err(e) => {
+                tracing::error!("Failed to load minigame settings: {e:#}");
+                Duration::ZERO
+            }
+        };
+    state.lock().unwrap().ready_at = Some(Instant::now() + cooldown);
+    match result {
+        Ok(announcement) => bot.chat.send(announcement),
+        Err(e) => {
+            tracing::error!("Heist in channel {} failed: {e:#}", bot.broadcaster_user_id);
+            bot.chat
+                .send("The heist was called off, nobody lost any points");
+        }
+    }
+}
+
+/// Settle the heist. The bets are taken first, participants who can no longer afford theirs are
+/// left behind, and the payout tier depends on how many are left. Each of them then gets away with
+/// the loot or not on their own. Returns the announcement of the result.
+async fn carry_out(bot: &BotContext, crew: Vec<Participant>) -> anyhow::Result<String> {
+    let broadcaster_id = bot.broadcaster_user_id.as_str();
+    let settings = minigame_settings::find_or_default(broadcaster_id, bot.db)
+        .await
+        .context("carry_out find settings")?;
+
+    let tx = bot
+        .db
+        .begin()
+        .await
+        .context("carry_out begin transaction")?;
+    let mut paying = Vec::with_capacity(crew.len());
+    for participant in crew {
+        let paid = user_channel_points::add_points(
+            broadcaster_id,
+            participant.user_id.as_str(),
+            -participant.bet,
+            &tx,
+        )
+        .await
+        .context("carry_out take bet")?;
+        if paid.is_some() {
+            paying.push(participant);
+        }
+    }
+    // returning without committing rolls back the bets taken so far
+    if paying.is_empty() {
+        return Ok("Nobody could pay for the heist, it was called off".to_owned());
+    }
+    let Some(tier) = settings.heist_payouts.tier(paying.len()).cloned() else {
+        return Ok("Not enough people joined the heist, it was called off".to_owned());
+    };
+
+    let mut stakes = Vec::with_capacity(paying.len());
+    let mut survivors = Vec::new();
+    for participant in paying {
+        let survived = rand::thread_rng().gen_range(0..100) < tier.win_percent;
+        let payout = if survived {
+            participant.bet * i64::from(tier.payout_percent) / 100
+        } else {
+            0
+        };
+        if payout > 0 {
+            user_channel_points::add_points(
+                broadcaster_id,
+                participant.user_id.as_str(),
+                payout,
+                &tx,
+            )
+            .await
+            .context("carry_out pay out")?;
+        }
+        if survived {
+            survivors.push(format!("{} ({payout})", participant.display_name));
+        }
+        stakes.push(Stake {
+            user_id: participant.user_id.to_string(),
+            bet: participant.bet,
+            payout,
+        });
+    }
+
+    let crew_size = stakes.len();
+    let outcome = format!("{}/{crew_size} got away", survivors.len());
+    minigame_round::record(broadcaster_id, Game::Heist, outcome, stakes, &tx)
+        .await
+        .context("carry_out record round")?;
+    tx.commit().await.context("carry_out commit")?;
+
+    Ok(if survivors.is_empty() {
+        format!("The heist failed, all {crew_size} participants lost their bets")
+    } else {
+        format!(
+            "The heist is over, {}/{crew_size} got away with the loot: {}",
+            survivors.len(),
+            survivors.join(", ")
+        )
+    })
+}
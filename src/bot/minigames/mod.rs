@@ -0,0 +1,132 @@
+//! Games chatters play with their points. Settings are loaded on every invocation, so changes
+//! take effect without restarting the bot.
+
+mod duel;
+mod heist;
+mod roulette;
+mod slots;
+
+use crate::bot::commands::{Command, CommandContext, CommandHandler, CommandRegistry, Permission};
+use crate::bot::BotContext;
+use crate::models::minigame_round::{self, Game, Stake};
+use crate::models::user_channel_points;
+use anyhow::Context;
+use sea_orm::TransactionTrait;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(super) fn register(registry: &mut CommandRegistry) -> anyhow::Result<()> {
+    let game_command = |name: &str, handler: Arc<dyn CommandHandler>| Command {
+        name: name.to_owned(),
+        aliases: vec![],
+        min_permission: Permission::Everyone,
+        // the cooldowns of the games are configured per channel and checked by the handlers
+        global_cooldown: Duration::ZERO,
+        user_cooldown: Duration::ZERO,
+        handler,
+    };
+    registry.register(game_command(
+        "roulette",
+        Arc::new(roulette::Roulette::default()),
+    ))?;
+    registry.register(game_command(
+        "slotmachine",
+        Arc::new(slots::SlotMachine::default()),
+    ))?;
+    let duels = Arc::new(duel::Duels::default());
+    registry.register(game_command(
+        "duel",
+        Arc::new(duel::Challenge(duels.clone())),
+    ))?;
+    registry.register(game_command(
+        "accept",
+        Arc::new(duel::Accept(duels.clone())),
+    ))?;
+    registry.register(game_command("deny", Arc::new(duel::Deny(duels))))?;
+    registry.register(game_command("heist", Arc::new(heist::Heist::default())))?;
+    Ok(())
+}
+
+/// Bounds of the bets of a game, from the channel's settings.
+struct BetLimits {
+    min: i64,
+    max: i64,
+}
+
+/// The bet a chatter placed with the first word of `args`, which is either a number of points or
+/// `all` to bet as much as allowed. `Err` holds the response to the chatter if the bet is invalid.
+async fn parse_bet(
+    ctx: &CommandContext,
+    args: &str,
+    limits: BetLimits,
+) -> anyhow::Result<Result<i64, String>> {
+    let usage = format!(
+        "Bet between {} and {} points, or \"all\"",
+        limits.min, limits.max
+    );
+    let Some(arg) = args.split_whitespace().next() else {
+        return Ok(Err(usage));
+    };
+    let balance = user_channel_points::get_points(
+        ctx.bot.broadcaster_user_id.as_str(),
+        ctx.message.chatter_user_id.as_str(),
+        ctx.bot.db,
+    )
+    .await?;
+    let bet = if arg.eq_ignore_ascii_case("all") {
+        balance.min(limits.max)
+    } else {
+        match arg.parse::<i64>() {
+            Ok(bet) => bet,
+            Err(_) => return Ok(Err(usage)),
+        }
+    };
+    if bet > balance {
+        return Ok(Err(format!("You only have {balance} points")));
+    }
+    if !(limits.min..=limits.max).contains(&bet) {
+        return Ok(Err(usage));
+    }
+    Ok(Ok(bet))
+}
+
+enum Settlement {
+    Settled,
+    /// Nothing changed, because a participant can't afford their bet anymore
+    CantAfford {
+        user_id: String,
+    },
+}
+
+/// Take the bets of all participants of a round, give them their payouts and record the round,
+/// all or nothing.
+async fn settle(
+    bot: &BotContext,
+    game: Game,
+    outcome: String,
+    stakes: Vec<Stake>,
+) -> anyhow::Result<Settlement> {
+    let broadcaster_id = bot.broadcaster_user_id.as_str();
+    let tx = bot.db.begin().await.context("settle begin transaction")?;
+    for stake in &stakes {
+        // the bet is taken first, so a payout can't cover a bet the participant doesn't have
+        let paid = user_channel_points::add_points(broadcaster_id, &stake.user_id, -stake.bet, &tx)
+            .await
+            .context("settle take bet")?;
+        if paid.is_none() {
+            return Ok(Settlement::CantAfford {
+                user_id: stake.user_id.clone(),
+            });
+        }
+        if stake.payout > 0 {
+            user_channel_points::add_points(broadcaster_id, &stake.user_id, stake.payout, &tx)
+                .await
+                .context("settle pay out")?;
+        }
+    }
+    minigame_round::record(broadcaster_id, game, outcome, stakes, &tx)
+        .await
+        .context("settle record round")?;
+    tx.commit().await.context("settle commit")?;
+    Ok(Settlement::Settled)
+}
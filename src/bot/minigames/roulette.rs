@@ -0,0 +1,54 @@
+use crate::bot::commands::{CommandContext, CommandHandler, Cooldowns};
+use crate::bot::minigames::{parse_bet, settle, BetLimits, Settlement};
+use crate::models::minigame_round::{Game, Stake};
+use crate::models::minigame_settings;
+use async_trait::async_trait;
+use rand::Rng;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `!roulette <bet>`: double the bet or lose it
+#[derive(Default)]
+pub struct Roulette {
+    cooldowns: Mutex<Cooldowns>,
+}
+
+#[async_trait]
+impl CommandHandler for Roulette {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let settings =
+            minigame_settings::find_or_default(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?;
+        if !settings.roulette_enabled {
+            return Ok(None);
+        }
+        let limits = BetLimits {
+            min: settings.roulette_min_bet,
+            max: settings.roulette_max_bet,
+        };
+        let bet = match parse_bet(ctx, &ctx.args, limits).await? {
+            Ok(bet) => bet,
+            Err(response) => return Ok(Some(response)),
+        };
+        if !self.cooldowns.lock().unwrap().try_start(
+            "roulette",
+            &ctx.message.chatter_user_id,
+            Duration::ZERO,
+            Duration::from_secs(settings.roulette_cooldown_seconds as u64),
+        ) {
+            return Ok(None);
+        }
+
+        let won = rand::thread_rng().gen_range(0..100) < settings.roulette_win_percent;
+        let stake = Stake {
+            user_id: ctx.message.chatter_user_id.to_string(),
+            bet,
+            payout: if won { bet * 2 } else { 0 },
+        };
+        let outcome = if won { "won" } else { "lost" };
+        match settle(&ctx.bot, Game::Roulette, outcome.to_owned(), vec![stake]).await? {
+            Settlement::Settled => Ok(Some(format!("You {outcome} {bet} points in roulette"))),
+            Settlement::CantAfford { .. } => Ok(Some(format!("You don't have {bet} points"))),
+        }
+    }
+}
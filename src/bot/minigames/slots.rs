@@ -0,0 +1,115 @@
+use crate::bot::commands::{CommandContext, CommandHandler, Cooldowns};
+use crate::bot::minigames::{parse_bet, settle, BetLimits, Settlement};
+use crate::models::minigame_round::{Game, Stake};
+use crate::models::minigame_settings;
+use async_trait::async_trait;
+use rand::seq::SliceRandom;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The symbols on each reel, with the payout in percent of the bet for three of them.
+const SYMBOLS: [(&str, i64); 6] = [
+    ("🍒", 500),
+    ("🍋", 500),
+    ("🍇", 1000),
+    ("🔔", 1000),
+    ("💎", 1500),
+    ("7️⃣", 2500),
+];
+/// Payout in percent of the bet for two matching symbols
+const PAIR_PAYOUT_PERCENT: i64 = 150;
+
+/// `!slotmachine <bet>`: spin three reels, matching symbols pay out
+#[derive(Default)]
+pub struct SlotMachine {
+    cooldowns: Mutex<Cooldowns>,
+}
+
+/// Payout in percent of the bet for the symbols shown by the reels.
+fn payout_percent(reels: &[(&str, i64); 3]) -> i64 {
+    let [a, b, c] = reels;
+    if a.0 == b.0 && b.0 == c.0 {
+        a.1
+    } else if a.0 == b.0 || b.0 == c.0 || a.0 == c.0 {
+        PAIR_PAYOUT_PERCENT
+    } else {
+        0
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SlotMachine {
+    async fn execute(&self, ctx: &CommandContext) -> anyhow::Result<Option<String>> {
+        let settings =
+            minigame_settings::find_or_default(ctx.bot.broadcaster_user_id.as_str(), ctx.bot.db)
+                .await?;
+        if !settings.slots_enabled {
+            return Ok(None);
+        }
+        let limits = BetLimits {
+            min: settings.slots_min_bet,
+            max: settings.slots_max_bet,
+        };
+        let bet = match parse_bet(ctx, &ctx.args, limits).await? {
+            Ok(bet) => bet,
+            Err(response) => return Ok(Some(response)),
+        };
+        if !self.cooldowns.lock().unwrap().try_start(
+            "slotmachine",
+            &ctx.message.chatter_user_id,
+            Duration::ZERO,
+            Duration::from_secs(settings.slots_cooldown_seconds as u64),
+        ) {
+            return Ok(None);
+        }
+
+        let reels = {
+            let mut rng = rand::thread_rng();
+            [(); 3].map(|_| *SYMBOLS.choose(&mut rng).unwrap())
+        };
+        let payout = bet * payout_percent(&reels) / 100;
+        let outcome = reels.map(|(symbol, _)| symbol).join(" ");
+        let stake = Stake {
+            user_id: ctx.message.chatter_user_id.to_string(),
+            bet,
+            payout,
+        };
+        match settle(&ctx.bot, Game::Slots, outcome.clone(), vec![stake]).await? {
+            Settlement::Settled if payout > 0 => Ok(Some(format!(
+                "[ {outcome} ] The machine paid out {payout} points"
+            ))),
+            Settlement::Settled => Ok(Some(format!("[ {outcome} ] You lost {bet} points"))),
+            Settlement::CantAfford { .. } => Ok(Some(format!("You don't have {bet} points"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str) -> (&'static str, i64) {
+        *SYMBOLS.iter().find(|(symbol, _)| *symbol == name).unwrap()
+    }
+
+    #[test]
+    fn three_of_a_kind_pays_the_symbol() {
+        for symbol in SYMBOLS {
+            assert_eq!(payout_percent(&[symbol; 3]), symbol.1);
+        }
+    }
+
+    #[test]
+    fn pair_pays_the_same_in_any_position() {
+        let (cherry, bell) = (symbol("🍒"), symbol("🔔"));
+        assert_eq!(payout_percent(&[cherry, cherry, bell]), PAIR_PAYOUT_PERCENT);
+        assert_eq!(payout_percent(&[bell, cherry, cherry]), PAIR_PAYOUT_PERCENT);
+        assert_eq!(payout_percent(&[cherry, bell, cherry]), PAIR_PAYOUT_PERCENT);
+    }
+
+    #[test]
+    fn no_match_pays_nothing() {
+        let reels = [symbol("🍒"), symbol("💎"), symbol("7️⃣")];
+        assert_eq!(payout_percent(&reels), 0);
+    }
+}
@@ -1,113 +1,254 @@
 use std::sync::Arc;
 
-use crate::config::BotConfig;
-use crate::{Config, Events};
-use anyhow::Context;
-use sea_orm::DatabaseConnection;
+use crate::config::eventsub::EventSubTransport;
+use crate::models::{bot, moderation_log, special_twitch_authorization, stream_session};
+use crate::{AuthorizationEvent, Config, Events};
+use anyhow::{anyhow, Context};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use tokio::sync::{broadcast, watch};
 use tokio_util::sync::CancellationToken;
-use twitch_api::client::ClientDefault;
-use twitch_api::HelixClient;
-use twitch_oauth2::AppAccessToken;
 use twitch_types::UserId;
 
+pub mod banphrases;
+pub mod chat;
+pub mod commands;
+pub mod conduit;
+pub mod manager;
+pub mod minigames;
+pub mod moderation;
+pub mod notifications;
+pub mod points;
+pub mod redemptions;
+pub mod revocations;
+pub mod scope_check;
+pub mod stream_sessions;
+pub mod subscriptions;
+pub mod timers;
+pub mod token_refresh;
 mod websocket;
 
+/// State shared between the components of a single running bot.
+pub struct BotContext {
+    pub config: &'static Config,
+    pub db: &'static DatabaseConnection,
+    pub bot_user_id: UserId,
+    pub broadcaster_user_id: UserId,
+    pub chat: chat::ChatSender,
+    pub conduits: &'static conduit::ConduitManager,
+    /// The session of the stream the channel is live with, `None` while it is offline
+    pub stream_session: watch::Receiver<Option<stream_session::Model>>,
+}
+
+impl BotContext {
+    /// Authorization of the bot account, as granted through the special authorization flow.
+    pub async fn bot_authorization(&self) -> anyhow::Result<special_twitch_authorization::Model> {
+        special_twitch_authorization::Entity::find_by_id(self.bot_user_id.as_str())
+            .one(self.db)
+            .await
+            .context("bot_authorization find authorization")?
+            .filter(|authorization| authorization.invalidated_at.is_none())
+            .ok_or_else(|| anyhow!("Bot account {} has not been authorized", self.bot_user_id))
+    }
+
+    /// Twitch access token of the bot account, as granted through the special authorization flow.
+    pub async fn bot_access_token(&self) -> anyhow::Result<String> {
+        Ok(self.bot_authorization().await?.twitch_access_token)
+    }
+
+    /// Whether the channel is live right now.
+    pub fn is_live(&self) -> bool {
+        self.stream_session.borrow().is_some()
+    }
+
+    /// Moderation actions in the bot's channel, performed as the bot account and logged
+    /// on behalf of `actor_id`.
+    pub fn moderation(
+        &self,
+        source: moderation_log::Source,
+        actor_id: &str,
+    ) -> moderation::ModerationExecutor {
+        moderation::ModerationExecutor::new(
+            self.config,
+            self.db,
+            self.broadcaster_user_id.as_str(),
+            self.bot_user_id.as_str(),
+            source,
+            actor_id,
+        )
+    }
+}
+
+/// Start a bot in the channel of `bot.broadcaster_id`. The returned task ends when the bot stops
+/// receiving events, all other parts of the bot stop once `shutdown_signal` fires.
 pub async fn run(
-    _config: &'static Config,
-    bot_config: &'static BotConfig,
-    _db: &'static DatabaseConnection,
+    config: &'static Config,
+    bot: bot::Model,
+    db: &'static DatabaseConnection,
+    conduits: &'static conduit::ConduitManager,
     events: Arc<Events>,
     shutdown_signal: CancellationToken,
 ) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
-    let client: HelixClient<_> = twitch_api::HelixClient::with_client(
-        <reqwest::Client>::default_client_with_name(Some(
-            "pajbot/3.0"
-                .parse()
-                .with_context(|| "when creating header name")
-                .unwrap(),
-        ))
-        .with_context(|| "when creating client")?,
-    );
-
-    let token = AppAccessToken::get_app_access_token(
-        client.get_client(),
-        bot_config.client_id.clone(),
-        bot_config.client_secret.clone(),
-        vec![],
+    let bot_user_id: UserId = bot.bot_id.into();
+    let streamer_user_id: UserId = bot.broadcaster_id.into();
+
+    let report = subscriptions::reconcile(
+        config,
+        db,
+        conduits,
+        streamer_user_id.as_str(),
+        bot_user_id.as_str(),
+        subscriptions::DESIRED_SUBSCRIPTIONS,
     )
-    .await?;
+    .await
+    .context("when reconciling subscriptions")?;
+    subscriptions::log_report(streamer_user_id.as_str(), &report);
 
-    let conduits = client.get_conduits(&token).await?;
-    println!("Conduits: {conduits:?}");
+    let (chat_sender, chat_queue) = chat::queue();
+    let (stream_session_sender, stream_session) = watch::channel(None);
+    let bot_context = Arc::new(BotContext {
+        config,
+        db,
+        bot_user_id: bot_user_id.clone(),
+        broadcaster_user_id: streamer_user_id.clone(),
+        chat: chat_sender,
+        conduits,
+        stream_session,
+    });
+    if let Err(e) = revocations::resolve(&bot_context, &report).await {
+        tracing::warn!("Failed to resolve revocations in channel {streamer_user_id}: {e:#}");
+    }
 
-    let first_conduit = conduits.first().unwrap().clone();
+    tokio::spawn(chat::run(
+        bot_context.clone(),
+        chat_queue,
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
 
-    let transport = twitch_api::eventsub::Transport::conduit(first_conduit.id.clone());
-    let bot_user_id: UserId = bot_config.bot_user_id.clone().into();
-    let streamer_user_id: UserId = bot_config.streamer_user_id.clone().into();
+    tokio::spawn(banphrases::run(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
 
-    match client
-        .create_eventsub_subscription(
-            twitch_api::eventsub::channel::ChannelChatMessageV1::new(
-                streamer_user_id.clone(),
-                bot_user_id.clone(),
-            ),
-            transport.clone(),
-            &token,
-        )
-        .await
-    {
-        Ok(created_subscription) => {
-            tracing::info!("Created subscription: {created_subscription:?}");
-        }
-        Err(e) => match e {
-            twitch_api::helix::ClientRequestError::HelixRequestPostError(e) => match e {
-                twitch_api::helix::HelixRequestPostError::Error {
-                    error: _,
-                    status,
-                    message: _,
-                    uri: _,
-                    body: _,
-                } => {
-                    if status == reqwest::StatusCode::CONFLICT {
-                        tracing::info!("This subscription already exists!");
-                    } else {
-                        return Err(e.into());
-                    }
-                }
-                e => {
-                    return Err(e.into());
-                }
-            },
-            e => {
-                return Err(e.into());
-            }
-        },
+    tokio::spawn(moderation::log_external_actions(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(stream_sessions::run(
+        bot_context.clone(),
+        stream_session_sender,
+        events.subscribe(streamer_user_id.clone()),
+        events.subscribe(streamer_user_id.clone()),
+        events.subscribe(streamer_user_id.clone()),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(points::run(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(timers::run(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(redemptions::run(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(revocations::run(
+        bot_context.clone(),
+        events.subscribe(streamer_user_id.clone()),
+        shutdown_signal.clone(),
+    ));
+
+    tokio::spawn(watch_authorizations(
+        bot_context.clone(),
+        events.get_receiver_authorization(),
+        shutdown_signal.clone(),
+    ));
+
+    let chat_messages = events.subscribe(streamer_user_id.clone());
+    tokio::spawn(commands::run(
+        bot_context,
+        bot.command_prefix,
+        commands::CommandRegistry::with_builtins()?,
+        chat_messages,
+        shutdown_signal.clone(),
+    ));
+
+    if let EventSubTransport::Webhook { .. } = config.eventsub.transport {
+        // events arrive through the web server, so there is nothing that could stop receiving them
+        return Ok(tokio::spawn(async move {
+            shutdown_signal.cancelled().await;
+            Ok(())
+        }));
     }
 
-    let websocket_client = websocket::WebsocketClient::new(
-        token.clone(),
-        client.clone(),
-        bot_config,
-        twitch_api::TWITCH_EVENTSUB_WEBSOCKET_URL.clone(),
-        events,
-    );
+    let connect_url = url::Url::parse(&config.twitch_api.eventsub_websocket_url)
+        .context("when parsing EventSub websocket URL")?;
+    let websocket_client = websocket::WebsocketClient::new(connect_url, events);
 
+    let shard_id = conduits.acquire_shard().await?;
     let (join_handle, mut recv) = websocket_client.start(shutdown_signal)?;
 
     tokio::spawn(async move {
-        while let Some(xd) = recv.recv().await {
-            let shard = twitch_api::eventsub::Shard::new(
-                "1",
-                twitch_api::eventsub::Transport::websocket(xd),
-            );
-
-            let response = client
-                .update_conduit_shards(&first_conduit.id, vec![shard], &token)
-                .await;
-            tracing::info!("response: {response:?}");
+        while let Some(session_id) = recv.recv().await {
+            if let Err(e) = conduits.assign_shard(shard_id, &session_id).await {
+                tracing::error!(
+                    "Failed to receive events through websocket session {session_id}: {e:#}"
+                );
+            }
         }
     });
 
-    Ok(join_handle)
+    Ok(tokio::spawn(async move {
+        let result = join_handle.await;
+        conduits.release_shard(shard_id).await;
+        result.context("websocket task ended abnormally")?
+    }))
+}
+
+/// Reports changes to the authorizations the bot depends on, until `shutdown_signal` fires.
+/// Refreshed tokens need no handling, since tokens are looked up from the database whenever
+/// they are used.
+async fn watch_authorizations(
+    bot: Arc<BotContext>,
+    mut authorizations: broadcast::Receiver<AuthorizationEvent>,
+    shutdown_signal: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            event = authorizations.recv() => event,
+        };
+        match event {
+            Ok(AuthorizationEvent::Invalidated { user_id }) if user_id == bot.bot_user_id => {
+                tracing::error!(
+                    "The authorization of bot account {user_id} in channel {} was invalidated, it can't chat or moderate until it is authorized again",
+                    bot.broadcaster_user_id
+                );
+            }
+            Ok(AuthorizationEvent::Invalidated { user_id })
+                if user_id == bot.broadcaster_user_id =>
+            {
+                tracing::error!(
+                    "The authorization of broadcaster {user_id} was invalidated, channel events requiring it will stop until it is authorized again"
+                );
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
 }
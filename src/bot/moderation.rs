@@ -0,0 +1,332 @@
+use crate::api;
+use crate::api::twitch::moderation::ModerationRequestError;
+use crate::bot::BotContext;
+use crate::models::moderation_log::{self, Source};
+use crate::models::special_twitch_authorization;
+use crate::Config;
+use chrono::{DateTime, Utc};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, DatabaseConnection};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::{ChannelBanV1Payload, ChannelUnbanV1Payload};
+use twitch_types::Timestamp;
+
+/// How often a request is attempted before giving up, if it keeps failing with a transient error
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait time before the first retry, doubled for every further retry
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Rate limit resets further in the future than this are not waited for
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(10);
+/// Twitch allows timeouts of up to two weeks
+const MAX_TIMEOUT: Duration = Duration::from_secs(1209600);
+
+#[derive(Error, Debug)]
+pub enum ModerationError {
+    #[error("The bot account has not been authorized")]
+    NotAuthorized,
+    /// e.g. the target is already banned, or is a moderator
+    #[error("Twitch rejected the action: {0}")]
+    Rejected(String),
+    #[error("The bot is not allowed to moderate this channel: {0}")]
+    Forbidden(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Rate limited by Twitch")]
+    RateLimited,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<ModerationRequestError> for ModerationError {
+    fn from(e: ModerationRequestError) -> ModerationError {
+        match e {
+            ModerationRequestError::RateLimited { .. } => ModerationError::RateLimited,
+            ModerationRequestError::BadRequest(message) => ModerationError::Rejected(message),
+            ModerationRequestError::Unauthorized(_) => ModerationError::NotAuthorized,
+            ModerationRequestError::Forbidden(message) => ModerationError::Forbidden(message),
+            ModerationRequestError::NotFound(message) => ModerationError::NotFound(message),
+            e => ModerationError::Other(e.into()),
+        }
+    }
+}
+
+/// Performs moderation actions in a channel through Helix, using the special authorization
+/// of the moderator (usually the bot account of the channel). Successful actions are recorded
+/// in the moderation log on behalf of the actor.
+#[derive(Clone)]
+pub struct ModerationExecutor {
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    broadcaster_id: String,
+    moderator_id: String,
+    source: Source,
+    actor_id: String,
+}
+
+impl ModerationExecutor {
+    pub fn new(
+        config: &'static Config,
+        db: &'static DatabaseConnection,
+        broadcaster_id: impl Into<String>,
+        moderator_id: impl Into<String>,
+        source: Source,
+        actor_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            broadcaster_id: broadcaster_id.into(),
+            moderator_id: moderator_id.into(),
+            source,
+            actor_id: actor_id.into(),
+        }
+    }
+
+    pub async fn timeout(
+        &self,
+        user_id: &str,
+        duration: Duration,
+        reason: &str,
+    ) -> Result<(), ModerationError> {
+        let duration_seconds = duration
+            .clamp(Duration::from_secs(1), MAX_TIMEOUT)
+            .as_secs() as u32;
+        self.execute(|access_token| async move {
+            api::twitch::moderation::ban_user(
+                &self.config.twitch_api,
+                &access_token,
+                &self.broadcaster_id,
+                &self.moderator_id,
+                user_id,
+                Some(duration_seconds),
+                reason,
+            )
+            .await
+        })
+        .await?;
+        self.record(moderation_log::ActiveModel {
+            action: Set(moderation_log::Action::Timeout),
+            target_user_id: Set(Some(user_id.to_owned())),
+            duration_seconds: Set(Some(duration_seconds as i32)),
+            reason: Set(reason.to_owned()),
+            ..Default::default()
+        })
+        .await;
+        Ok(())
+    }
+
+    pub async fn ban(&self, user_id: &str, reason: &str) -> Result<(), ModerationError> {
+        self.execute(|access_token| async move {
+            api::twitch::moderation::ban_user(
+                &self.config.twitch_api,
+                &access_token,
+                &self.broadcaster_id,
+                &self.moderator_id,
+                user_id,
+                None,
+                reason,
+            )
+            .await
+        })
+        .await?;
+        self.record(moderation_log::ActiveModel {
+            action: Set(moderation_log::Action::Ban),
+            target_user_id: Set(Some(user_id.to_owned())),
+            reason: Set(reason.to_owned()),
+            ..Default::default()
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Lifts both bans and timeouts.
+    pub async fn unban(&self, user_id: &str) -> Result<(), ModerationError> {
+        self.execute(|access_token| async move {
+            api::twitch::moderation::unban_user(
+                &self.config.twitch_api,
+                &access_token,
+                &self.broadcaster_id,
+                &self.moderator_id,
+                user_id,
+            )
+            .await
+        })
+        .await?;
+        self.record(moderation_log::ActiveModel {
+            action: Set(moderation_log::Action::Unban),
+            target_user_id: Set(Some(user_id.to_owned())),
+            ..Default::default()
+        })
+        .await;
+        Ok(())
+    }
+
+    /// `user_id` is the author of the message, which is only used for the moderation log.
+    pub async fn delete_message(
+        &self,
+        message_id: &str,
+        user_id: Option<&str>,
+    ) -> Result<(), ModerationError> {
+        self.execute(|access_token| async move {
+            api::twitch::moderation::delete_chat_message(
+                &self.config.twitch_api,
+                &access_token,
+                &self.broadcaster_id,
+                &self.moderator_id,
+                message_id,
+            )
+            .await
+        })
+        .await?;
+        self.record(moderation_log::ActiveModel {
+            action: Set(moderation_log::Action::Delete),
+            target_user_id: Set(user_id.map(ToOwned::to_owned)),
+            message_id: Set(Some(message_id.to_owned())),
+            ..Default::default()
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Add the action to the moderation log. Failures are only logged, since the action
+    /// itself has already been performed at this point.
+    async fn record(&self, entry: moderation_log::ActiveModel) {
+        let entry = moderation_log::ActiveModel {
+            id: NotSet,
+            broadcaster_id: Set(self.broadcaster_id.clone()),
+            actor_id: Set(self.actor_id.clone()),
+            source: Set(self.source),
+            created_at: Set(Utc::now()),
+            ..entry
+        };
+        if let Err(e) = entry.insert(self.db).await {
+            tracing::error!(
+                "Failed to record moderation action in channel {}: {e}",
+                self.broadcaster_id
+            );
+        }
+    }
+
+    /// Run the request with the moderator's access token, retrying transient failures.
+    async fn execute<F, Fut>(&self, request: F) -> Result<(), ModerationError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<(), ModerationRequestError>>,
+    {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            // looked up for every attempt, since the token might have been refreshed in the meantime
+            let access_token =
+                special_twitch_authorization::find_access_token(&self.moderator_id, self.db)
+                    .await?
+                    .ok_or(ModerationError::NotAuthorized)?;
+
+            let e = match request(access_token).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if attempt >= MAX_ATTEMPTS || !e.is_transient() {
+                return Err(e.into());
+            }
+
+            let wait = match &e {
+                ModerationRequestError::RateLimited {
+                    reset_at: Some(reset_at),
+                } => (*reset_at - Utc::now())
+                    .to_std()
+                    .unwrap_or(backoff)
+                    .min(MAX_RATE_LIMIT_WAIT),
+                _ => backoff,
+            };
+            tracing::warn!(
+                "Moderation request in channel {} failed (attempt {attempt}/{MAX_ATTEMPTS}), retrying in {wait:?}: {e}",
+                self.broadcaster_id
+            );
+            tokio::time::sleep(wait).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+/// Records the bans, timeouts and unbans in the bot's channel that were performed by someone other
+/// than the bot in the moderation log, until `shutdown_signal` fires. The bot's own actions are
+/// already recorded by the [`ModerationExecutor`].
+pub async fn log_external_actions(
+    bot: Arc<BotContext>,
+    mut bans: broadcast::Receiver<Arc<ChannelBanV1Payload>>,
+    mut unbans: broadcast::Receiver<Arc<ChannelUnbanV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    loop {
+        let entry = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            ban = bans.recv() => match ban {
+                Ok(ban) if ban.moderator_user_id != bot.bot_user_id => ban_log_entry(&ban),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Moderation log lagged behind, skipped {skipped} ban events");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            unban = unbans.recv() => match unban {
+                Ok(unban) if unban.moderator_user_id != bot.bot_user_id => moderation_log::ActiveModel {
+                    id: NotSet,
+                    broadcaster_id: Set(unban.broadcaster_user_id.to_string()),
+                    action: Set(moderation_log::Action::Unban),
+                    actor_id: Set(unban.moderator_user_id.to_string()),
+                    target_user_id: Set(Some(unban.user_id.to_string())),
+                    source: Set(Source::Manual),
+                    created_at: Set(Utc::now()),
+                    ..Default::default()
+                },
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Moderation log lagged behind, skipped {skipped} unban events");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        if let Err(e) = entry.insert(bot.db).await {
+            tracing::error!("Failed to record external moderation action: {e}");
+        }
+    }
+}
+
+fn ban_log_entry(ban: &ChannelBanV1Payload) -> moderation_log::ActiveModel {
+    let banned_at = parse_timestamp(&ban.banned_at).unwrap_or_else(Utc::now);
+    let (action, duration_seconds) = match &ban.ends_at {
+        Some(ends_at) if !ban.is_permanent => (
+            moderation_log::Action::Timeout,
+            parse_timestamp(ends_at).map(|ends_at| (ends_at - banned_at).num_seconds() as i32),
+        ),
+        _ => (moderation_log::Action::Ban, None),
+    };
+    moderation_log::ActiveModel {
+        id: NotSet,
+        broadcaster_id: Set(ban.broadcaster_user_id.to_string()),
+        action: Set(action),
+        actor_id: Set(ban.moderator_user_id.to_string()),
+        target_user_id: Set(Some(ban.user_id.to_string())),
+        message_id: Set(None),
+        duration_seconds: Set(duration_seconds),
+        reason: Set(ban.reason.clone()),
+        source: Set(Source::Manual),
+        created_at: Set(banned_at),
+    }
+}
+
+fn parse_timestamp(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp.as_str())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok()
+}
@@ -0,0 +1,102 @@
+use crate::api::twitch::eventsub::Subscription;
+use crate::events::{ChannelEvent, ReplayError, EVENT_METRICS};
+use crate::models::subscription_revocation;
+use crate::{Events, SubscriptionRevoked};
+use thiserror::Error;
+use twitch_api::eventsub::{self, Event, EventSubscription, EventsubError};
+
+/// Why a message received from EventSub was skipped. None of these affect the transport it was
+/// received through.
+#[derive(Error, Debug)]
+pub enum EventError {
+    #[error("Failed to parse EventSub message: {0}")]
+    Parse(#[from] EventsubError),
+    #[error("Failed to parse revocation message: {0}")]
+    ParseRevocation(#[from] serde_json::Error),
+    #[error("Revoked {0} subscription belongs to no channel")]
+    RevocationWithoutChannel(String),
+    #[error("Received a message that is not a notification for a {0} subscription")]
+    UnexpectedMessage(&'static str),
+    #[error("Received a notification of unhandled event type {0}")]
+    UnknownEvent(String),
+    #[error("Received an EventSub message of unknown type")]
+    UnknownMessage,
+    #[error("{0}")]
+    Replay(#[from] ReplayError),
+}
+
+/// Count and log a message that could not be handled.
+pub fn skip(error: EventError) {
+    match error {
+        // redeliveries are expected now and then
+        EventError::Replay(ReplayError::Duplicate(_)) => {
+            EVENT_METRICS.count_duplicate();
+            tracing::debug!("{error}");
+            return;
+        }
+        EventError::Replay(ReplayError::Stale { .. }) => EVENT_METRICS.count_stale(),
+        EventError::UnknownEvent(_) | EventError::UnknownMessage => EVENT_METRICS.count_unknown(),
+        _ => EVENT_METRICS.count_malformed(),
+    }
+    tracing::warn!("{error}");
+}
+
+/// Publish a notification on the event bus. `subscription_type` describes the event for the
+/// error if it is of a type the bot does not handle.
+pub fn publish(
+    events: &Events,
+    event: Event,
+    subscription_type: impl std::fmt::Display,
+) -> Result<(), EventError> {
+    match event {
+        Event::ChannelChatMessageV1(payload) => publish_payload(events, payload),
+        Event::ChannelBanV1(payload) => publish_payload(events, payload),
+        Event::ChannelUnbanV1(payload) => publish_payload(events, payload),
+        Event::ChannelFollowV2(payload) => publish_payload(events, payload),
+        Event::ChannelSubscribeV1(payload) => publish_payload(events, payload),
+        Event::ChannelCheerV1(payload) => publish_payload(events, payload),
+        Event::ChannelRaidV1(payload) => publish_payload(events, payload),
+        Event::ChannelUpdateV2(payload) => publish_payload(events, payload),
+        Event::StreamOnlineV1(payload) => publish_payload(events, payload),
+        Event::StreamOfflineV1(payload) => publish_payload(events, payload),
+        Event::ChannelPointsCustomRewardRedemptionAddV1(payload) => {
+            publish_payload(events, payload)
+        }
+        _ => Err(EventError::UnknownEvent(subscription_type.to_string())),
+    }
+}
+
+fn publish_payload<E>(events: &Events, payload: eventsub::Payload<E>) -> Result<(), EventError>
+where
+    E: EventSubscription,
+    E::Payload: ChannelEvent,
+{
+    match payload.message {
+        eventsub::Message::Notification(message) => {
+            events.publish(message);
+            Ok(())
+        }
+        _ => Err(EventError::UnexpectedMessage(std::any::type_name::<E>())),
+    }
+}
+
+/// Publish that Twitch revoked a subscription, for the bot of its channel to handle.
+pub fn publish_revocation(events: &Events, subscription: Subscription) -> Result<(), EventError> {
+    let broadcaster_id = subscription
+        .broadcaster_id()
+        .ok_or_else(|| EventError::RevocationWithoutChannel(subscription.kind.clone()))?;
+    let revoked = SubscriptionRevoked {
+        broadcaster_id: broadcaster_id.into(),
+        reason: subscription_revocation::Reason::from_status(&subscription.status),
+        subscription_type: subscription.kind,
+        subscription_version: subscription.version,
+    };
+    tracing::warn!(
+        "Twitch revoked the {} subscription of channel {}: {:?}",
+        revoked.subscription_type,
+        revoked.broadcaster_id,
+        revoked.reason
+    );
+    events.publish(revoked);
+    Ok(())
+}
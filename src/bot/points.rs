@@ -0,0 +1,162 @@
+use crate::bot::commands::{is_subscriber, Permission};
+use crate::bot::BotContext;
+use crate::models::user::{self, UserBasics};
+use crate::models::{points_settings, user_channel_points};
+use anyhow::Context;
+use sea_orm::TransactionTrait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+use twitch_types::UserId;
+
+/// Wait time before trying again if the settings could not be loaded
+const SETTINGS_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A chatter who may earn points, as of their last chat message
+struct Chatter {
+    login: String,
+    display_name: String,
+    last_seen: Instant,
+    subscriber: bool,
+    vip: bool,
+}
+
+impl Chatter {
+    /// Points the chatter earns per interval, the higher multiplier counts if they have more than
+    /// one.
+    fn points_per_interval(&self, settings: &points_settings::Model) -> i64 {
+        let percent = [
+            (self.subscriber, settings.subscriber_multiplier_percent),
+            (self.vip, settings.vip_multiplier_percent),
+        ]
+        .into_iter()
+        .filter(|(applies, _)| *applies)
+        .map(|(_, percent)| percent)
+        .max()
+        .unwrap_or(100);
+        i64::from(settings.points_per_interval) * i64::from(percent) / 100
+    }
+}
+
+/// Gives points to the active chatters of the bot's channel every interval while the stream is
+/// online, until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let mut chatters = HashMap::new();
+    let mut next_award = Instant::now() + interval(&bot).await;
+
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            message = chat_messages.recv() => match message {
+                Ok(message) => {
+                    if message.chatter_user_id != bot.bot_user_id {
+                        see(&mut chatters, &message);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Points tracker lagged behind, skipped {skipped} chat messages");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = tokio::time::sleep_until(next_award) => {
+                // settings are loaded every time, so changes take effect without restarting the bot
+                let settings = match points_settings::find_or_default(bot.broadcaster_user_id.as_str(), bot.db).await {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        tracing::error!("Failed to load points settings: {e:#}");
+                        next_award = Instant::now() + SETTINGS_RETRY_INTERVAL;
+                        continue;
+                    }
+                };
+                next_award = Instant::now() + settings_interval(&settings);
+                let active_for = Duration::from_secs(settings.active_seconds as u64);
+                chatters.retain(|_, chatter| chatter.last_seen.elapsed() < active_for);
+                if !bot.is_live() || !settings.enabled {
+                    continue;
+                }
+                if let Err(e) = award(&bot, &settings, &chatters).await {
+                    tracing::error!(
+                        "Failed to give points in channel {}: {e:#}",
+                        bot.broadcaster_user_id
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn settings_interval(settings: &points_settings::Model) -> Duration {
+    Duration::from_secs(settings.interval_seconds as u64)
+}
+
+async fn interval(bot: &BotContext) -> Duration {
+    match points_settings::find_or_default(bot.broadcaster_user_id.as_str(), bot.db).await {
+        Ok(settings) => settings_interval(&settings),
+        Err(e) => {
+            tracing::error!("Failed to load points settings: {e:#}");
+            SETTINGS_RETRY_INTERVAL
+        }
+    }
+}
+
+fn see(chatters: &mut HashMap<UserId, Chatter>, message: &ChannelChatMessageV1Payload) {
+    chatters.insert(
+        message.chatter_user_id.clone(),
+        Chatter {
+            login: message.chatter_user_login.to_string(),
+            display_name: message.chatter_user_name.to_string(),
+            last_seen: Instant::now(),
+            subscriber: is_subscriber(message),
+            vip: Permission::of_chatter(message) == Permission::Vip,
+        },
+    );
+}
+
+/// Give every chatter their points for one interval, all or none of them.
+async fn award(
+    bot: &BotContext,
+    settings: &points_settings::Model,
+    chatters: &HashMap<UserId, Chatter>,
+) -> anyhow::Result<()> {
+    let tx = bot.db.begin().await.context("award begin transaction")?;
+    for (user_id, chatter) in chatters {
+        let points = chatter.points_per_interval(settings);
+        if points == 0 {
+            continue;
+        }
+        // points are keyed by the user table, which only knows users who logged in so far
+        user::upsert_user(
+            UserBasics {
+                id: user_id.to_string(),
+                login: chatter.login.clone(),
+                display_name: chatter.display_name.clone(),
+            },
+            &tx,
+        )
+        .await
+        .context("award upsert user")?;
+        user_channel_points::add_points(
+            bot.broadcaster_user_id.as_str(),
+            user_id.as_str(),
+            points,
+            &tx,
+        )
+        .await
+        .context("award add points")?;
+    }
+    tx.commit().await.context("award commit")?;
+    tracing::debug!(
+        "Gave points to {} chatters in channel {}",
+        chatters.len(),
+        bot.broadcaster_user_id
+    );
+    Ok(())
+}
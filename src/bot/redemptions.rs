@@ -0,0 +1,217 @@
+use crate::api;
+use crate::api::twitch::channel_points::RedemptionStatus;
+use crate::bot::commands::variables::{self, TemplateValues};
+use crate::bot::BotContext;
+use crate::models::moderation_log::Source;
+use crate::models::reward_action::{self, Action};
+use crate::models::special_authorization_state::Kind;
+use crate::models::{command, redemption_queue_entry, special_twitch_authorization};
+use crate::Config;
+use anyhow::{anyhow, Context};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::channel_points_custom_reward_redemption;
+use twitch_api::eventsub::channel::ChannelPointsCustomRewardRedemptionAddV1Payload;
+
+/// Scope the broadcaster has to have granted to fulfill or cancel redemptions
+const MANAGE_REDEMPTIONS_SCOPE: &str = "channel:manage:redemptions";
+
+/// Carries out the actions bound to the channel point rewards of the bot's channel whenever one
+/// is redeemed, until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut redemptions: broadcast::Receiver<Arc<ChannelPointsCustomRewardRedemptionAddV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    loop {
+        let redemption = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            redemption = redemptions.recv() => redemption,
+        };
+        let redemption = match redemption {
+            Ok(redemption) => redemption,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Redemption handler lagged behind, skipped {skipped} redemptions");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        // Run actions in their own task, so a timeout being retried does not hold up the channel
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(&bot, &redemption).await {
+                tracing::error!(
+                    "Failed to handle redemption of reward {} in channel {}: {e:#}",
+                    redemption.reward.id,
+                    bot.broadcaster_user_id
+                );
+            }
+        });
+    }
+}
+
+async fn handle(
+    bot: &BotContext,
+    redemption: &ChannelPointsCustomRewardRedemptionAddV1Payload,
+) -> anyhow::Result<()> {
+    let Some(action) = reward_action::find_by_reward(
+        bot.broadcaster_user_id.as_str(),
+        redemption.reward.id.as_str(),
+        bot.db,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let result = execute(bot, &action, redemption).await;
+    // queued redemptions are completed once they leave the queue
+    if action.update_redemption
+        && is_unfulfilled(redemption)
+        && !(action.action == Action::Queue && result.is_ok())
+    {
+        let status = match result {
+            Ok(()) => RedemptionStatus::Fulfilled,
+            Err(_) => RedemptionStatus::Canceled,
+        };
+        if let Err(e) = update_status(
+            bot.config,
+            bot.db,
+            bot.broadcaster_user_id.as_str(),
+            redemption.reward.id.as_str(),
+            redemption.id.as_str(),
+            status,
+        )
+        .await
+        {
+            tracing::warn!(
+                "Failed to mark redemption {} as {status:?}: {e:#}",
+                redemption.id
+            );
+        }
+    }
+    result
+}
+
+async fn execute(
+    bot: &BotContext,
+    action: &reward_action::Model,
+    redemption: &ChannelPointsCustomRewardRedemptionAddV1Payload,
+) -> anyhow::Result<()> {
+    match action.action {
+        Action::Command => {
+            let trigger = required(&action.command_trigger, action, "command trigger")?;
+            let command =
+                command::find_by_trigger(bot.broadcaster_user_id.as_str(), &trigger, bot.db)
+                    .await?
+                    .ok_or_else(|| anyhow!("There is no enabled command `{trigger}`"))?;
+            let count = command::increment_use_count(command.id, bot.db).await?;
+            let values = TemplateValues {
+                bot,
+                user: Some(&redemption.user_name),
+                args: &redemption.user_input,
+                count: Some(count),
+            };
+            bot.chat.send(variables::render(&command.response, &values));
+        }
+        Action::Message => {
+            let template = required(&action.message, action, "message")?;
+            let values = TemplateValues {
+                bot,
+                user: Some(&redemption.user_name),
+                args: &redemption.user_input,
+                count: None,
+            };
+            bot.chat.send(variables::render(&template, &values));
+        }
+        Action::Timeout => {
+            let seconds = required(&action.timeout_seconds, action, "timeout duration")?;
+            // the redeemer asked for it, so they are the one responsible
+            bot.moderation(Source::Redemption, redemption.user_id.as_str())
+                .timeout(
+                    redemption.user_id.as_str(),
+                    Duration::from_secs(seconds.max(1) as u64),
+                    &format!("Redeemed {}", redemption.reward.title),
+                )
+                .await?;
+        }
+        Action::Queue => {
+            let queue_name = required(&action.queue_name, action, "queue name")?;
+            redemption_queue_entry::ActiveModel {
+                id: NotSet,
+                broadcaster_id: Set(bot.broadcaster_user_id.to_string()),
+                queue_name: Set(queue_name),
+                reward_id: Set(redemption.reward.id.to_string()),
+                redemption_id: Set(redemption.id.to_string()),
+                user_id: Set(redemption.user_id.to_string()),
+                user_login: Set(redemption.user_login.to_string()),
+                user_name: Set(redemption.user_name.to_string()),
+                user_input: Set(redemption.user_input.clone()),
+                update_redemption: Set(action.update_redemption && is_unfulfilled(redemption)),
+                created_at: NotSet,
+            }
+            .insert(bot.db)
+            .await
+            .context("execute insert queue entry")?;
+        }
+    }
+    Ok(())
+}
+
+/// Only unfulfilled redemptions can be fulfilled or canceled. Rewards that skip the request queue
+/// are fulfilled by Twitch right away.
+fn is_unfulfilled(redemption: &ChannelPointsCustomRewardRedemptionAddV1Payload) -> bool {
+    matches!(
+        redemption.status,
+        channel_points_custom_reward_redemption::RedemptionStatus::Unfulfilled
+    )
+}
+
+/// A setting of a reward action that its type needs, which the API ensures is set.
+fn required<T: Clone>(
+    value: &Option<T>,
+    action: &reward_action::Model,
+    name: &str,
+) -> anyhow::Result<T> {
+    value
+        .clone()
+        .ok_or_else(|| anyhow!("Reward action {} has no {name}", action.id))
+}
+
+/// Fulfill or cancel a redemption through Helix as the broadcaster. Fails if Twitch does not
+/// allow it, e.g. because the reward was not created with our client ID.
+pub async fn update_status(
+    config: &Config,
+    db: &impl ConnectionTrait,
+    broadcaster_id: &str,
+    reward_id: &str,
+    redemption_id: &str,
+    status: RedemptionStatus,
+) -> anyhow::Result<()> {
+    let authorization = special_twitch_authorization::Entity::find_by_id(broadcaster_id)
+        .one(db)
+        .await
+        .context("update_status find authorization")?
+        .filter(|authorization| authorization.invalidated_at.is_none())
+        .ok_or_else(|| anyhow!("Broadcaster {broadcaster_id} has not been authorized"))?;
+    if !authorization.has_scope(Kind::Broadcaster, MANAGE_REDEMPTIONS_SCOPE) {
+        anyhow::bail!(
+            "Broadcaster {broadcaster_id} has to authorize again to allow managing redemptions"
+        );
+    }
+    api::twitch::channel_points::update_redemption_status(
+        &config.twitch_api,
+        &authorization.twitch_access_token,
+        broadcaster_id,
+        reward_id,
+        redemption_id,
+        status,
+    )
+    .await?;
+    Ok(())
+}
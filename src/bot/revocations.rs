@@ -0,0 +1,141 @@
+use crate::bot::subscriptions::{self, SubscriptionOutcome, SubscriptionReport};
+use crate::bot::BotContext;
+use crate::models::subscription_revocation::{self, Reason};
+use crate::SubscriptionRevoked;
+use anyhow::Context;
+use sea_orm::ActiveValue::Set;
+use sea_orm::EntityTrait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// How often subscribing again is attempted while there are unresolved revocations, e.g. until
+/// the user authorized the application again
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Records the subscriptions Twitch revokes in the bot's channel and subscribes to them again
+/// once that is possible, until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut revocations: broadcast::Receiver<Arc<SubscriptionRevoked>>,
+    shutdown_signal: CancellationToken,
+) {
+    // the bot subscribed to everything possible right before this started
+    let mut interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + RESUBSCRIBE_INTERVAL,
+        RESUBSCRIBE_INTERVAL,
+    );
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            revoked = revocations.recv() => match revoked {
+                Ok(revoked) => {
+                    if let Err(e) = record(&bot, &revoked).await {
+                        tracing::error!("Failed to record revoked subscription: {e:#}");
+                    }
+                    // subscriptions revoked for other reasons are worth retrying right away,
+                    // e.g. after Twitch had an outage
+                    if revoked.reason != Reason::Other {
+                        continue;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => {}
+        }
+
+        if let Err(e) = resubscribe(&bot).await {
+            tracing::warn!(
+                "Failed to subscribe again in channel {}: {e:#}",
+                bot.broadcaster_user_id
+            );
+        }
+    }
+}
+
+async fn record(bot: &BotContext, revoked: &SubscriptionRevoked) -> anyhow::Result<()> {
+    subscription_revocation::Entity::insert(subscription_revocation::ActiveModel {
+        broadcaster_id: Set(revoked.broadcaster_id.to_string()),
+        subscription_type: Set(revoked.subscription_type.clone()),
+        subscription_version: Set(revoked.subscription_version.clone()),
+        reason: Set(revoked.reason),
+        ..Default::default()
+    })
+    .exec(bot.db)
+    .await
+    .context("record insert revocation")?;
+
+    let kind = &revoked.subscription_type;
+    match revoked.reason {
+        Reason::AuthorizationRevoked => {
+            // chat messages are received through the bot account, everything else through the
+            // broadcaster
+            let user_id = if kind == "channel.chat.message" {
+                &bot.bot_user_id
+            } else {
+                &bot.broadcaster_user_id
+            };
+            tracing::error!(
+                "User {user_id} revoked the authorization needed for {kind} events in channel {}, they need to authorize again",
+                bot.broadcaster_user_id
+            );
+        }
+        Reason::UserRemoved => tracing::error!(
+            "A user needed for {kind} events in channel {} was removed from Twitch",
+            bot.broadcaster_user_id
+        ),
+        Reason::ModeratorRemoved => tracing::error!(
+            "The moderator needed for {kind} events in channel {} is no moderator anymore",
+            bot.broadcaster_user_id
+        ),
+        Reason::VersionRemoved => tracing::error!(
+            "Twitch no longer supports version {} of {kind} events, the desired subscriptions need to be updated",
+            revoked.subscription_version
+        ),
+        Reason::Other => {}
+    }
+    Ok(())
+}
+
+/// Subscribe to all desired events again, if there are revocations to recover from.
+async fn resubscribe(bot: &BotContext) -> anyhow::Result<()> {
+    let unresolved =
+        subscription_revocation::find_unresolved(bot.broadcaster_user_id.as_str(), bot.db)
+            .await
+            .context("resubscribe find revocations")?;
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+
+    let report = subscriptions::reconcile(
+        bot.config,
+        bot.db,
+        bot.conduits,
+        bot.broadcaster_user_id.as_str(),
+        bot.bot_user_id.as_str(),
+        subscriptions::DESIRED_SUBSCRIPTIONS,
+    )
+    .await?;
+    subscriptions::log_report(bot.broadcaster_user_id.as_str(), &report);
+    resolve(bot, &report).await
+}
+
+/// Mark the revocations of all subscriptions that exist again as resolved.
+pub async fn resolve(bot: &BotContext, report: &SubscriptionReport) -> anyhow::Result<()> {
+    let active = report
+        .iter()
+        .filter(|(_, outcome)| {
+            matches!(
+                outcome,
+                SubscriptionOutcome::Active | SubscriptionOutcome::Created
+            )
+        })
+        .map(|(kind, _)| *kind)
+        .collect::<Vec<_>>();
+    subscription_revocation::resolve(bot.broadcaster_user_id.as_str(), &active, bot.db)
+        .await
+        .context("resolve revocations")
+}
@@ -0,0 +1,58 @@
+use crate::models::special_authorization_state::Kind;
+use crate::models::special_twitch_authorization::{self, AuthorizationStatus};
+use anyhow::Context;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How often authorizations are checked for outdated scope versions, in addition to at startup
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Reports authorizations that were granted with an older scope version than the current one,
+/// until `shutdown_signal` fires. Features needing the newer scopes check for them on their own
+/// and stay disabled until the user authorizes again, which the dashboard asks them to do based on
+/// the channel authorization status.
+pub async fn run(
+    db: &'static DatabaseConnection,
+    shutdown_signal: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        if let Err(e) = check(db).await {
+            tracing::error!("Failed to check authorization scope versions: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+async fn check(db: &'static DatabaseConnection) -> anyhow::Result<()> {
+    let authorizations = special_twitch_authorization::Entity::find()
+        .filter(special_twitch_authorization::Column::InvalidatedAt.is_null())
+        .all(db)
+        .await
+        .context("check find authorizations")?;
+
+    for authorization in authorizations {
+        let outdated_bot =
+            authorization.status(Kind::Bot) == AuthorizationStatus::ReauthorizeRequired;
+        let outdated_broadcaster =
+            authorization.status(Kind::Broadcaster) == AuthorizationStatus::ReauthorizeRequired;
+        if !(outdated_bot || outdated_broadcaster) {
+            continue;
+        }
+
+        tracing::warn!(
+            "User {} authorized with an outdated scope version (bot: {:?}, broadcaster: {:?}) and needs to authorize again",
+            authorization.user_id,
+            authorization.bot_scope_version,
+            authorization.broadcaster_scope_version
+        );
+    }
+    Ok(())
+}
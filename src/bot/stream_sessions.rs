@@ -0,0 +1,239 @@
+use crate::api;
+use crate::bot::BotContext;
+use crate::models::stream_session::{self, StreamInfo};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sea_orm::TransactionTrait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+use twitch_api::eventsub::channel::ChannelUpdateV2Payload;
+use twitch_api::eventsub::stream::{StreamOfflineV1Payload, StreamOnlineV1Payload};
+
+/// How often the stream is looked up through Helix, to sample its viewer count and to notice
+/// online and offline events that were missed
+const POLL_INTERVAL: Duration = Duration::from_secs(300);
+/// Helix needs a moment to list a stream that just went live, with its title and category
+const POLL_DELAY_AFTER_ONLINE: Duration = Duration::from_secs(60);
+/// Helix not listing a stream that went live more recently than this does not mean it ended
+const OFFLINE_GRACE_SECONDS: i64 = 300;
+
+/// Records the streams of the bot's channel as sessions and publishes the live session through
+/// `live`, until `shutdown_signal` fires.
+pub async fn run(
+    bot: Arc<BotContext>,
+    live: watch::Sender<Option<stream_session::Model>>,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    mut stream_online: broadcast::Receiver<Arc<StreamOnlineV1Payload>>,
+    mut stream_offline: broadcast::Receiver<Arc<StreamOfflineV1Payload>>,
+    mut channel_updates: broadcast::Receiver<Arc<ChannelUpdateV2Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let mut tracker = Tracker {
+        bot,
+        live,
+        chat_messages: 0,
+    };
+    // a session left open by the last run is ended by the first poll if the stream is over
+    match stream_session::find_live(tracker.bot.broadcaster_user_id.as_str(), tracker.bot.db).await
+    {
+        Ok(session) => {
+            tracker.live.send_replace(session);
+        }
+        Err(e) => tracing::warn!(
+            "Failed to restore the stream session of channel {}: {e:#}",
+            tracker.bot.broadcaster_user_id
+        ),
+    }
+    // the first tick completes immediately
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        let result = tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            message = chat_messages.recv() => match message {
+                Ok(message) => {
+                    let live = tracker.live.borrow().is_some();
+                    if live && message.chatter_user_id != tracker.bot.bot_user_id {
+                        tracker.chat_messages += 1;
+                    }
+                    Ok(())
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => Ok(()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = stream_online.recv() => match event {
+                Ok(event) => {
+                    poll.reset_after(POLL_DELAY_AFTER_ONLINE);
+                    let started_at = DateTime::parse_from_rfc3339(event.started_at.as_str())
+                        .map_or_else(|_| Utc::now(), |started_at| started_at.with_timezone(&Utc));
+                    tracker.go_online(event.id.as_str(), started_at, None).await
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => Ok(()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = stream_offline.recv() => match event {
+                Ok(_) => tracker.go_offline().await,
+                Err(broadcast::error::RecvError::Lagged(_)) => Ok(()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = channel_updates.recv() => match event {
+                Ok(event) => {
+                    tracker
+                        .change(StreamInfo {
+                            title: event.title.clone(),
+                            category_id: event.category_id.to_string(),
+                            category_name: event.category_name.clone(),
+                        })
+                        .await
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => Ok(()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = poll.tick() => tracker.poll().await,
+        };
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to track the stream of channel {}: {e:#}",
+                tracker.bot.broadcaster_user_id
+            );
+        }
+    }
+
+    if let Err(e) = tracker.flush(None).await {
+        tracing::warn!("Failed to save stream statistics before shutting down: {e:#}");
+    }
+}
+
+struct Tracker {
+    bot: Arc<BotContext>,
+    live: watch::Sender<Option<stream_session::Model>>,
+    /// Chat messages of the live session that were not saved yet
+    chat_messages: i64,
+}
+
+impl Tracker {
+    fn live_session(&self) -> Option<stream_session::Model> {
+        self.live.borrow().clone()
+    }
+
+    async fn go_online(
+        &mut self,
+        twitch_stream_id: &str,
+        started_at: DateTime<Utc>,
+        info: Option<StreamInfo>,
+    ) -> anyhow::Result<()> {
+        if self
+            .live_session()
+            .is_some_and(|session| session.twitch_stream_id != twitch_stream_id)
+        {
+            // the messages still belong to the previous stream
+            self.flush(None).await?;
+        }
+
+        let db = self.bot.db;
+        let tx = db.begin().await.context("go_online begin transaction")?;
+        let session = stream_session::start(
+            self.bot.broadcaster_user_id.as_str(),
+            twitch_stream_id,
+            started_at,
+            &tx,
+        )
+        .await
+        .context("go_online start session")?;
+        if let Some(info) = info {
+            stream_session::record_change(session.id, info, &tx)
+                .await
+                .context("go_online record change")?;
+        }
+        tx.commit().await.context("go_online commit")?;
+
+        if self.live_session().as_ref().map(|live| live.id) != Some(session.id) {
+            tracing::info!(
+                "Channel {} went live, tracking stream session {}",
+                self.bot.broadcaster_user_id,
+                session.id
+            );
+        }
+        self.live.send_replace(Some(session));
+        Ok(())
+    }
+
+    async fn go_offline(&mut self) -> anyhow::Result<()> {
+        let Some(session) = self.live_session() else {
+            return Ok(());
+        };
+        self.flush(None).await?;
+        stream_session::end(session.id, Utc::now(), self.bot.db)
+            .await
+            .context("go_offline end session")?;
+        tracing::info!(
+            "Channel {} went offline, ended stream session {}",
+            self.bot.broadcaster_user_id,
+            session.id
+        );
+        self.live.send_replace(None);
+        Ok(())
+    }
+
+    /// Title and category changes only matter while the channel is live.
+    async fn change(&mut self, info: StreamInfo) -> anyhow::Result<()> {
+        let Some(session) = self.live_session() else {
+            return Ok(());
+        };
+        stream_session::record_change(session.id, info, self.bot.db)
+            .await
+            .context("change record change")
+    }
+
+    async fn poll(&mut self) -> anyhow::Result<()> {
+        let access_token = self.bot.bot_access_token().await?;
+        let stream = api::twitch::stream::get_stream(
+            &self.bot.config.twitch_api,
+            &access_token,
+            self.bot.broadcaster_user_id.as_str(),
+        )
+        .await?;
+
+        match stream {
+            Some(stream) => {
+                let info = StreamInfo {
+                    title: stream.title,
+                    category_id: stream.game_id,
+                    category_name: stream.game_name,
+                };
+                self.go_online(&stream.id, stream.started_at, Some(info))
+                    .await?;
+                self.flush(Some(i32::try_from(stream.viewer_count).unwrap_or(i32::MAX)))
+                    .await
+            }
+            None => {
+                let Some(session) = self.live_session() else {
+                    return Ok(());
+                };
+                if (Utc::now() - session.started_at).num_seconds() < OFFLINE_GRACE_SECONDS {
+                    return Ok(());
+                }
+                self.go_offline().await
+            }
+        }
+    }
+
+    /// Save the chat messages counted so far and the current viewer count to the live session.
+    async fn flush(&mut self, viewers: Option<i32>) -> anyhow::Result<()> {
+        let Some(session) = self.live_session() else {
+            self.chat_messages = 0;
+            return Ok(());
+        };
+        if self.chat_messages == 0 && viewers.is_none() {
+            return Ok(());
+        }
+        stream_session::add_stats(session.id, self.chat_messages, viewers, self.bot.db)
+            .await
+            .context("flush add stats")?;
+        self.chat_messages = 0;
+        Ok(())
+    }
+}
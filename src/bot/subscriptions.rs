@@ -0,0 +1,203 @@
+use crate::api::twitch::eventsub::{self, EventSubRequestError};
+use crate::bot::conduit::ConduitManager;
+use crate::models::special_authorization_state::Kind;
+use crate::models::special_twitch_authorization;
+use crate::Config;
+use anyhow::Context;
+use http::StatusCode;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use std::collections::BTreeMap;
+
+/// Which users a subscription condition refers to.
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    /// `broadcaster_user_id`
+    Broadcaster,
+    /// `broadcaster_user_id` and the bot as `user_id`, for events the bot receives itself
+    BroadcasterAndBot,
+    /// `broadcaster_user_id`, with the broadcaster as `moderator_user_id` as well
+    BroadcasterAsModerator,
+    /// `to_broadcaster_user_id`, e.g. for raids into the channel
+    ToBroadcaster,
+}
+
+/// An EventSub subscription every channel with a bot should have.
+#[derive(Debug, Clone, Copy)]
+pub struct DesiredSubscription {
+    kind: &'static str,
+    version: &'static str,
+    condition: Condition,
+    /// Scope the broadcaster has to have granted for Twitch to allow the subscription
+    broadcaster_scope: Option<&'static str>,
+}
+
+const fn desired(
+    kind: &'static str,
+    version: &'static str,
+    condition: Condition,
+    broadcaster_scope: Option<&'static str>,
+) -> DesiredSubscription {
+    DesiredSubscription {
+        kind,
+        version,
+        condition,
+        broadcaster_scope,
+    }
+}
+
+#[rustfmt::skip]
+pub const DESIRED_SUBSCRIPTIONS: &[DesiredSubscription] = &[
+    desired("channel.chat.message", "1", Condition::BroadcasterAndBot, None),
+    desired("channel.ban", "1", Condition::Broadcaster, Some("channel:moderate")),
+    desired("channel.unban", "1", Condition::Broadcaster, Some("channel:moderate")),
+    desired("channel.follow", "2", Condition::BroadcasterAsModerator, Some("moderator:read:followers")),
+    desired("channel.cheer", "1", Condition::Broadcaster, Some("bits:read")),
+    desired("channel.raid", "1", Condition::ToBroadcaster, None),
+    desired("channel.subscribe", "1", Condition::Broadcaster, Some("channel:read:subscriptions")),
+    desired("channel.update", "2", Condition::Broadcaster, None),
+    desired("stream.online", "1", Condition::Broadcaster, None),
+    desired("stream.offline", "1", Condition::Broadcaster, None),
+    desired("channel.channel_points_custom_reward_redemption.add", "1", Condition::Broadcaster, Some("channel:read:redemptions")),
+];
+
+impl DesiredSubscription {
+    fn condition(&self, broadcaster_id: &str, bot_id: &str) -> BTreeMap<String, String> {
+        let users: &[(&str, &str)] = match self.condition {
+            Condition::Broadcaster => &[("broadcaster_user_id", broadcaster_id)],
+            Condition::BroadcasterAndBot => {
+                &[("broadcaster_user_id", broadcaster_id), ("user_id", bot_id)]
+            }
+            Condition::BroadcasterAsModerator => &[
+                ("broadcaster_user_id", broadcaster_id),
+                ("moderator_user_id", broadcaster_id),
+            ],
+            Condition::ToBroadcaster => &[("to_broadcaster_user_id", broadcaster_id)],
+        };
+        users
+            .iter()
+            .map(|(key, user_id)| (key.to_string(), user_id.to_string()))
+            .collect()
+    }
+}
+
+/// What became of a desired subscription during reconciliation.
+#[derive(Debug)]
+pub enum SubscriptionOutcome {
+    /// It already existed and is enabled
+    Active,
+    Created,
+    /// The broadcaster has not granted the scope needed for it yet
+    MissingScope(&'static str),
+    Failed(anyhow::Error),
+}
+
+/// Outcome of a reconciliation for each desired subscription type.
+pub type SubscriptionReport = Vec<(&'static str, SubscriptionOutcome)>;
+
+/// Bring the subscriptions of the channel of `broadcaster_id` in line with `desired`: existing
+/// subscriptions are listed, missing ones created, and those that are not desired anymore, are
+/// disabled or use another transport are deleted.
+pub async fn reconcile(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    conduits: &'static ConduitManager,
+    broadcaster_id: &str,
+    bot_id: &str,
+    desired: &[DesiredSubscription],
+) -> anyhow::Result<SubscriptionReport> {
+    let token = conduits.access_token().await?;
+    let broadcaster_authorization =
+        special_twitch_authorization::Entity::find_by_id(broadcaster_id)
+            .one(db)
+            .await
+            .context("reconcile find broadcaster authorization")?;
+
+    let existing = eventsub::get_subscriptions_for_user(&config.twitch_api, &token, broadcaster_id)
+        .await
+        .context("when listing subscriptions")?
+        .into_iter()
+        // the user is also mentioned by subscriptions of other channels, e.g. if they are
+        // the bot there
+        .filter(|subscription| subscription.broadcaster_id() == Some(broadcaster_id));
+
+    let mut satisfied = vec![false; desired.len()];
+    for subscription in existing {
+        let matching = desired.iter().position(|wanted| {
+            subscription.kind == wanted.kind
+                && subscription.version == wanted.version
+                && subscription.condition == wanted.condition(broadcaster_id, bot_id)
+        });
+        let usable = subscription.status == "enabled"
+            && subscription.transport.conduit_id.as_deref() == Some(conduits.conduit_id());
+        match matching {
+            Some(index) if usable && !satisfied[index] => satisfied[index] = true,
+            _ => {
+                tracing::info!(
+                    "Deleting obsolete {} subscription {} ({}) of channel {broadcaster_id}",
+                    subscription.kind,
+                    subscription.id,
+                    subscription.status
+                );
+                if let Err(e) =
+                    eventsub::delete_subscription(&config.twitch_api, &token, &subscription.id)
+                        .await
+                {
+                    tracing::warn!("Failed to delete subscription {}: {e}", subscription.id);
+                }
+            }
+        }
+    }
+
+    let mut report = vec![];
+    for (wanted, satisfied) in desired.iter().zip(satisfied) {
+        let missing_scope = wanted.broadcaster_scope.filter(|scope| {
+            !broadcaster_authorization
+                .as_ref()
+                .is_some_and(|authorization| authorization.has_scope(Kind::Broadcaster, scope))
+        });
+        let outcome = if satisfied {
+            SubscriptionOutcome::Active
+        } else if let Some(scope) = missing_scope {
+            SubscriptionOutcome::MissingScope(scope)
+        } else {
+            match eventsub::create_subscription(
+                &config.twitch_api,
+                &token,
+                wanted.kind,
+                wanted.version,
+                &wanted.condition(broadcaster_id, bot_id),
+                conduits.conduit_id(),
+            )
+            .await
+            {
+                Ok(_) => SubscriptionOutcome::Created,
+                // created concurrently, e.g. by a restarting bot
+                Err(EventSubRequestError::Status {
+                    status: StatusCode::CONFLICT,
+                    ..
+                }) => SubscriptionOutcome::Active,
+                Err(e) => SubscriptionOutcome::Failed(e.into()),
+            }
+        };
+        report.push((wanted.kind, outcome));
+    }
+    Ok(report)
+}
+
+/// Log the outcome of a reconciliation, so problems with single event types don't go unnoticed.
+pub fn log_report(broadcaster_id: &str, report: &SubscriptionReport) {
+    for (kind, outcome) in report {
+        match outcome {
+            SubscriptionOutcome::Active => {}
+            SubscriptionOutcome::Created => {
+                tracing::info!("Subscribed to {kind} events of channel {broadcaster_id}")
+            }
+            SubscriptionOutcome::MissingScope(scope) => tracing::warn!(
+                "Broadcaster {broadcaster_id} needs to authorize again with the {scope} scope to receive {kind} events"
+            ),
+            SubscriptionOutcome::Failed(e) => tracing::warn!(
+                "Failed to subscribe to {kind} events of channel {broadcaster_id}: {e:#}"
+            ),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::bot::commands::variables::{self, TemplateValues};
+use crate::bot::BotContext;
+use crate::models::{timer, timer_message};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+/// How often the timers are loaded and checked whether they are due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What happened since a timer last posted
+struct TimerState {
+    last_post: Instant,
+    chat_messages: i32,
+    last_message_id: Option<i32>,
+}
+
+impl TimerState {
+    fn new() -> Self {
+        TimerState {
+            last_post: Instant::now(),
+            chat_messages: 0,
+            last_message_id: None,
+        }
+    }
+
+    fn is_due(&self, timer: &timer::Model, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_post)
+            >= Duration::from_secs(timer.interval_minutes as u64 * 60)
+            && self.chat_messages >= timer.min_chat_messages
+    }
+}
+
+/// Posts the messages of the timers of the bot's channel once they are due, until
+/// `shutdown_signal` fires. Timers are loaded on every check, so changes take effect without
+/// restarting the bot.
+pub async fn run(
+    bot: Arc<BotContext>,
+    mut chat_messages: broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>,
+    shutdown_signal: CancellationToken,
+) {
+    let mut states: HashMap<i32, TimerState> = HashMap::new();
+    let mut check = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            message = chat_messages.recv() => match message {
+                Ok(message) => {
+                    if message.chatter_user_id != bot.bot_user_id {
+                        for state in states.values_mut() {
+                            state.chat_messages = state.chat_messages.saturating_add(1);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Timers lagged behind, skipped {skipped} chat messages");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = check.tick() => {
+                if let Err(e) = post_due(&bot, &mut states).await {
+                    tracing::error!(
+                        "Failed to post timers in channel {}: {e:#}",
+                        bot.broadcaster_user_id
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Post a message of every timer that is due and has a message for the current stream status.
+/// Timers only post while the stream is online unless they have messages marked as offline or
+/// any, which are posted while it is offline too.
+async fn post_due(bot: &BotContext, states: &mut HashMap<i32, TimerState>) -> anyhow::Result<()> {
+    let online = bot.is_live();
+    let timers = timer::find_with_messages(bot.broadcaster_user_id.as_str(), false, bot.db).await?;
+    states.retain(|id, _| timers.iter().any(|(timer, _)| timer.id == *id));
+    let now = Instant::now();
+    for (timer, messages) in &timers {
+        // new timers wait a full interval before their first post
+        let state = states.entry(timer.id).or_insert_with(TimerState::new);
+        if !state.is_due(timer, now) {
+            continue;
+        }
+        let Some(message) = pick_message(messages, online, state.last_message_id) else {
+            continue;
+        };
+
+        let values = TemplateValues {
+            bot,
+            user: None,
+            args: "",
+            count: None,
+        };
+        bot.chat.send(variables::render(&message.message, &values));
+        *state = TimerState {
+            last_post: now,
+            chat_messages: 0,
+            last_message_id: Some(message.id),
+        };
+    }
+    Ok(())
+}
+
+/// A random message of a timer for the current stream status, which is not the one posted last
+/// time unless it is the only one.
+fn pick_message(
+    messages: &[timer_message::Model],
+    online: bool,
+    last_message_id: Option<i32>,
+) -> Option<&timer_message::Model> {
+    let candidates: Vec<_> = messages
+        .iter()
+        .filter(|message| message.stream_state.matches(online))
+        .collect();
+    let fresh: Vec<_> = candidates
+        .iter()
+        .copied()
+        .filter(|message| Some(message.id) != last_message_id)
+        .collect();
+    let mut rng = rand::thread_rng();
+    fresh
+        .choose(&mut rng)
+        .or_else(|| candidates.choose(&mut rng))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::timer_message::StreamState;
+    use std::collections::HashSet;
+
+    fn timer(interval_minutes: i32, min_chat_messages: i32) -> timer::Model {
+        timer::Model {
+            id: 1,
+            broadcaster_id: "1000".to_owned(),
+            name: "test".to_owned(),
+            interval_minutes,
+            min_chat_messages,
+            enabled: true,
+            created_at: "2024-05-22T12:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn message(id: i32, stream_state: StreamState) -> timer_message::Model {
+        timer_message::Model {
+            id,
+            timer_id: 1,
+            message: format!("message {id}"),
+            stream_state,
+        }
+    }
+
+    /// Whether a timer with the state is due `minutes` after its last post.
+    fn due_after(timer: &timer::Model, minutes: u64, chat_messages: i32) -> bool {
+        let state = TimerState {
+            chat_messages,
+            ..TimerState::new()
+        };
+        state.is_due(timer, state.last_post + Duration::from_secs(minutes * 60))
+    }
+
+    /// The IDs of the messages `pick_message` chose over many tries.
+    fn picked(
+        messages: &[timer_message::Model],
+        online: bool,
+        last_message_id: Option<i32>,
+    ) -> HashSet<Option<i32>> {
+        (0..100)
+            .map(|_| pick_message(messages, online, last_message_id).map(|message| message.id))
+            .collect()
+    }
+
+    #[test]
+    fn due_after_interval_and_enough_chat_messages() {
+        let timer = timer(5, 3);
+        assert!(due_after(&timer, 5, 3));
+        assert!(due_after(&timer, 6, 10));
+        assert!(!due_after(&timer, 4, 3));
+        assert!(!due_after(&timer, 5, 2));
+    }
+
+    #[test]
+    fn new_timer_waits_a_full_interval() {
+        assert!(!TimerState::new().is_due(&timer(1, 0), Instant::now()));
+    }
+
+    #[test]
+    fn picks_messages_for_stream_status() {
+        let messages = [
+            message(1, StreamState::Online),
+            message(2, StreamState::Offline),
+            message(3, StreamState::Any),
+        ];
+        assert_eq!(
+            picked(&messages, true, None),
+            HashSet::from([Some(1), Some(3)])
+        );
+        assert_eq!(
+            picked(&messages, false, None),
+            HashSet::from([Some(2), Some(3)])
+        );
+    }
+
+    #[test]
+    fn does_not_repeat_last_message() {
+        let messages = [
+            message(1, StreamState::Online),
+            message(2, StreamState::Online),
+        ];
+        assert_eq!(picked(&messages, true, Some(1)), HashSet::from([Some(2)]));
+    }
+
+    #[test]
+    fn repeats_only_message() {
+        let messages = [
+            message(1, StreamState::Online),
+            message(2, StreamState::Offline),
+        ];
+        assert_eq!(picked(&messages, true, Some(1)), HashSet::from([Some(1)]));
+    }
+
+    #[test]
+    fn nothing_to_post_without_matching_message() {
+        let messages = [message(1, StreamState::Online)];
+        assert_eq!(pick_message(&messages, false, None), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::api;
+use crate::api::twitch::auth::RefreshTokenError;
+use crate::models::special_twitch_authorization;
+use crate::{AuthorizationEvent, Config, Events};
+use anyhow::Context;
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How often the database is checked for authorizations that are about to expire
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Tokens are refreshed once they expire in less than this. Has to be comfortably larger than
+/// [`CHECK_INTERVAL`], so a token is never used after it expired.
+const REFRESH_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+/// Keeps the access tokens of all special Twitch authorizations fresh, until `shutdown_signal`
+/// fires. Authorizations whose refresh token Twitch rejects are marked as invalidated.
+pub async fn run(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    events: Arc<Events>,
+    shutdown_signal: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown_signal.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        if let Err(e) = refresh_expiring(config, db, &events).await {
+            tracing::error!("Failed to refresh expiring authorizations: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+async fn refresh_expiring(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    events: &Events,
+) -> anyhow::Result<()> {
+    let deadline = Utc::now() + chrono::Duration::from_std(REFRESH_MARGIN)?;
+    let authorizations = special_twitch_authorization::find_expiring(deadline, db)
+        .await
+        .context("refresh_expiring find authorizations")?;
+
+    for authorization in authorizations {
+        let user_id = authorization.user_id.clone();
+        match refresh(config, db, authorization).await {
+            Ok(event) => events.publish_authorization(event),
+            // other authorizations might still succeed, this one is retried with the next check
+            Err(e) => tracing::warn!("Failed to refresh authorization of user {user_id}: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+async fn refresh(
+    config: &'static Config,
+    db: &'static DatabaseConnection,
+    authorization: special_twitch_authorization::Model,
+) -> anyhow::Result<AuthorizationEvent> {
+    let user_id = authorization.user_id;
+    match api::twitch::auth::refresh_token(&config.twitch_api, &authorization.twitch_refresh_token)
+        .await
+    {
+        Ok(new_twitch_auth) => {
+            update_if_unchanged(
+                &user_id,
+                &authorization.twitch_refresh_token,
+                special_twitch_authorization::ActiveModel {
+                    twitch_access_token: Set(new_twitch_auth.access_token),
+                    twitch_refresh_token: Set(new_twitch_auth.refresh_token),
+                    valid_until: Set(new_twitch_auth.valid_until),
+                    ..Default::default()
+                },
+                db,
+            )
+            .await
+            .context("refresh update authorization")?;
+            tracing::debug!("Refreshed authorization of user {user_id}");
+            Ok(AuthorizationEvent::Refreshed {
+                user_id: user_id.into(),
+            })
+        }
+        Err(RefreshTokenError::InvalidRefreshToken(_)) => {
+            update_if_unchanged(
+                &user_id,
+                &authorization.twitch_refresh_token,
+                special_twitch_authorization::ActiveModel {
+                    invalidated_at: Set(Some(Utc::now())),
+                    ..Default::default()
+                },
+                db,
+            )
+            .await
+            .context("refresh mark authorization invalidated")?;
+            tracing::warn!(
+                "Twitch rejected the refresh token of user {user_id}, they need to authorize again"
+            );
+            Ok(AuthorizationEvent::Invalidated {
+                user_id: user_id.into(),
+            })
+        }
+        Err(RefreshTokenError::Other(e)) => Err(e.into()),
+    }
+}
+
+/// Update the authorization, unless the user authorized again while the old refresh token was
+/// being used, which replaces the refresh token.
+async fn update_if_unchanged(
+    user_id: &str,
+    refresh_token: &str,
+    update: special_twitch_authorization::ActiveModel,
+    db: &DatabaseConnection,
+) -> anyhow::Result<()> {
+    special_twitch_authorization::Entity::update_many()
+        .set(update)
+        .filter(special_twitch_authorization::Column::UserId.eq(user_id))
+        .filter(special_twitch_authorization::Column::TwitchRefreshToken.eq(refresh_token))
+        .exec(db)
+        .await?;
+    Ok(())
+}
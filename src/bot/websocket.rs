@@ -1,52 +1,63 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+use futures::{FutureExt, StreamExt};
+use rand::Rng;
+use serde::Deserialize;
+use tokio::time::Instant;
 use tokio_tungstenite::tungstenite;
 use tokio_util::sync::CancellationToken;
-use twitch_api::twitch_oauth2::TwitchToken;
-use twitch_api::{
-    eventsub::{
-        self,
-        event::websocket::{EventsubWebsocketData, ReconnectPayload, SessionData, WelcomePayload},
-        Event,
-    },
-    HelixClient,
+use twitch_api::eventsub::{
+    event::websocket::{EventsubWebsocketData, ReconnectPayload, SessionData, WelcomePayload},
+    Event,
 };
-use twitch_oauth2::AppAccessToken;
 
-use crate::config::BotConfig;
+use crate::api::twitch::eventsub::Subscription;
+use crate::bot::notifications::{self, skip, EventError};
 use crate::Events;
 
+type WebsocketStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Wait time before the first reconnection attempt, doubled for every further failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Twitch sends the welcome message right after connecting, a connection without one is dead
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+/// Allowance on top of the keepalive timeout announced by Twitch, for network latency
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(3);
+
 pub struct WebsocketClient {
     /// The session id of the websocket connection
     session_id: Option<String>,
-    /// The token used to authenticate with the Twitch API
-    token: AppAccessToken,
-    /// The client used to make requests to the Twitch API
-    client: HelixClient<'static, reqwest::Client>,
-    bot_config: &'static BotConfig,
     /// The url to use for websocket
     connect_url: url::Url,
+    /// The url to connect to after the connection was lost. `connect_url` only changes for
+    /// reconnects requested by Twitch.
+    initial_connect_url: url::Url,
+    /// How long the connection may be silent before it is considered dead
+    keepalive_timeout: Duration,
 
     events: Arc<Events>,
 
     on_ready_sender: Option<tokio::sync::mpsc::Sender<String>>,
 }
 
+/// Why a websocket session ended without an error
+enum SessionEnd {
+    Shutdown,
+    /// Twitch asked us to move to a new connection, which has already been established
+    Reconnected(Box<WebsocketStream>),
+}
+
 impl WebsocketClient {
-    pub fn new(
-        token: AppAccessToken,
-        client: HelixClient<'static, reqwest::Client>,
-        bot_config: &'static BotConfig,
-        connect_url: url::Url,
-        events: Arc<Events>,
-    ) -> Self {
+    pub fn new(connect_url: url::Url, events: Arc<Events>) -> Self {
         Self {
             session_id: None,
-            token,
-            client,
-            bot_config,
+            initial_connect_url: connect_url.clone(),
             connect_url,
+            keepalive_timeout: WELCOME_TIMEOUT,
             events,
             on_ready_sender: None,
         }
@@ -68,13 +79,7 @@ impl WebsocketClient {
     }
 
     /// Connect to the websocket and return the stream
-    async fn connect(
-        &self,
-    ) -> anyhow::Result<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-    > {
+    async fn connect(&self, url: &url::Url) -> anyhow::Result<WebsocketStream> {
         tracing::info!("connecting to twitch");
         let config = tungstenite::protocol::WebSocketConfig {
             // max_write_buffer_size: 2048,
@@ -83,137 +88,425 @@ impl WebsocketClient {
             accept_unmasked_frames: false,
             ..tungstenite::protocol::WebSocketConfig::default()
         };
-        let (socket, _) =
-            tokio_tungstenite::connect_async_with_config(&self.connect_url, Some(config), false)
-                .await
-                .context("Can't connect")?;
+        let (socket, _) = tokio_tungstenite::connect_async_with_config(url, Some(config), false)
+            .await
+            .context("Can't connect")?;
 
         Ok(socket)
     }
 
-    /// Run the websocket subscriber
+    /// Run the websocket subscriber, reconnecting whenever the connection is lost, until
+    /// `shutdown_signal` fires.
     // #[tracing::instrument(name = "subscriber", skip_all, fields())]
     async fn run(mut self, shutdown_signal: CancellationToken) -> anyhow::Result<()> {
-        // Establish the stream
-        let mut s = self
-            .connect()
-            .await
-            .context("when establishing connection")?;
-        // Loop over the stream, processing messages as they come in.
+        let mut backoff = INITIAL_BACKOFF;
+        let mut handed_over: Option<Box<WebsocketStream>> = None;
         loop {
+            let result = match handed_over.take() {
+                Some(socket) => self.run_session(*socket, &shutdown_signal).await,
+                None => match self.connect(&self.connect_url).await {
+                    Ok(socket) => self.run_session(socket, &shutdown_signal).await,
+                    Err(e) => Err(e),
+                },
+            };
+            let e = match result {
+                Ok(SessionEnd::Shutdown) => {
+                    tracing::info!("Shutdown signal fired, websocket closed");
+                    return Ok(());
+                }
+                Ok(SessionEnd::Reconnected(socket)) => {
+                    handed_over = Some(socket);
+                    backoff = INITIAL_BACKOFF;
+                    continue;
+                }
+                Err(e) => e,
+            };
+
+            // a session that got as far as being welcomed worked, so start over with the backoff
+            if self.session_id.take().is_some() {
+                backoff = INITIAL_BACKOFF;
+            }
+            self.connect_url = self.initial_connect_url.clone();
+            self.keepalive_timeout = WELCOME_TIMEOUT;
+
+            // full jitter, so many bots losing their connection at once don't reconnect in lockstep
+            let delay = backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0));
+            tracing::warn!("Websocket connection lost, reconnecting in {delay:?}: {e:#}");
             tokio::select! {
+                _ = shutdown_signal.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(delay) => {}
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Process messages from the socket until it fails, Twitch moves us to a new connection or
+    /// `shutdown_signal` fires.
+    async fn run_session(
+        &mut self,
+        mut socket: WebsocketStream,
+        shutdown_signal: &CancellationToken,
+    ) -> anyhow::Result<SessionEnd> {
+        let mut deadline = Instant::now() + self.keepalive_timeout;
+        loop {
+            let msg = tokio::select! {
                 _ = shutdown_signal.cancelled() => {
-                    tracing::info!("Shutdown signal fired!!!!!!");
-                    break Ok(());
+                    // best effort, we are leaving either way
+                    let _ = socket.close(None).await;
+                    return Ok(SessionEnd::Shutdown);
                 }
-                Some(msg) = futures::StreamExt::next(&mut s) => {
-                    let msg = match msg {
-                        Err(tungstenite::Error::Protocol(
-                            tungstenite::error::ProtocolError::ResetWithoutClosingHandshake,
-                        )) => {
-                            tracing::warn!(
-                                "connection was sent an unexpected frame or was reset, reestablishing it"
-                            );
-                            s = self
-                                .connect()
-                                .await
-                                .context("when reestablishing connection")?;
-                            continue
-                        }
-                        _ => msg.context("when getting message")?,
-                    };
-                    self.process_message(msg).await?
+                _ = tokio::time::sleep_until(deadline) => {
+                    return Err(anyhow!(
+                        "no message received within the keepalive timeout of {:?}",
+                        self.keepalive_timeout
+                    ));
+                }
+                msg = socket.next() => msg,
+            };
+            let msg = msg
+                .ok_or_else(|| anyhow!("connection closed"))?
+                .context("when getting message")?;
+
+            if let Some(reconnect_url) = self.process_message(msg).await? {
+                let new_socket = self.hand_over(&reconnect_url).await?;
+                // the old connection is only dropped once the new one is ready, and might have
+                // received some last events in the meantime
+                while let Some(Some(Ok(msg))) = socket.next().now_or_never() {
+                    self.process_message(msg).await?;
+                }
+                drop(socket);
+                return Ok(SessionEnd::Reconnected(Box::new(new_socket)));
+            }
+            deadline = Instant::now() + self.keepalive_timeout;
+        }
+    }
+
+    /// Connect to the reconnect url Twitch sent us and wait for its welcome message.
+    async fn hand_over(&mut self, reconnect_url: &str) -> anyhow::Result<WebsocketStream> {
+        tracing::info!("Twitch requested a reconnect, moving to a new connection");
+        self.connect_url = reconnect_url
+            .parse()
+            .context("when parsing reconnect url")?;
+        let mut socket = self.connect(&self.connect_url).await?;
+        loop {
+            let msg = tokio::time::timeout(WELCOME_TIMEOUT, socket.next())
+                .await
+                .context("no welcome message on the new connection")?
+                .ok_or_else(|| anyhow!("new connection closed before welcome"))?
+                .context("when getting message from the new connection")?;
+            if let tungstenite::Message::Text(s) = &msg {
+                if let Ok(EventsubWebsocketData::Welcome { .. }) = Event::parse_websocket(s) {
+                    self.process_message(msg).await?;
+                    return Ok(socket);
                 }
             }
         }
     }
 
-    /// Process a message from the websocket
-    async fn process_message(&mut self, msg: tungstenite::Message) -> anyhow::Result<()> {
+    /// Process a message from the websocket. Returns the url to reconnect to if Twitch asked us to.
+    /// Only problems with the connection itself are returned as errors, a message that can't be
+    /// handled is logged and skipped.
+    async fn process_message(
+        &mut self,
+        msg: tungstenite::Message,
+    ) -> anyhow::Result<Option<String>> {
         match msg {
             tungstenite::Message::Text(s) => {
                 // Parse the message into a [twitch_api::eventsub::EventsubWebsocketData]
-                match Event::parse_websocket(&s)? {
+                let data = match Event::parse_websocket(&s) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        skip(EventError::Parse(e));
+                        return Ok(None);
+                    }
+                };
+                match data {
                     EventsubWebsocketData::Welcome {
                         payload: WelcomePayload { session },
                         ..
-                    }
-                    | EventsubWebsocketData::Reconnect {
-                        payload: ReconnectPayload { session },
-                        ..
                     } => {
                         self.process_welcome_message(session).await?;
-                        Ok(())
+                        Ok(None)
                     }
-                    // Here is where you would handle the events you want to listen to
-                    EventsubWebsocketData::Notification {
-                        metadata: _,
-                        payload,
-                    } => {
-                        match payload {
-                            Event::ChannelBanV1(eventsub::Payload { message, .. }) => {
-                                tracing::info!(?message, "got ban event");
-                            }
-                            Event::ChannelUnbanV1(eventsub::Payload { message, .. }) => {
-                                tracing::info!(?message, "got ban event");
-                            }
-                            Event::ChannelChatMessageV1(eventsub::Payload { message, .. }) => {
-                                match message {
-                                    eventsub::Message::VerificationRequest(_) => unreachable!(
-                                        "This should only be reachable by webhook events"
-                                    ),
-                                    eventsub::Message::Revocation() => unreachable!(
-                                        "This should be handled by the Revocation portion below"
+                    EventsubWebsocketData::Reconnect {
+                        payload: ReconnectPayload { session },
+                        ..
+                    } => Ok(Some(
+                        session
+                            .reconnect_url
+                            .ok_or_else(|| anyhow!("reconnect message without reconnect url"))?
+                            .into_owned(),
+                    )),
+                    EventsubWebsocketData::Notification { metadata, payload } => {
+                        let result = self
+                            .events
+                            .accept_message(
+                                &metadata.message_id,
+                                metadata.message_timestamp.as_str(),
+                            )
+                            .map_err(EventError::from)
+                            .and_then(|()| {
+                                notifications::publish(
+                                    &self.events,
+                                    payload,
+                                    format_args!(
+                                        "{:?} version {}",
+                                        metadata.subscription_type, metadata.subscription_version
                                     ),
-                                    eventsub::Message::Notification(message) => {
-                                        self.events.publish_chat_message(message)?;
-                                    }
-                                    _ => panic!("non_exhaustive enum {message:?}"),
-                                }
-                            }
-                            _ => {}
+                                )
+                            });
+                        if let Err(e) = result {
+                            skip(e);
                         }
-                        Ok(())
+                        Ok(None)
+                    }
+                    // only this subscription is affected, the other channels keep working
+                    EventsubWebsocketData::Revocation { metadata, .. } => {
+                        let result = self
+                            .events
+                            .accept_message(
+                                &metadata.message_id,
+                                metadata.message_timestamp.as_str(),
+                            )
+                            .map_err(EventError::from)
+                            .and_then(|()| self.publish_revocation(&s));
+                        if let Err(e) = result {
+                            skip(e);
+                        }
+                        Ok(None)
                     }
-                    EventsubWebsocketData::Revocation {
-                        metadata,
-                        payload: _,
-                    } => anyhow::bail!("got revocation event: {metadata:?}"),
                     EventsubWebsocketData::Keepalive {
                         metadata: _,
                         payload: _,
-                    } => Ok(()),
-                    _ => Ok(()),
+                    } => Ok(None),
+                    _ => {
+                        skip(EventError::UnknownMessage);
+                        Ok(None)
+                    }
                 }
             }
-            tungstenite::Message::Close(_) => todo!(),
-            _ => Ok(()),
+            tungstenite::Message::Close(frame) => {
+                anyhow::bail!("connection closed by Twitch: {frame:?}")
+            }
+            _ => Ok(None),
         }
     }
 
+    /// The parsed [`Event`] only exposes the revoked subscription per event type, so the message
+    /// is parsed again for the parts all subscriptions have in common.
+    fn publish_revocation(&self, message: &str) -> Result<(), EventError> {
+        let message = serde_json::from_str::<RevocationMessage>(message)?;
+        notifications::publish_revocation(&self.events, message.payload.subscription)
+    }
+
     async fn process_welcome_message(&mut self, data: SessionData<'_>) -> anyhow::Result<()> {
         self.session_id = Some(data.id.to_string());
+        if let Some(keepalive_timeout_seconds) = data.keepalive_timeout_seconds {
+            self.keepalive_timeout =
+                Duration::from_secs(keepalive_timeout_seconds.max(0) as u64) + KEEPALIVE_GRACE;
+        }
         tracing::info!("Processing welcome message");
+        // every new session has to be registered with the conduit shard again
         self.on_ready_sender
             .as_mut()
-            .unwrap()
+            .context("websocket client was not started")?
             .send(data.id.to_string())
             .await?;
         tracing::info!("Sent to on ready sender");
-        if let Some(url) = data.reconnect_url {
-            self.connect_url = url.parse()?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct RevocationMessage {
+    payload: RevocationPayload,
+}
+
+#[derive(Deserialize)]
+struct RevocationPayload {
+    subscription: Subscription,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::chat;
+    use crate::bot::commands::{self, parse_command, CommandRegistry};
+    use crate::bot::conduit::ConduitManager;
+    use crate::models::special_twitch_authorization::{self, BOT_SCOPE_VERSION};
+    use crate::testing::{self, MockTwitch};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+    use serde_json::{json, Value};
+    use tokio::sync::broadcast;
+    use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+
+    fn chat_message(text: &str) -> Value {
+        json!({
+            "broadcaster_user_id": "1000",
+            "broadcaster_user_login": "mockstreamer",
+            "broadcaster_user_name": "MockStreamer",
+            "chatter_user_id": "3000",
+            "chatter_user_login": "chatter",
+            "chatter_user_name": "Chatter",
+            "message_id": format!("chat-{text}"),
+            "message": {
+                "text": text,
+                "fragments": [
+                    { "type": "text", "text": text, "cheermote": null, "emote": null, "mention": null },
+                ],
+            },
+            "color": "#FF0000",
+            "badges": [],
+            "message_type": "text",
+            "cheer": null,
+            "reply": null,
+            "channel_points_custom_reward_id": null,
+        })
+    }
+
+    /// Connect a websocket client to the mock server, returning once its session is ready.
+    async fn connect(twitch: &MockTwitch, events: Arc<Events>) -> (CancellationToken, String) {
+        let url = url::Url::parse(&twitch.credentials().eventsub_websocket_url).unwrap();
+        let shutdown = CancellationToken::new();
+        let (_, mut ready) = WebsocketClient::new(url, events)
+            .start(shutdown.clone())
+            .unwrap();
+        let session_id = tokio::time::timeout(Duration::from_secs(5), ready.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        (shutdown, session_id)
+    }
+
+    async fn next<T: Clone>(receiver: &mut broadcast::Receiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("no event was published")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn chat_commands_are_published_once() {
+        let twitch = MockTwitch::start().await;
+        let events = Arc::new(Events::new(Duration::from_secs(600)));
+        let mut chat_messages = events.subscribe::<ChannelChatMessageV1Payload>("1000".into());
+        let (shutdown, _) = connect(&twitch, events.clone()).await;
+
+        let ping = twitch.send_notification("channel.chat.message", "1", chat_message("!ping"));
+        // Twitch may deliver a message again, e.g. after a reconnect
+        twitch.send_websocket_message(&ping);
+        twitch.send_notification("channel.chat.message", "1", chat_message("hello"));
+
+        let received = next(&mut chat_messages).await;
+        let command = parse_command("!", &received.message.text).unwrap();
+        assert_eq!(command.trigger, "ping");
+        assert!(CommandRegistry::with_builtins()
+            .unwrap()
+            .resolve(command.trigger)
+            .is_some());
+        assert_eq!(next(&mut chat_messages).await.message.text, "hello");
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn stale_messages_are_dropped() {
+        let twitch = MockTwitch::start().await;
+        let events = Arc::new(Events::new(Duration::from_secs(600)));
+        let mut chat_messages = events.subscribe::<ChannelChatMessageV1Payload>("1000".into());
+        let (shutdown, _) = connect(&twitch, events.clone()).await;
+
+        let mut replayed = twitch.notification("channel.chat.message", "1", chat_message("!ping"));
+        replayed["metadata"]["message_timestamp"] =
+            json!((chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339());
+        twitch.send_websocket_message(&replayed);
+        twitch.send_notification("channel.chat.message", "1", chat_message("hello"));
+
+        assert_eq!(next(&mut chat_messages).await.message.text, "hello");
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn shards_follow_websocket_sessions() {
+        let twitch = MockTwitch::start().await;
+        let config = twitch.config();
+        let conduits = ConduitManager::start(config).await.unwrap();
+        assert_eq!(twitch.state().conduits.len(), 1);
+        assert_eq!(twitch.state().conduits[0].id, conduits.conduit_id());
+
+        let events = Arc::new(Events::new(config.eventsub.max_message_age));
+        let mut sessions = vec![];
+        for _ in 0..2 {
+            let (shutdown, session_id) = connect(&twitch, events.clone()).await;
+            let shard_id = conduits.acquire_shard().await.unwrap();
+            conduits.assign_shard(shard_id, &session_id).await.unwrap();
+            sessions.push((shutdown, session_id));
         }
-        // check if the token is expired, if it is, request a new token. This only works if using a oauth service for getting a token
-        if self.token.is_elapsed() {
-            tracing::info!("Refreshing WebSocket AppAccessToken");
-            self.token = AppAccessToken::get_app_access_token(
-                self.client.get_client(),
-                self.bot_config.client_id.clone(),
-                self.bot_config.client_secret.clone(),
-                vec![],
-            )
-            .await?;
+        {
+            let state = twitch.state();
+            let conduit = &state.conduits[0];
+            assert_eq!(conduit.shard_count, 2);
+            assert_eq!(conduit.shards["0"]["session_id"], sessions[0].1.as_str());
+            assert_eq!(conduit.shards["1"]["session_id"], sessions[1].1.as_str());
         }
-        Ok(())
+
+        // Twitch refuses sessions that are not connected
+        assert!(conduits.assign_shard(0, "unknown-session").await.is_err());
+
+        sessions[1].0.cancel();
+        conduits.release_shard(1).await;
+        assert_eq!(twitch.state().conduits[0].shard_count, 1);
+        sessions[0].0.cancel();
+    }
+
+    #[tokio::test]
+    async fn chat_commands_are_answered() {
+        let twitch = MockTwitch::start().await;
+        // looked up for every chat message the bot sends
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[special_twitch_authorization::Model {
+                user_id: testing::BOT_USER_ID.to_owned(),
+                bot_scope_version: Some(BOT_SCOPE_VERSION),
+                broadcaster_scope_version: None,
+                twitch_access_token: testing::USER_ACCESS_TOKEN.to_owned(),
+                twitch_refresh_token: testing::REFRESH_TOKEN.to_owned(),
+                valid_until: chrono::Utc::now() + chrono::Duration::hours(4),
+                invalidated_at: None,
+            }]])
+            .into_connection();
+        let (bot, chat_queue) = twitch.bot_context(db, None).await;
+        let events = Arc::new(Events::new(bot.config.eventsub.max_message_age));
+        let (shutdown, _) = connect(&twitch, events.clone()).await;
+        tokio::spawn(chat::run(
+            bot.clone(),
+            chat_queue,
+            events.subscribe("1000".into()),
+            shutdown.clone(),
+        ));
+        tokio::spawn(commands::run(
+            bot,
+            "!".to_owned(),
+            CommandRegistry::with_builtins().unwrap(),
+            events.subscribe("1000".into()),
+            shutdown.clone(),
+        ));
+
+        twitch.send_notification("channel.chat.message", "1", chat_message("!ping"));
+
+        let reply = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let sent = twitch.state().chat_messages.first().cloned();
+                if let Some(sent) = sent {
+                    return sent;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("no reply was sent");
+        assert_eq!(reply.broadcaster_id, "1000");
+        assert_eq!(reply.sender_id, testing::BOT_USER_ID);
+        assert_eq!(reply.reply_parent_message_id.as_deref(), Some("chat-!ping"));
+        assert!(reply.message.starts_with("pajbot3 v"));
+        shutdown.cancel();
     }
 }
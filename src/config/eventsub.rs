@@ -0,0 +1,121 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventSubConfig {
+    /// Websockets unless `transport` is set
+    #[serde(flatten)]
+    pub transport: EventSubTransport,
+    /// Notifications sent longer ago than this are rejected as replays. Message ids are
+    /// remembered for as long to recognize redeliveries.
+    #[serde(
+        default = "EventSubConfig::default_max_message_age",
+        with = "humantime_serde"
+    )]
+    pub max_message_age: Duration,
+}
+
+impl EventSubConfig {
+    /// Twitch's own recommendation for webhooks
+    fn default_max_message_age() -> Duration {
+        Duration::from_secs(10 * 60)
+    }
+}
+
+impl Default for EventSubConfig {
+    fn default() -> Self {
+        Self {
+            transport: EventSubTransport::default(),
+            max_message_age: Self::default_max_message_age(),
+        }
+    }
+}
+
+/// How Twitch delivers EventSub notifications to this process.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "TransportKeys")]
+pub enum EventSubTransport {
+    /// Every bot keeps a websocket connection to Twitch open
+    #[default]
+    Websocket,
+    /// Twitch sends all notifications to the webhook route of the web server
+    Webhook {
+        /// The public HTTPS URL under which `/api/v1/eventsub/webhook` of the web server is
+        /// reachable, on port 443
+        callback_url: String,
+        /// Used by Twitch to sign the notifications, between 10 and 100 characters long
+        secret: String,
+    },
+}
+
+/// The keys of `[eventsub]` choosing the transport. Unlike a tagged enum, this allows leaving
+/// out `transport`, which is how configs written before webhooks existed look.
+#[derive(Deserialize)]
+struct TransportKeys {
+    #[serde(default)]
+    transport: TransportKind,
+    callback_url: Option<String>,
+    secret: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TransportKind {
+    #[default]
+    Websocket,
+    Webhook,
+}
+
+impl TryFrom<TransportKeys> for EventSubTransport {
+    type Error = &'static str;
+
+    fn try_from(keys: TransportKeys) -> Result<Self, Self::Error> {
+        Ok(match keys.transport {
+            TransportKind::Websocket => EventSubTransport::Websocket,
+            TransportKind::Webhook => EventSubTransport::Webhook {
+                callback_url: keys
+                    .callback_url
+                    .ok_or("missing field `callback_url` for the webhook transport")?,
+                secret: keys
+                    .secret
+                    .ok_or("missing field `secret` for the webhook transport")?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transport_defaults_to_websocket() {
+        let config: EventSubConfig = toml::from_str(r#"max_message_age = "5m""#).unwrap();
+        assert!(matches!(config.transport, EventSubTransport::Websocket));
+        assert_eq!(config.max_message_age, Duration::from_secs(5 * 60));
+    }
+
+    #[test]
+    fn webhook_transport() {
+        let config: EventSubConfig = toml::from_str(
+            r#"
+            transport = "webhook"
+            callback_url = "https://example.com"
+            secret = "0123456789"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.transport,
+            EventSubTransport::Webhook { ref callback_url, ref secret }
+                if callback_url == "https://example.com" && secret == "0123456789"
+        ));
+        assert_eq!(config.max_message_age, Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn webhook_transport_needs_callback_url() {
+        let config = toml::from_str::<EventSubConfig>(r#"transport = "webhook""#);
+        assert!(config.is_err());
+    }
+}
@@ -1,14 +1,13 @@
-mod bot;
 pub mod database;
+pub mod eventsub;
 pub mod web;
 
 use crate::api::twitch;
 use crate::config::database::DatabaseConfig;
+use crate::config::eventsub::{EventSubConfig, EventSubTransport};
 use crate::config::web::WebConfig;
 use anyhow::Context;
-pub use bot::BotConfig;
 use serde::Deserialize;
-use std::collections::HashMap;
 use std::path::Path;
 
 #[derive(Debug, Deserialize)]
@@ -18,7 +17,7 @@ pub struct Config {
     pub web: WebConfig,
     pub twitch_api: twitch::ApiClientCredentials,
     #[serde(default)]
-    pub twitch_bot: HashMap<String, BotConfig>,
+    pub eventsub: EventSubConfig,
 }
 
 impl Config {
@@ -37,12 +36,39 @@ impl Config {
     }
 
     fn validate(&self) -> anyhow::Result<()> {
-        if self.twitch_bot.is_empty() {
-            anyhow::bail!("You must specify at least one twitch_bot section. Check the example config file for how it should be structured.")
+        if self.twitch_api.client_id.is_empty() {
+            anyhow::bail!("[twitch_api]: client_id must not be empty");
         }
-
-        for (config_key, twitch_bot_config) in &self.twitch_bot {
-            twitch_bot_config.validate(config_key)?;
+        if self.twitch_api.client_secret.is_empty() {
+            anyhow::bail!("[twitch_api]: client_secret must not be empty");
+        }
+        for (key, url) in [
+            ("helix_url", &self.twitch_api.helix_url),
+            ("oauth2_url", &self.twitch_api.oauth2_url),
+            (
+                "eventsub_websocket_url",
+                &self.twitch_api.eventsub_websocket_url,
+            ),
+        ] {
+            url::Url::parse(url)
+                .with_context(|| format!("[twitch_api]: {key} is not a valid URL"))?;
+        }
+        if let EventSubTransport::Webhook {
+            callback_url,
+            secret,
+        } = &self.eventsub.transport
+        {
+            let callback_url = url::Url::parse(callback_url)
+                .context("[eventsub]: callback_url is not a valid URL")?;
+            if callback_url.scheme() != "https" {
+                anyhow::bail!("[eventsub]: callback_url must be an https URL");
+            }
+            if !(10..=100).contains(&secret.len()) {
+                anyhow::bail!("[eventsub]: secret must be between 10 and 100 characters long");
+            }
+        }
+        if self.eventsub.max_message_age.is_zero() {
+            anyhow::bail!("[eventsub]: max_message_age must not be zero");
         }
 
         Ok(())
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Upper bound of remembered message ids, in case Twitch sends more messages within the maximum
+/// message age than expected. The oldest ids are forgotten first.
+const MAX_REMEMBERED_MESSAGES: usize = 50_000;
+
+/// Why a message received from EventSub is not published.
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Message {0} was received before")]
+    Duplicate(String),
+    #[error("Message {message_id} was sent {age:?} ago, which is too long ago")]
+    Stale { message_id: String, age: Duration },
+    #[error("Message {message_id} has an invalid timestamp: {timestamp}")]
+    InvalidTimestamp {
+        message_id: String,
+        timestamp: String,
+    },
+}
+
+/// Recognizes messages Twitch delivers more than once, e.g. after a reconnect or when the
+/// response to a webhook got lost, and messages that are replayed much later.
+pub struct MessageDeduplicator {
+    max_age: Duration,
+    seen: Mutex<SeenMessages>,
+}
+
+#[derive(Default)]
+struct SeenMessages {
+    ids: HashSet<String>,
+    /// The same ids in the order they were received
+    received: VecDeque<(Instant, String)>,
+}
+
+impl MessageDeduplicator {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            seen: Mutex::new(SeenMessages::default()),
+        }
+    }
+
+    /// Accept a message the first time it is received, if it was sent within the maximum message
+    /// age. `timestamp` is when Twitch sent it, as an RFC 3339 date.
+    pub fn accept(&self, message_id: &str, timestamp: &str) -> Result<(), ReplayError> {
+        let sent_at =
+            DateTime::parse_from_rfc3339(timestamp).map_err(|_| ReplayError::InvalidTimestamp {
+                message_id: message_id.to_owned(),
+                timestamp: timestamp.to_owned(),
+            })?;
+        // messages from the future are fine, the clocks are just not perfectly in sync
+        let age = (Utc::now() - sent_at.with_timezone(&Utc))
+            .to_std()
+            .unwrap_or_default();
+        if age > self.max_age {
+            return Err(ReplayError::Stale {
+                message_id: message_id.to_owned(),
+                age,
+            });
+        }
+
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        // anything older is rejected as stale anyway, so there is no need to remember it
+        while let Some((received_at, _)) = seen.received.front() {
+            if now.duration_since(*received_at) <= self.max_age
+                && seen.received.len() < MAX_REMEMBERED_MESSAGES
+            {
+                break;
+            }
+            let (_, id) = seen.received.pop_front().unwrap();
+            seen.ids.remove(&id);
+        }
+        if !seen.ids.insert(message_id.to_owned()) {
+            return Err(ReplayError::Duplicate(message_id.to_owned()));
+        }
+        seen.received.push_back((now, message_id.to_owned()));
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of what happened to the messages received through EventSub, since the start of the
+/// process.
+pub struct EventMetrics {
+    /// Notifications published on the event bus with at least one receiver
+    published: AtomicU64,
+    /// Notifications published on the event bus nobody was subscribed to
+    dropped: AtomicU64,
+    /// Notifications of event types the bot does not handle
+    unknown: AtomicU64,
+    /// Messages that could not be parsed, or did not have the expected content
+    malformed: AtomicU64,
+    /// Messages that were received before
+    duplicate: AtomicU64,
+    /// Messages that were sent too long ago
+    stale: AtomicU64,
+}
+
+pub static EVENT_METRICS: EventMetrics = EventMetrics::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventMetricsSnapshot {
+    pub published: u64,
+    pub dropped: u64,
+    pub unknown: u64,
+    pub malformed: u64,
+    pub duplicate: u64,
+    pub stale: u64,
+}
+
+impl EventMetrics {
+    const fn new() -> Self {
+        Self {
+            published: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            unknown: AtomicU64::new(0),
+            malformed: AtomicU64::new(0),
+            duplicate: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+        }
+    }
+
+    pub fn count_published(&self, delivered: bool) {
+        let counter = if delivered {
+            &self.published
+        } else {
+            &self.dropped
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_unknown(&self) {
+        self.unknown.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_malformed(&self) {
+        self.malformed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_duplicate(&self) {
+        self.duplicate.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_stale(&self) {
+        self.stale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> EventMetricsSnapshot {
+        EventMetricsSnapshot {
+            published: self.published.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            unknown: self.unknown.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            duplicate: self.duplicate.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+        }
+    }
+}
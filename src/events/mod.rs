@@ -1,51 +1,140 @@
+mod dedup;
+mod metrics;
+
+pub use dedup::ReplayError;
+pub use metrics::{EventMetricsSnapshot, EVENT_METRICS};
+
+use std::any::{Any, TypeId};
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::models::subscription_revocation;
 use dashmap::DashMap;
+use tokio::sync::broadcast;
 use twitch_api::eventsub::channel::chat::message::ChannelChatMessageV1Payload;
+use twitch_api::eventsub::channel::{
+    ChannelBanV1Payload, ChannelCheerV1Payload, ChannelFollowV2Payload,
+    ChannelPointsCustomRewardRedemptionAddV1Payload, ChannelRaidV1Payload,
+    ChannelSubscribeV1Payload, ChannelUnbanV1Payload, ChannelUpdateV2Payload,
+};
+use twitch_api::eventsub::stream::{StreamOfflineV1Payload, StreamOnlineV1Payload};
 use twitch_api::types::UserId;
 
+/// How many events of one type and channel are buffered for slow receivers
+const CHANNEL_CAPACITY: usize = 69;
+
+/// Changes to the special Twitch authorization of a user.
+#[derive(Debug, Clone)]
+pub enum AuthorizationEvent {
+    /// The access token was replaced by a new one
+    Refreshed { user_id: UserId },
+    /// Twitch rejected the refresh token, the user has to authorize the application again
+    Invalidated { user_id: UserId },
+}
+
+/// Twitch revoked an EventSub subscription of a channel, so its events are not received anymore.
+#[derive(Debug, Clone)]
+pub struct SubscriptionRevoked {
+    pub broadcaster_id: UserId,
+    pub subscription_type: String,
+    pub subscription_version: String,
+    pub reason: subscription_revocation::Reason,
+}
+
+/// An EventSub event that happened in a channel, delivered to the receivers of that channel.
+pub trait ChannelEvent: Send + Sync + 'static {
+    fn broadcaster_id(&self) -> &UserId;
+}
+
+macro_rules! channel_events {
+    ($($payload:ty => $broadcaster_field:ident),* $(,)?) => {
+        $(
+            impl ChannelEvent for $payload {
+                fn broadcaster_id(&self) -> &UserId {
+                    &self.$broadcaster_field
+                }
+            }
+        )*
+    };
+}
+
+channel_events! {
+    ChannelChatMessageV1Payload => broadcaster_user_id,
+    ChannelBanV1Payload => broadcaster_user_id,
+    ChannelUnbanV1Payload => broadcaster_user_id,
+    ChannelFollowV2Payload => broadcaster_user_id,
+    ChannelSubscribeV1Payload => broadcaster_user_id,
+    ChannelCheerV1Payload => broadcaster_user_id,
+    // raids are delivered to the channel being raided
+    ChannelRaidV1Payload => to_broadcaster_user_id,
+    ChannelUpdateV2Payload => broadcaster_user_id,
+    StreamOnlineV1Payload => broadcaster_user_id,
+    StreamOfflineV1Payload => broadcaster_user_id,
+    ChannelPointsCustomRewardRedemptionAddV1Payload => broadcaster_user_id,
+    SubscriptionRevoked => broadcaster_id,
+}
+
 pub struct Events {
-    pub on_chat_message:
-        DashMap<UserId, tokio::sync::broadcast::Sender<Arc<ChannelChatMessageV1Payload>>>,
+    /// A `broadcast::Sender<Arc<E>>` for every event type `E` and channel that has been
+    /// subscribed to or published to
+    channels: DashMap<(TypeId, UserId), Box<dyn Any + Send + Sync>>,
+    on_authorization: broadcast::Sender<AuthorizationEvent>,
+    /// Shared by all transports, since Twitch may redeliver a message through another websocket
+    /// session of the conduit
+    messages: dedup::MessageDeduplicator,
 }
 
 impl Events {
-    pub fn new() -> Self {
+    /// Messages sent longer than `max_message_age` ago are not accepted.
+    pub fn new(max_message_age: Duration) -> Self {
         Self {
-            on_chat_message: DashMap::new(),
+            channels: DashMap::new(),
+            on_authorization: broadcast::channel(CHANNEL_CAPACITY).0,
+            messages: dedup::MessageDeduplicator::new(max_message_age),
         }
     }
 
-    pub fn get_receiver_chat_message(
+    fn sender<E: ChannelEvent>(&self, broadcaster_id: UserId) -> broadcast::Sender<Arc<E>> {
+        self.channels
+            .entry((TypeId::of::<E>(), broadcaster_id))
+            .or_insert_with(|| Box::new(broadcast::channel::<Arc<E>>(CHANNEL_CAPACITY).0))
+            .downcast_ref::<broadcast::Sender<Arc<E>>>()
+            .expect("channels are keyed by the type of their events")
+            .clone()
+    }
+
+    /// Receive all events of type `E` in the channel of `broadcaster_id`, e.g.
+    /// `events.subscribe::<ChannelBanV1Payload>(broadcaster_id)`.
+    pub fn subscribe<E: ChannelEvent>(
         &self,
         broadcaster_id: UserId,
-    ) -> anyhow::Result<tokio::sync::broadcast::Receiver<Arc<ChannelChatMessageV1Payload>>> {
-        let tx = self
-            .on_chat_message
-            .entry(broadcaster_id)
-            .or_insert_with(|| {
-                let (tx, _) = tokio::sync::broadcast::channel(69);
-                tx
-            });
-        Ok(tx.subscribe())
+    ) -> broadcast::Receiver<Arc<E>> {
+        self.sender(broadcaster_id).subscribe()
     }
 
-    pub fn publish_chat_message(&self, payload: ChannelChatMessageV1Payload) -> anyhow::Result<()> {
-        let broadcaster_id = payload.broadcaster_user_id.clone();
-        let tx = self
-            .on_chat_message
-            .entry(broadcaster_id)
-            .or_insert_with(|| {
-                let (tx, _) = tokio::sync::broadcast::channel(69);
-                tx
-            });
-        tx.send(Arc::new(payload))?;
-        Ok(())
+    /// Deliver an event to the receivers of its channel. Events nobody subscribed to are dropped,
+    /// since the conduit delivers all events Twitch has for us, whether a module uses them or not.
+    pub fn publish<E: ChannelEvent>(&self, event: E) {
+        let delivered = self
+            .sender::<E>(event.broadcaster_id().clone())
+            .send(Arc::new(event))
+            .is_ok();
+        EVENT_METRICS.count_published(delivered);
+    }
+
+    /// Check an EventSub message before publishing its event, so it is only handled once even if
+    /// Twitch delivers it again.
+    pub fn accept_message(&self, message_id: &str, timestamp: &str) -> Result<(), ReplayError> {
+        self.messages.accept(message_id, timestamp)
+    }
+
+    pub fn get_receiver_authorization(&self) -> broadcast::Receiver<AuthorizationEvent> {
+        self.on_authorization.subscribe()
     }
-}
 
-impl Default for Events {
-    fn default() -> Self {
-        Self::new()
+    /// Authorizations exist for users without a running bot, too, so it's fine if nobody is
+    /// listening.
+    pub fn publish_authorization(&self, event: AuthorizationEvent) {
+        let _ = self.on_authorization.send(event);
     }
 }
@@ -11,9 +11,9 @@ use sea_orm::Database;
 use sea_orm_migration::MigratorTrait;
 use std::process::ExitCode;
 use std::sync::Arc;
+use tokio::sync::Notify;
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
-use twitch_types::UserId;
 
 pub mod api;
 pub mod args;
@@ -23,8 +23,10 @@ mod events;
 pub mod migration;
 pub mod models;
 pub mod shutdown;
+#[cfg(test)]
+mod testing;
 pub mod web;
-pub use events::Events;
+pub use events::{AuthorizationEvent, Events, SubscriptionRevoked};
 
 lazy_static! {
     static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::new();
@@ -68,47 +70,49 @@ async fn main_inner() -> anyhow::Result<()> {
         .context("Failed to run database migrations")?;
     tracing::info!("Successfully ran database migrations");
 
-    let events = Arc::new(Events::new());
+    let events = Arc::new(Events::new(config.eventsub.max_message_age));
+    // notified whenever the bot table is modified, to start and stop bots accordingly
+    let bot_changes: &'static Notify = Box::leak(Box::new(Notify::new()));
+
+    // shared by all bots, so they receive their events through a single conduit
+    let conduits: &'static bot::conduit::ConduitManager = Box::leak(Box::new(
+        bot::conduit::ConduitManager::start(config)
+            .await
+            .context("Failed to set up EventSub conduit")?,
+    ));
 
     let shutdown_signal = CancellationToken::new();
 
-    let webserver = web::run(config, db, shutdown_signal.clone())
-        .await
-        .context("Failed to run web server")?;
+    let webserver = web::run(
+        config,
+        db,
+        events.clone(),
+        bot_changes,
+        shutdown_signal.clone(),
+    )
+    .await
+    .context("Failed to run web server")?;
     let mut webserver_join_handle = tokio::spawn(webserver).fuse();
 
     let mut bot_handles = JoinSet::new();
-    for bot_config in config.twitch_bot.values() {
-        tracing::info!("bot: {bot_config:?}");
-
-        let mut rx =
-            events.get_receiver_chat_message(UserId::new(bot_config.streamer_user_id.clone()))?;
-
-        tokio::spawn(async move {
-            loop {
-                match rx.recv().await {
-                    Ok(message) => {
-                        tracing::info!("received msg {message:?}");
-                    }
-                    Err(e) => {
-                        tracing::warn!("Received an error from the get_chat_message event: {e:?}");
-                        break;
-                    }
-                }
-            }
-        });
-
-        let bot_join_handle = bot::run(
-            config,
-            bot_config,
-            db,
-            events.clone(),
-            shutdown_signal.clone(),
-        )
-        .await
-        .context("Failed to run bot")?;
-        bot_handles.spawn(bot_join_handle);
-    }
+    bot_handles.spawn(tokio::spawn(bot::token_refresh::run(
+        config,
+        db,
+        events.clone(),
+        shutdown_signal.clone(),
+    )));
+    bot_handles.spawn(tokio::spawn(bot::scope_check::run(
+        db,
+        shutdown_signal.clone(),
+    )));
+    bot_handles.spawn(tokio::spawn(bot::manager::run(
+        config,
+        db,
+        conduits,
+        events.clone(),
+        bot_changes,
+        shutdown_signal.clone(),
+    )));
 
     let os_shutdown_signal = shutdown::shutdown_signal().fuse();
     futures::pin_mut!(os_shutdown_signal);
@@ -0,0 +1,19 @@
+-- Custom text commands, defined per channel by the broadcaster.
+CREATE TABLE command
+(
+    id                      SERIAL  NOT NULL PRIMARY KEY,
+    broadcaster_id          TEXT    NOT NULL REFERENCES "user" (id),
+    -- without the command prefix
+    trigger                 TEXT    NOT NULL,
+    -- may contain variables like $(user), see bot::commands::variables
+    response                TEXT    NOT NULL,
+    global_cooldown_seconds INTEGER NOT NULL DEFAULT 5 CHECK (global_cooldown_seconds >= 0),
+    user_cooldown_seconds   INTEGER NOT NULL DEFAULT 15 CHECK (user_cooldown_seconds >= 0),
+    min_permission          TEXT    NOT NULL DEFAULT 'everyone'
+        CHECK (min_permission IN ('everyone', 'subscriber', 'vip', 'moderator', 'broadcaster')),
+    enabled                 BOOLEAN NOT NULL DEFAULT TRUE,
+    use_count               INTEGER NOT NULL DEFAULT 0
+);
+
+-- triggers are matched case-insensitively
+CREATE UNIQUE INDEX command_broadcaster_id_trigger ON command (broadcaster_id, lower(trigger));
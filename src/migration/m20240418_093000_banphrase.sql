@@ -0,0 +1,24 @@
+-- Phrases that are not allowed in the chat of a channel, and what to do with the chatter
+-- if their message contains one.
+CREATE TABLE banphrase
+(
+    id                SERIAL  NOT NULL PRIMARY KEY,
+    broadcaster_id    TEXT    NOT NULL REFERENCES "user" (id),
+    name              TEXT    NOT NULL,
+    phrase            TEXT    NOT NULL,
+    operator          TEXT    NOT NULL CHECK (operator IN ('contains', 'exact', 'regex')),
+    action            TEXT    NOT NULL CHECK (action IN ('delete', 'timeout', 'ban')),
+    -- only used if action = 'timeout'. Twitch allows timeouts of up to two weeks.
+    timeout_seconds   INTEGER NOT NULL DEFAULT 300 CHECK (timeout_seconds BETWEEN 1 AND 1209600),
+    case_sensitive    BOOLEAN NOT NULL DEFAULT FALSE,
+    -- strip diacritics from messages before matching, so e.g. "é" matches "e"
+    remove_accents    BOOLEAN NOT NULL DEFAULT FALSE,
+    -- chatters with this permission level or higher are not affected by the banphrase
+    exempt_permission TEXT    NOT NULL DEFAULT 'moderator'
+        CHECK (exempt_permission IN ('everyone', 'subscriber', 'vip', 'moderator', 'broadcaster')),
+    -- subscribers are not affected by the banphrase, regardless of exempt_permission
+    sub_immunity      BOOLEAN NOT NULL DEFAULT FALSE,
+    enabled           BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE INDEX banphrase_broadcaster_id ON banphrase (broadcaster_id);
@@ -0,0 +1,24 @@
+-- Audit trail of the moderation actions in a channel, both the ones performed by the bot and
+-- the ones observed through EventSub.
+CREATE TABLE moderation_log
+(
+    id               BIGSERIAL   NOT NULL PRIMARY KEY,
+    broadcaster_id   TEXT        NOT NULL REFERENCES "user" (id),
+    action           TEXT        NOT NULL CHECK (action IN ('delete', 'timeout', 'ban', 'unban')),
+    -- the user responsible for the action: the chatter who used the command, the dashboard user,
+    -- the moderator who acted directly on Twitch, or the bot itself for filters
+    actor_id         TEXT        NOT NULL,
+    target_user_id   TEXT,
+    -- only set if action = 'delete'
+    message_id       TEXT,
+    -- only set if action = 'timeout'
+    duration_seconds INTEGER,
+    reason           TEXT        NOT NULL DEFAULT '',
+    -- command: a chat command, filter: automatic moderation like banphrases,
+    -- manual: a moderator using the dashboard or acting directly on Twitch
+    source           TEXT        NOT NULL CHECK (source IN ('command', 'filter', 'manual')),
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX moderation_log_broadcaster_id_id ON moderation_log (broadcaster_id, id DESC);
+CREATE INDEX moderation_log_broadcaster_id_target_user_id ON moderation_log (broadcaster_id, target_user_id);
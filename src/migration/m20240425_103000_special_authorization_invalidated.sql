@@ -0,0 +1,8 @@
+-- Set when Twitch rejected the refresh token of the authorization, e.g. because the user
+-- revoked access to the application or changed their password. The authorization can't be used
+-- anymore and has to be granted again by the user, which resets this to NULL.
+ALTER TABLE special_twitch_authorization
+    ADD COLUMN invalidated_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX special_twitch_authorization_valid_until ON special_twitch_authorization (valid_until)
+    WHERE invalidated_at IS NULL;
@@ -0,0 +1,5 @@
+-- Bots are now registered in the database instead of the config file, so the settings
+-- that used to be configured there move into the bot table.
+ALTER TABLE bot
+    -- chat messages starting with this are interpreted as commands
+    ADD COLUMN command_prefix TEXT NOT NULL DEFAULT '!' CHECK (btrim(command_prefix) <> '');
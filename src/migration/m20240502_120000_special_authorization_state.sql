@@ -0,0 +1,12 @@
+-- The "state" parameters handed out with Twitch authorize URLs for the special authorization
+-- flow. A callback is only accepted with a state from this table, which protects against CSRF and
+-- tells us which channel the authorization is meant for. Each state can only be used once.
+CREATE TABLE special_authorization_state
+(
+    state          TEXT                     NOT NULL PRIMARY KEY,
+    -- which scope set was requested
+    kind           TEXT                     NOT NULL CHECK (kind IN ('bot', 'broadcaster')),
+    -- the logged in user who started the flow, and whose channel it is for
+    broadcaster_id TEXT                     NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    created_at     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
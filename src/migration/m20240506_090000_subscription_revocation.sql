@@ -0,0 +1,15 @@
+-- EventSub subscriptions of a channel that Twitch revoked. Entries stay unresolved until the bot
+-- managed to subscribe to the same event type again, and make up the health of the channel's bot.
+CREATE TABLE subscription_revocation
+(
+    id                   BIGSERIAL   NOT NULL PRIMARY KEY,
+    broadcaster_id       TEXT        NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    subscription_type    TEXT        NOT NULL,
+    subscription_version TEXT        NOT NULL,
+    -- the status Twitch revoked the subscription with, anything unexpected is 'other'
+    reason               TEXT        NOT NULL CHECK (reason IN ('authorization_revoked', 'user_removed', 'moderator_removed', 'version_removed', 'other')),
+    revoked_at           TIMESTAMPTZ NOT NULL DEFAULT now(),
+    resolved_at          TIMESTAMPTZ
+);
+
+CREATE INDEX subscription_revocation_broadcaster_id_unresolved ON subscription_revocation (broadcaster_id) WHERE resolved_at IS NULL;
@@ -0,0 +1,25 @@
+-- Points of a chatter in a channel, earned by watching the stream and spent on minigames.
+CREATE TABLE user_channel_points
+(
+    broadcaster_id TEXT   NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    user_id        TEXT   NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    points         BIGINT NOT NULL DEFAULT 0 CHECK (points >= 0),
+    PRIMARY KEY (broadcaster_id, user_id)
+);
+
+CREATE INDEX user_channel_points_leaderboard ON user_channel_points (broadcaster_id, points DESC);
+
+-- How chatters earn points in a channel. Channels without a row use the defaults.
+CREATE TABLE points_settings
+(
+    broadcaster_id                TEXT    NOT NULL PRIMARY KEY REFERENCES "user" (id) ON DELETE CASCADE,
+    enabled                       BOOLEAN NOT NULL DEFAULT TRUE,
+    -- every interval while the stream is online, each active chatter earns this many points
+    points_per_interval           INTEGER NOT NULL DEFAULT 5 CHECK (points_per_interval BETWEEN 0 AND 100000),
+    interval_seconds              INTEGER NOT NULL DEFAULT 300 CHECK (interval_seconds BETWEEN 30 AND 86400),
+    -- chatters count as active for this long after their last chat message
+    active_seconds                INTEGER NOT NULL DEFAULT 900 CHECK (active_seconds BETWEEN 30 AND 86400),
+    -- subscribers and VIPs earn this many percent of points_per_interval, the higher one counts
+    subscriber_multiplier_percent INTEGER NOT NULL DEFAULT 200 CHECK (subscriber_multiplier_percent BETWEEN 0 AND 10000),
+    vip_multiplier_percent        INTEGER NOT NULL DEFAULT 150 CHECK (vip_multiplier_percent BETWEEN 0 AND 10000)
+);
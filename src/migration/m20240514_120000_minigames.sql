@@ -0,0 +1,57 @@
+-- Minigame configuration of a channel. Channels without a row use the defaults. Bets are in
+-- points, cooldowns are per chatter except for heists, which are channel-wide.
+CREATE TABLE minigame_settings
+(
+    broadcaster_id            TEXT    NOT NULL PRIMARY KEY REFERENCES "user" (id) ON DELETE CASCADE,
+    roulette_enabled          BOOLEAN NOT NULL DEFAULT TRUE,
+    roulette_min_bet          BIGINT  NOT NULL DEFAULT 1 CHECK (roulette_min_bet >= 1),
+    roulette_max_bet          BIGINT  NOT NULL DEFAULT 10000 CHECK (roulette_max_bet >= roulette_min_bet),
+    roulette_cooldown_seconds INTEGER NOT NULL DEFAULT 60 CHECK (roulette_cooldown_seconds BETWEEN 0 AND 86400),
+    -- chance to double the bet, in percent
+    roulette_win_percent      INTEGER NOT NULL DEFAULT 50 CHECK (roulette_win_percent BETWEEN 0 AND 100),
+    duel_enabled              BOOLEAN NOT NULL DEFAULT TRUE,
+    duel_min_bet              BIGINT  NOT NULL DEFAULT 1 CHECK (duel_min_bet >= 1),
+    duel_max_bet              BIGINT  NOT NULL DEFAULT 10000 CHECK (duel_max_bet >= duel_min_bet),
+    duel_cooldown_seconds     INTEGER NOT NULL DEFAULT 60 CHECK (duel_cooldown_seconds BETWEEN 0 AND 86400),
+    -- a challenge that is not accepted in time is cancelled
+    duel_accept_seconds       INTEGER NOT NULL DEFAULT 60 CHECK (duel_accept_seconds BETWEEN 10 AND 600),
+    slots_enabled             BOOLEAN NOT NULL DEFAULT TRUE,
+    slots_min_bet             BIGINT  NOT NULL DEFAULT 1 CHECK (slots_min_bet >= 1),
+    slots_max_bet             BIGINT  NOT NULL DEFAULT 10000 CHECK (slots_max_bet >= slots_min_bet),
+    slots_cooldown_seconds    INTEGER NOT NULL DEFAULT 60 CHECK (slots_cooldown_seconds BETWEEN 0 AND 86400),
+    heist_enabled             BOOLEAN NOT NULL DEFAULT TRUE,
+    heist_min_bet             BIGINT  NOT NULL DEFAULT 1 CHECK (heist_min_bet >= 1),
+    heist_max_bet             BIGINT  NOT NULL DEFAULT 10000 CHECK (heist_max_bet >= heist_min_bet),
+    heist_cooldown_seconds    INTEGER NOT NULL DEFAULT 600 CHECK (heist_cooldown_seconds BETWEEN 0 AND 86400),
+    -- chatters can join a heist for this long after it was started
+    heist_join_seconds        INTEGER NOT NULL DEFAULT 120 CHECK (heist_join_seconds BETWEEN 10 AND 600),
+    -- array of {"min_participants", "win_percent", "payout_percent"}, the tier with the highest
+    -- min_participants not above the crew size applies
+    heist_payouts             JSONB   NOT NULL DEFAULT '[{"min_participants": 1, "win_percent": 40, "payout_percent": 200}, {"min_participants": 5, "win_percent": 50, "payout_percent": 200}, {"min_participants": 10, "win_percent": 60, "payout_percent": 250}]'
+);
+
+-- One played round of a minigame, kept to resolve disputes about lost points.
+CREATE TABLE minigame_round
+(
+    id             BIGSERIAL   NOT NULL PRIMARY KEY,
+    broadcaster_id TEXT        NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    game           TEXT        NOT NULL CHECK (game IN ('roulette', 'duel', 'slots', 'heist')),
+    -- what happened, e.g. the symbols of a slot machine
+    outcome        TEXT        NOT NULL,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX minigame_round_broadcaster_id_id ON minigame_round (broadcaster_id, id DESC);
+
+CREATE TABLE minigame_participant
+(
+    round_id BIGINT NOT NULL REFERENCES minigame_round (id) ON DELETE CASCADE,
+    user_id  TEXT   NOT NULL,
+    -- points taken from the participant
+    bet      BIGINT NOT NULL,
+    -- points given to the participant, 0 if they lost
+    payout   BIGINT NOT NULL,
+    PRIMARY KEY (round_id, user_id)
+);
+
+CREATE INDEX minigame_participant_user_id ON minigame_participant (user_id, round_id);
@@ -0,0 +1,45 @@
+-- What the bot does when a channel point reward of a channel is redeemed.
+CREATE TABLE reward_action
+(
+    id                SERIAL  NOT NULL PRIMARY KEY,
+    broadcaster_id    TEXT    NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    reward_id         TEXT    NOT NULL,
+    action            TEXT    NOT NULL CHECK (action IN ('command', 'message', 'timeout', 'queue')),
+    -- only set if action = 'command': trigger of a custom command of the channel
+    command_trigger   TEXT,
+    -- only set if action = 'message': a template like the responses of custom commands
+    message           TEXT,
+    -- only set if action = 'timeout': the redeemer is timed out for this long
+    timeout_seconds   INTEGER CHECK (timeout_seconds BETWEEN 1 AND 1209600),
+    -- only set if action = 'queue'
+    queue_name        TEXT,
+    -- mark redemptions as fulfilled if the action succeeds, or cancel them to refund the points if
+    -- it fails. Twitch only allows this for rewards created with the client ID of this application.
+    update_redemption BOOLEAN NOT NULL DEFAULT FALSE,
+    enabled           BOOLEAN NOT NULL DEFAULT TRUE,
+    UNIQUE (broadcaster_id, reward_id)
+);
+
+-- Redemptions waiting in a queue of a channel, e.g. viewers who want to play with the streamer.
+CREATE TABLE redemption_queue_entry
+(
+    id                BIGSERIAL   NOT NULL PRIMARY KEY,
+    broadcaster_id    TEXT        NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    queue_name        TEXT        NOT NULL,
+    reward_id         TEXT        NOT NULL,
+    redemption_id     TEXT        NOT NULL,
+    user_id           TEXT        NOT NULL,
+    user_login        TEXT        NOT NULL,
+    user_name         TEXT        NOT NULL,
+    user_input        TEXT        NOT NULL,
+    -- copied from the reward action, the redemption is completed when the entry is removed
+    update_redemption BOOLEAN     NOT NULL,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX redemption_queue_entry_broadcaster_id_queue_name ON redemption_queue_entry (broadcaster_id, queue_name, id);
+
+-- timeouts of redeemers are logged with their own source
+ALTER TABLE moderation_log
+    DROP CONSTRAINT moderation_log_source_check,
+    ADD CONSTRAINT moderation_log_source_check CHECK (source IN ('command', 'filter', 'manual', 'redemption'));
@@ -0,0 +1,24 @@
+-- Messages the bot posts in a channel periodically, as long as chat is active.
+CREATE TABLE timer
+(
+    id                SERIAL      NOT NULL PRIMARY KEY,
+    broadcaster_id    TEXT        NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    name              TEXT        NOT NULL,
+    interval_minutes  INTEGER     NOT NULL CHECK (interval_minutes BETWEEN 1 AND 1440),
+    -- chat messages that have to arrive after a post before the timer posts again
+    min_chat_messages INTEGER     NOT NULL CHECK (min_chat_messages BETWEEN 0 AND 10000),
+    enabled           BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (broadcaster_id, name)
+);
+
+-- The messages of a timer, each post picks one of those matching the stream status at random.
+CREATE TABLE timer_message
+(
+    id           SERIAL  NOT NULL PRIMARY KEY,
+    timer_id     INTEGER NOT NULL REFERENCES timer (id) ON DELETE CASCADE,
+    message      TEXT    NOT NULL,
+    stream_state TEXT    NOT NULL CHECK (stream_state IN ('online', 'offline', 'any'))
+);
+
+CREATE INDEX timer_message_timer_id ON timer_message (timer_id);
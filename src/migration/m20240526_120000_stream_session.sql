@@ -0,0 +1,30 @@
+-- Streams of a channel, from going live until going offline.
+CREATE TABLE stream_session
+(
+    id               BIGSERIAL   NOT NULL PRIMARY KEY,
+    broadcaster_id   TEXT        NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    twitch_stream_id TEXT        NOT NULL,
+    started_at       TIMESTAMPTZ NOT NULL,
+    -- NULL while the stream is live
+    ended_at         TIMESTAMPTZ,
+    peak_viewers     INTEGER     NOT NULL DEFAULT 0,
+    chat_messages    BIGINT      NOT NULL DEFAULT 0,
+    UNIQUE (broadcaster_id, twitch_stream_id)
+);
+
+-- a channel can only be live once at a time
+CREATE UNIQUE INDEX stream_session_live ON stream_session (broadcaster_id) WHERE ended_at IS NULL;
+CREATE INDEX stream_session_broadcaster_id_started_at ON stream_session (broadcaster_id, started_at);
+
+-- The title and category of a stream, whenever one of them changed while it was live.
+CREATE TABLE stream_session_change
+(
+    id            BIGSERIAL   NOT NULL PRIMARY KEY,
+    session_id    BIGINT      NOT NULL REFERENCES stream_session (id) ON DELETE CASCADE,
+    title         TEXT        NOT NULL,
+    category_id   TEXT        NOT NULL,
+    category_name TEXT        NOT NULL,
+    changed_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX stream_session_change_session_id ON stream_session_change (session_id, id);
@@ -1,10 +1,18 @@
 pub use sea_orm_migration::prelude::*;
 
-#[derive(DeriveMigrationName)]
 pub struct RawSqlMigration {
+    name: &'static str,
     up_sql: &'static str,
 }
 
+// Not derived, since the derive macro names the migration after the file it is
+// declared in, which would give all raw SQL migrations the same name.
+impl MigrationName for RawSqlMigration {
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
 #[async_trait::async_trait]
 impl MigrationTrait for RawSqlMigration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
@@ -18,7 +26,11 @@ impl MigrationTrait for RawSqlMigration {
 
 macro_rules! raw_sql_migration {
     ($e:expr) => {
+        raw_sql_migration!($e, $e)
+    };
+    ($e:expr, $name:expr) => {
         Box::new(RawSqlMigration {
+            name: $name,
             up_sql: include_str!(concat!($e, ".sql")),
         })
     };
@@ -29,6 +41,22 @@ pub struct Migrator;
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![raw_sql_migration!("m20231003_143225_initial")]
+        vec![
+            // applied under the name the derive macro gave it, which deployed databases
+            // have recorded in `seaql_migrations`
+            raw_sql_migration!("m20231003_143225_initial", "mod"),
+            raw_sql_migration!("m20240415_120000_command"),
+            raw_sql_migration!("m20240418_093000_banphrase"),
+            raw_sql_migration!("m20240422_181500_moderation_log"),
+            raw_sql_migration!("m20240425_103000_special_authorization_invalidated"),
+            raw_sql_migration!("m20240428_150000_bot_settings"),
+            raw_sql_migration!("m20240502_120000_special_authorization_state"),
+            raw_sql_migration!("m20240506_090000_subscription_revocation"),
+            raw_sql_migration!("m20240510_120000_points"),
+            raw_sql_migration!("m20240514_120000_minigames"),
+            raw_sql_migration!("m20240518_120000_reward_action"),
+            raw_sql_migration!("m20240522_120000_timer"),
+            raw_sql_migration!("m20240526_120000_stream_session"),
+        ]
     }
 }
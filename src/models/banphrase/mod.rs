@@ -0,0 +1,84 @@
+mod op;
+
+pub use op::*;
+
+use crate::bot::commands::Permission;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "banphrase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub broadcaster_id: String,
+    pub name: String,
+    pub phrase: String,
+    pub operator: Operator,
+    pub action: Action,
+    pub timeout_seconds: i32,
+    pub case_sensitive: bool,
+    pub remove_accents: bool,
+    pub exempt_permission: Permission,
+    pub sub_immunity: bool,
+    pub enabled: bool,
+}
+
+/// How the phrase is compared against chat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Operator {
+    /// The message contains the phrase
+    #[sea_orm(string_value = "contains")]
+    Contains,
+    /// The message is exactly the phrase
+    #[sea_orm(string_value = "exact")]
+    Exact,
+    /// The phrase is a regular expression that matches the message
+    #[sea_orm(string_value = "regex")]
+    Regex,
+}
+
+/// What happens to a chatter whose message matched, ordered from least to most severe
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    DeriveActiveEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Action {
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    #[sea_orm(string_value = "timeout")]
+    Timeout,
+    #[sea_orm(string_value = "ban")]
+    Ban,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
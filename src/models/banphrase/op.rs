@@ -0,0 +1,13 @@
+use crate::models::banphrase;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+pub async fn find_enabled_for_channel(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<banphrase::Model>> {
+    Ok(banphrase::Entity::find()
+        .filter(banphrase::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(banphrase::Column::Enabled.eq(true))
+        .all(db)
+        .await?)
+}
@@ -1,5 +1,7 @@
 mod op;
 
+pub use op::*;
+
 use sea_orm::entity::prelude::*;
 use sea_orm::entity::LinkDef;
 use serde::{Deserialize, Serialize};
@@ -10,6 +12,8 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub broadcaster_id: String,
     pub bot_id: String,
+    /// Chat messages starting with this are interpreted as commands
+    pub command_prefix: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1 +1,18 @@
+use crate::models::bot;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ConnectionTrait, EntityTrait};
 
+pub async fn upsert(
+    bot: impl Into<bot::ActiveModel>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<bot::Model> {
+    let active_model = bot.into();
+    Ok(bot::Entity::insert(active_model)
+        .on_conflict(
+            OnConflict::column(bot::Column::BroadcasterId)
+                .update_columns([bot::Column::BotId, bot::Column::CommandPrefix])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await?)
+}
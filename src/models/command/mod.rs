@@ -0,0 +1,40 @@
+mod op;
+
+pub use op::*;
+
+use crate::bot::commands::Permission;
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "command")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub broadcaster_id: String,
+    pub trigger: String,
+    pub response: String,
+    pub global_cooldown_seconds: i32,
+    pub user_cooldown_seconds: i32,
+    pub min_permission: Permission,
+    pub enabled: bool,
+    pub use_count: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
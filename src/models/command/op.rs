@@ -0,0 +1,35 @@
+use crate::models::command;
+use sea_orm::sea_query::{Expr, Func};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+/// Find the enabled command of a channel with the given trigger, ignoring case.
+pub async fn find_by_trigger(
+    broadcaster_id: &str,
+    trigger: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<command::Model>> {
+    Ok(command::Entity::find()
+        .filter(command::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(command::Column::Enabled.eq(true))
+        .filter(
+            Expr::expr(Func::lower(Expr::col(command::Column::Trigger))).eq(trigger.to_lowercase()),
+        )
+        .one(db)
+        .await?)
+}
+
+/// Count one use of the command, returning the new use count.
+pub async fn increment_use_count(id: i32, db: &impl ConnectionTrait) -> anyhow::Result<i32> {
+    let updated = command::Entity::update_many()
+        .col_expr(
+            command::Column::UseCount,
+            Expr::col(command::Column::UseCount).add(1),
+        )
+        .filter(command::Column::Id.eq(id))
+        .exec_with_returning(db)
+        .await?;
+    match updated.first() {
+        Some(model) => Ok(model.use_count),
+        None => anyhow::bail!("Command {id} does not exist"),
+    }
+}
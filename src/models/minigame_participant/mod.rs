@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "minigame_participant")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[serde(skip)]
+    pub round_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: String,
+    /// Points taken from the participant
+    pub bet: i64,
+    /// Points given to the participant, 0 if they lost
+    pub payout: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::minigame_round::Entity",
+        from = "Column::RoundId",
+        to = "super::minigame_round::Column::Id"
+    )]
+    Round,
+}
+
+impl Related<super::minigame_round::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Round.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,58 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "minigame_round")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub broadcaster_id: String,
+    pub game: Game,
+    /// What happened, e.g. the symbols of a slot machine
+    pub outcome: String,
+    pub created_at: ChronoDateTimeUtc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Game {
+    #[sea_orm(string_value = "roulette")]
+    Roulette,
+    #[sea_orm(string_value = "duel")]
+    Duel,
+    #[sea_orm(string_value = "slots")]
+    Slots,
+    #[sea_orm(string_value = "heist")]
+    Heist,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+    #[sea_orm(has_many = "super::minigame_participant::Entity")]
+    Participant,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl Related<super::minigame_participant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Participant.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
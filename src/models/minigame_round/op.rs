@@ -0,0 +1,108 @@
+use crate::models::minigame_participant;
+use crate::models::minigame_round::{self, Game};
+use sea_orm::sea_query::Query;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::collections::HashMap;
+
+/// Points a participant put in and got back in a round.
+#[derive(Debug)]
+pub struct Stake {
+    pub user_id: String,
+    pub bet: i64,
+    pub payout: i64,
+}
+
+/// Save a played round. Should happen in the same transaction as the point transfers.
+pub async fn record(
+    broadcaster_id: &str,
+    game: Game,
+    outcome: String,
+    stakes: Vec<Stake>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<minigame_round::Model> {
+    let round = minigame_round::Entity::insert(minigame_round::ActiveModel {
+        id: NotSet,
+        broadcaster_id: Set(broadcaster_id.to_owned()),
+        game: Set(game),
+        outcome: Set(outcome),
+        created_at: NotSet,
+    })
+    .exec_with_returning(db)
+    .await?;
+    minigame_participant::Entity::insert_many(stakes.into_iter().map(|stake| {
+        minigame_participant::ActiveModel {
+            round_id: Set(round.id),
+            user_id: Set(stake.user_id),
+            bet: Set(stake.bet),
+            payout: Set(stake.payout),
+        }
+    }))
+    .exec_without_returning(db)
+    .await?;
+    Ok(round)
+}
+
+/// Restricts which rounds of a channel are returned. Unset fields match everything.
+#[derive(Debug, Default)]
+pub struct RoundFilter {
+    pub game: Option<Game>,
+    /// Only rounds this user took part in
+    pub user_id: Option<String>,
+}
+
+/// Up to `limit` rounds played in a channel matching `filter` with their participants, newest
+/// first. Only rounds older than the round with the ID `before` are returned, if given.
+pub async fn find_page(
+    broadcaster_id: &str,
+    filter: RoundFilter,
+    before: Option<i64>,
+    limit: u64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<(minigame_round::Model, Vec<minigame_participant::Model>)>> {
+    let mut query = minigame_round::Entity::find()
+        .filter(minigame_round::Column::BroadcasterId.eq(broadcaster_id));
+    if let Some(game) = filter.game {
+        query = query.filter(minigame_round::Column::Game.eq(game));
+    }
+    if let Some(user_id) = filter.user_id {
+        query = query.filter(
+            minigame_round::Column::Id.in_subquery(
+                Query::select()
+                    .column(minigame_participant::Column::RoundId)
+                    .from(minigame_participant::Entity)
+                    .and_where(minigame_participant::Column::UserId.eq(user_id))
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(before) = before {
+        query = query.filter(minigame_round::Column::Id.lt(before));
+    }
+    let rounds = query
+        .order_by_desc(minigame_round::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    // loaded separately, since the limit applies to rounds and not to participants
+    let mut participants: HashMap<i64, Vec<minigame_participant::Model>> = HashMap::new();
+    for participant in minigame_participant::Entity::find()
+        .filter(minigame_participant::Column::RoundId.is_in(rounds.iter().map(|round| round.id)))
+        .order_by_asc(minigame_participant::Column::UserId)
+        .all(db)
+        .await?
+    {
+        participants
+            .entry(participant.round_id)
+            .or_default()
+            .push(participant);
+    }
+    Ok(rounds
+        .into_iter()
+        .map(|round| {
+            let participants = participants.remove(&round.id).unwrap_or_default();
+            (round, participants)
+        })
+        .collect())
+}
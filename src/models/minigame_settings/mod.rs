@@ -0,0 +1,170 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use sea_orm::FromJsonQueryResult;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "minigame_settings")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub broadcaster_id: String,
+    pub roulette_enabled: bool,
+    pub roulette_min_bet: i64,
+    pub roulette_max_bet: i64,
+    pub roulette_cooldown_seconds: i32,
+    /// Chance to double the bet
+    pub roulette_win_percent: i32,
+    pub duel_enabled: bool,
+    pub duel_min_bet: i64,
+    pub duel_max_bet: i64,
+    pub duel_cooldown_seconds: i32,
+    /// A challenge that is not accepted in time is cancelled
+    pub duel_accept_seconds: i32,
+    pub slots_enabled: bool,
+    pub slots_min_bet: i64,
+    pub slots_max_bet: i64,
+    pub slots_cooldown_seconds: i32,
+    pub heist_enabled: bool,
+    pub heist_min_bet: i64,
+    pub heist_max_bet: i64,
+    /// Channel-wide, starting when a heist ends
+    pub heist_cooldown_seconds: i32,
+    /// Chatters can join a heist for this long after it was started
+    pub heist_join_seconds: i32,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub heist_payouts: HeistPayouts,
+}
+
+/// Odds of a heist depending on the size of the crew.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+#[serde(transparent)]
+pub struct HeistPayouts(pub Vec<HeistTier>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeistTier {
+    /// The tier with the highest `min_participants` not above the crew size applies
+    pub min_participants: u32,
+    /// Chance of each participant to get away with the loot
+    pub win_percent: u32,
+    /// Participants who get away receive this many percent of their bet
+    pub payout_percent: u32,
+}
+
+impl HeistPayouts {
+    /// The tier that applies to a crew of `participants`, if any.
+    pub fn tier(&self, participants: usize) -> Option<&HeistTier> {
+        self.0
+            .iter()
+            .filter(|tier| tier.min_participants as usize <= participants)
+            .max_by_key(|tier| tier.min_participants)
+    }
+}
+
+impl Model {
+    /// The settings of channels that did not configure minigames, same as the column defaults.
+    pub fn default_for(broadcaster_id: String) -> Model {
+        Model {
+            broadcaster_id,
+            roulette_enabled: true,
+            roulette_min_bet: 1,
+            roulette_max_bet: 10000,
+            roulette_cooldown_seconds: 60,
+            roulette_win_percent: 50,
+            duel_enabled: true,
+            duel_min_bet: 1,
+            duel_max_bet: 10000,
+            duel_cooldown_seconds: 60,
+            duel_accept_seconds: 60,
+            slots_enabled: true,
+            slots_min_bet: 1,
+            slots_max_bet: 10000,
+            slots_cooldown_seconds: 60,
+            heist_enabled: true,
+            heist_min_bet: 1,
+            heist_max_bet: 10000,
+            heist_cooldown_seconds: 600,
+            heist_join_seconds: 120,
+            heist_payouts: HeistPayouts(vec![
+                HeistTier {
+                    min_participants: 1,
+                    win_percent: 40,
+                    payout_percent: 200,
+                },
+                HeistTier {
+                    min_participants: 5,
+                    win_percent: 50,
+                    payout_percent: 200,
+                },
+                HeistTier {
+                    min_participants: 10,
+                    win_percent: 60,
+                    payout_percent: 250,
+                },
+            ]),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn min_participants(payouts: &HeistPayouts, participants: usize) -> Option<u32> {
+        payouts.tier(participants).map(|tier| tier.min_participants)
+    }
+
+    #[test]
+    fn heist_tier_is_the_largest_reached() {
+        let payouts = Model::default_for("1".to_owned()).heist_payouts;
+        assert_eq!(min_participants(&payouts, 1), Some(1));
+        assert_eq!(min_participants(&payouts, 4), Some(1));
+        assert_eq!(min_participants(&payouts, 5), Some(5));
+        assert_eq!(min_participants(&payouts, 9), Some(5));
+        assert_eq!(min_participants(&payouts, 10), Some(10));
+        assert_eq!(min_participants(&payouts, 100), Some(10));
+    }
+
+    #[test]
+    fn heist_tiers_do_not_need_to_be_ordered() {
+        let tier = |min_participants| HeistTier {
+            min_participants,
+            win_percent: 50,
+            payout_percent: 200,
+        };
+        let payouts = HeistPayouts(vec![tier(5), tier(2), tier(3)]);
+        assert_eq!(min_participants(&payouts, 4), Some(3));
+        assert_eq!(min_participants(&payouts, 6), Some(5));
+    }
+
+    #[test]
+    fn heist_needs_enough_participants_for_a_tier() {
+        let payouts = HeistPayouts(vec![HeistTier {
+            min_participants: 3,
+            win_percent: 50,
+            payout_percent: 200,
+        }]);
+        assert_eq!(min_participants(&payouts, 2), None);
+        assert_eq!(min_participants(&HeistPayouts(vec![]), 5), None);
+    }
+}
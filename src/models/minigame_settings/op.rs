@@ -0,0 +1,32 @@
+use crate::models::minigame_settings;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ConnectionTrait, EntityTrait, Iterable};
+
+/// Minigame settings of a channel, or the defaults if it has none.
+pub async fn find_or_default(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<minigame_settings::Model> {
+    Ok(minigame_settings::Entity::find_by_id(broadcaster_id)
+        .one(db)
+        .await?
+        .unwrap_or_else(|| minigame_settings::Model::default_for(broadcaster_id.to_owned())))
+}
+
+pub async fn upsert(
+    settings: impl Into<minigame_settings::ActiveModel>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<minigame_settings::Model> {
+    Ok(minigame_settings::Entity::insert(settings.into())
+        .on_conflict(
+            OnConflict::column(minigame_settings::Column::BroadcasterId)
+                .update_columns(
+                    minigame_settings::Column::iter().filter(|column| {
+                        !matches!(column, minigame_settings::Column::BroadcasterId)
+                    }),
+                )
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await?)
+}
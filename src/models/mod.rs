@@ -1,4 +1,20 @@
+pub mod banphrase;
 pub mod bot;
+pub mod command;
+pub mod minigame_participant;
+pub mod minigame_round;
+pub mod minigame_settings;
+pub mod moderation_log;
+pub mod points_settings;
+pub mod redemption_queue_entry;
+pub mod reward_action;
+pub mod special_authorization_state;
 pub mod special_twitch_authorization;
+pub mod stream_session;
+pub mod stream_session_change;
+pub mod subscription_revocation;
+pub mod timer;
+pub mod timer_message;
 pub mod user;
 pub mod user_authorization;
+pub mod user_channel_points;
@@ -0,0 +1,73 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "moderation_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub broadcaster_id: String,
+    pub action: Action,
+    pub actor_id: String,
+    pub target_user_id: Option<String>,
+    pub message_id: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub reason: String,
+    pub source: Source,
+    pub created_at: ChronoDateTimeUtc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Action {
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    #[sea_orm(string_value = "timeout")]
+    Timeout,
+    #[sea_orm(string_value = "ban")]
+    Ban,
+    #[sea_orm(string_value = "unban")]
+    Unban,
+}
+
+/// What caused a moderation action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Source {
+    /// A chatter used a chat command
+    #[sea_orm(string_value = "command")]
+    Command,
+    /// Automatic moderation, e.g. a banphrase
+    #[sea_orm(string_value = "filter")]
+    Filter,
+    /// A moderator acted through the dashboard or directly on Twitch
+    #[sea_orm(string_value = "manual")]
+    Manual,
+    /// A viewer redeemed a channel point reward, e.g. to time themselves out
+    #[sea_orm(string_value = "redemption")]
+    Redemption,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,44 @@
+use crate::models::moderation_log::{self, Action, Source};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+/// Restricts which entries of a channel's moderation log are returned. Unset fields match everything.
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    pub action: Option<Action>,
+    pub source: Option<Source>,
+    pub actor_id: Option<String>,
+    pub target_user_id: Option<String>,
+}
+
+/// Up to `limit` entries of a channel's moderation log matching `filter`, newest first.
+/// Only entries older than the entry with the ID `before` are returned, if given.
+pub async fn find_page(
+    broadcaster_id: &str,
+    filter: LogFilter,
+    before: Option<i64>,
+    limit: u64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<moderation_log::Model>> {
+    let mut query = moderation_log::Entity::find()
+        .filter(moderation_log::Column::BroadcasterId.eq(broadcaster_id));
+    if let Some(action) = filter.action {
+        query = query.filter(moderation_log::Column::Action.eq(action));
+    }
+    if let Some(source) = filter.source {
+        query = query.filter(moderation_log::Column::Source.eq(source));
+    }
+    if let Some(actor_id) = filter.actor_id {
+        query = query.filter(moderation_log::Column::ActorId.eq(actor_id));
+    }
+    if let Some(target_user_id) = filter.target_user_id {
+        query = query.filter(moderation_log::Column::TargetUserId.eq(target_user_id));
+    }
+    if let Some(before) = before {
+        query = query.filter(moderation_log::Column::Id.lt(before));
+    }
+    Ok(query
+        .order_by_desc(moderation_log::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?)
+}
@@ -0,0 +1,56 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "points_settings")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub broadcaster_id: String,
+    pub enabled: bool,
+    /// Every interval while the stream is online, each active chatter earns this many points
+    pub points_per_interval: i32,
+    pub interval_seconds: i32,
+    /// Chatters count as active for this long after their last chat message
+    pub active_seconds: i32,
+    /// Subscribers earn this many percent of `points_per_interval`
+    pub subscriber_multiplier_percent: i32,
+    /// VIPs earn this many percent of `points_per_interval`
+    pub vip_multiplier_percent: i32,
+}
+
+impl Model {
+    /// The settings of channels that did not configure points, same as the column defaults.
+    pub fn default_for(broadcaster_id: String) -> Model {
+        Model {
+            broadcaster_id,
+            enabled: true,
+            points_per_interval: 5,
+            interval_seconds: 300,
+            active_seconds: 900,
+            subscriber_multiplier_percent: 200,
+            vip_multiplier_percent: 150,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+use crate::models::points_settings;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ConnectionTrait, EntityTrait};
+
+/// Points settings of a channel, or the defaults if it has none.
+pub async fn find_or_default(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<points_settings::Model> {
+    Ok(points_settings::Entity::find_by_id(broadcaster_id)
+        .one(db)
+        .await?
+        .unwrap_or_else(|| points_settings::Model::default_for(broadcaster_id.to_owned())))
+}
+
+pub async fn upsert(
+    settings: impl Into<points_settings::ActiveModel>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<points_settings::Model> {
+    Ok(points_settings::Entity::insert(settings.into())
+        .on_conflict(
+            OnConflict::column(points_settings::Column::BroadcasterId)
+                .update_columns([
+                    points_settings::Column::Enabled,
+                    points_settings::Column::PointsPerInterval,
+                    points_settings::Column::IntervalSeconds,
+                    points_settings::Column::ActiveSeconds,
+                    points_settings::Column::SubscriberMultiplierPercent,
+                    points_settings::Column::VipMultiplierPercent,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await?)
+}
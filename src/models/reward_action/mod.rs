@@ -0,0 +1,65 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "reward_action")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub broadcaster_id: String,
+    pub reward_id: String,
+    pub action: Action,
+    /// Only set if `action` is [`Action::Command`]
+    pub command_trigger: Option<String>,
+    /// Only set if `action` is [`Action::Message`]
+    pub message: Option<String>,
+    /// Only set if `action` is [`Action::Timeout`]
+    pub timeout_seconds: Option<i32>,
+    /// Only set if `action` is [`Action::Queue`]
+    pub queue_name: Option<String>,
+    /// Mark redemptions as fulfilled if the action succeeds, or cancel them to refund the points
+    /// if it fails. Twitch only allows this for rewards created with our client ID.
+    pub update_redemption: bool,
+    pub enabled: bool,
+}
+
+/// What the bot does when the reward is redeemed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Action {
+    /// Respond like a custom command of the channel, with the input of the redeemer as `$(args)`
+    #[sea_orm(string_value = "command")]
+    Command,
+    /// Send a message, which may contain the same variables as custom command responses
+    #[sea_orm(string_value = "message")]
+    Message,
+    /// Time out the redeemer
+    #[sea_orm(string_value = "timeout")]
+    Timeout,
+    /// Add the redeemer to a queue, managed through the API
+    #[sea_orm(string_value = "queue")]
+    Queue,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
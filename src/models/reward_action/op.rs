@@ -0,0 +1,16 @@
+use crate::models::reward_action;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+/// Find the enabled action bound to a reward of a channel.
+pub async fn find_by_reward(
+    broadcaster_id: &str,
+    reward_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<reward_action::Model>> {
+    Ok(reward_action::Entity::find()
+        .filter(reward_action::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(reward_action::Column::RewardId.eq(reward_id))
+        .filter(reward_action::Column::Enabled.eq(true))
+        .one(db)
+        .await?)
+}
@@ -0,0 +1,37 @@
+use crate::models::special_authorization_state;
+use chrono::{DateTime, Utc};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+/// Remove the state from the database and return it, if it exists and was created after `not_before`.
+/// If the same state is taken concurrently, only one of the callers gets it.
+pub async fn take(
+    state: &str,
+    not_before: DateTime<Utc>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<special_authorization_state::Model>> {
+    let Some(model) = special_authorization_state::Entity::find_by_id(state)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let deleted = special_authorization_state::Entity::delete_by_id(state)
+        .exec(db)
+        .await?;
+    if deleted.rows_affected == 0 || model.created_at < not_before {
+        return Ok(None);
+    }
+    Ok(Some(model))
+}
+
+/// Remove all states created before `not_before`.
+pub async fn delete_expired(
+    not_before: DateTime<Utc>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<()> {
+    special_authorization_state::Entity::delete_many()
+        .filter(special_authorization_state::Column::CreatedAt.lt(not_before))
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -1,6 +1,8 @@
 mod op;
+mod scope;
 
 pub use op::*;
+pub use scope::*;
 
 use sea_orm::entity::prelude::*;
 
@@ -16,6 +18,8 @@ pub struct Model {
     pub twitch_access_token: String,
     pub twitch_refresh_token: String,
     pub valid_until: ChronoDateTimeUtc,
+    /// Set once Twitch rejected the refresh token, the authorization has to be granted again
+    pub invalidated_at: Option<ChronoDateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
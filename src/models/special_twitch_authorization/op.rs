@@ -1,6 +1,7 @@
 use crate::models::special_twitch_authorization;
+use chrono::{DateTime, Utc};
 use sea_orm::sea_query::OnConflict;
-use sea_orm::{ConnectionTrait, EntityTrait};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
 
 pub async fn upsert(
     special_twitch_authorization: impl Into<special_twitch_authorization::ActiveModel>,
@@ -16,6 +17,7 @@ pub async fn upsert(
                     special_twitch_authorization::Column::TwitchAccessToken,
                     special_twitch_authorization::Column::TwitchRefreshToken,
                     special_twitch_authorization::Column::ValidUntil,
+                    special_twitch_authorization::Column::InvalidatedAt,
                 ])
                 .to_owned(),
         )
@@ -23,3 +25,28 @@ pub async fn upsert(
         .await?;
     Ok(())
 }
+
+/// The Twitch access token granted by a user through the special authorization flow, if any
+/// and it has not been invalidated.
+pub async fn find_access_token(
+    user_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<String>> {
+    Ok(special_twitch_authorization::Entity::find_by_id(user_id)
+        .filter(special_twitch_authorization::Column::InvalidatedAt.is_null())
+        .one(db)
+        .await?
+        .map(|authorization| authorization.twitch_access_token))
+}
+
+/// Authorizations that have not been invalidated and expire before `deadline`.
+pub async fn find_expiring(
+    deadline: DateTime<Utc>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<special_twitch_authorization::Model>> {
+    Ok(special_twitch_authorization::Entity::find()
+        .filter(special_twitch_authorization::Column::InvalidatedAt.is_null())
+        .filter(special_twitch_authorization::Column::ValidUntil.lt(deadline))
+        .all(db)
+        .await?)
+}
@@ -0,0 +1,113 @@
+use crate::models::special_authorization_state::Kind;
+use crate::models::special_twitch_authorization;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashSet;
+
+// The scopes added in each scope version, starting with version 1. Add a new entry whenever a
+// scope gets added to a set. Nothing has to change when a scope is no longer needed, though.
+// TODO review these, make sure we're only requesting what we need
+const BOT_SCOPE_HISTORY: &[&[&str]] = &[
+    &[
+        "moderator:manage:announcements",
+        "moderator:manage:banned_users",
+        "moderator:manage:chat_messages",
+        "user:manage:whispers",
+        "channel:moderate",
+        "chat:edit",
+        "chat:read",
+        "whispers:read",
+        "whispers:edit",
+    ],
+    &["user:write:chat"],
+];
+const BROADCASTER_SCOPE_HISTORY: &[&[&str]] = &[
+    &[
+        "channel:read:subscriptions",
+        "channel:manage:broadcast",
+        "channel:read:vips",
+        "moderation:read",
+    ],
+    &["channel:moderate"],
+    &["moderator:read:followers", "channel:read:redemptions"],
+    &["bits:read"],
+    &["channel:manage:redemptions"],
+];
+
+pub const BOT_SCOPE_VERSION: i16 = BOT_SCOPE_HISTORY.len() as i16;
+pub const BROADCASTER_SCOPE_VERSION: i16 = BROADCASTER_SCOPE_HISTORY.len() as i16;
+
+lazy_static! {
+    /// Scope requested from bot accounts
+    pub static ref BOT_SCOPE: HashSet<&'static str> = scope(Kind::Bot, BOT_SCOPE_VERSION);
+    /// Scope requested from broadcasters
+    pub static ref BROADCASTER_SCOPE: HashSet<&'static str> =
+        scope(Kind::Broadcaster, BROADCASTER_SCOPE_VERSION);
+}
+
+fn scope_at(history: &[&[&'static str]], version: i16) -> HashSet<&'static str> {
+    history
+        .iter()
+        .take(version.max(0) as usize)
+        .flat_map(|scopes| scopes.iter().copied())
+        .collect()
+}
+
+/// The scope granted by authorizations of the given kind and version.
+pub fn scope(kind: Kind, version: i16) -> HashSet<&'static str> {
+    match kind {
+        Kind::Bot => scope_at(BOT_SCOPE_HISTORY, version),
+        Kind::Broadcaster => scope_at(BROADCASTER_SCOPE_HISTORY, version),
+    }
+}
+
+/// The scope version new authorizations of the given kind are granted with.
+pub fn current_scope_version(kind: Kind) -> i16 {
+    match kind {
+        Kind::Bot => BOT_SCOPE_VERSION,
+        Kind::Broadcaster => BROADCASTER_SCOPE_VERSION,
+    }
+}
+
+/// Whether an authorization can be used for a purpose, as shown on the dashboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorizationStatus {
+    /// Granted with the current scope version
+    Valid,
+    /// Granted with an older scope version. Features needing the newer scopes don't work until
+    /// the user authorizes again.
+    ReauthorizeRequired,
+    /// Twitch rejected the refresh token, nothing works until the user authorizes again
+    Invalidated,
+    /// Never granted for this purpose
+    Missing,
+}
+
+impl special_twitch_authorization::Model {
+    pub fn scope_version(&self, kind: Kind) -> Option<i16> {
+        match kind {
+            Kind::Bot => self.bot_scope_version,
+            Kind::Broadcaster => self.broadcaster_scope_version,
+        }
+    }
+
+    /// Whether the authorization can currently be used for something requiring `scope`.
+    pub fn has_scope(&self, kind: Kind, scope: &str) -> bool {
+        self.invalidated_at.is_none()
+            && self
+                .scope_version(kind)
+                .is_some_and(|version| self::scope(kind, version).contains(scope))
+    }
+
+    pub fn status(&self, kind: Kind) -> AuthorizationStatus {
+        match self.scope_version(kind) {
+            None => AuthorizationStatus::Missing,
+            Some(_) if self.invalidated_at.is_some() => AuthorizationStatus::Invalidated,
+            Some(version) if version < current_scope_version(kind) => {
+                AuthorizationStatus::ReauthorizeRequired
+            }
+            Some(_) => AuthorizationStatus::Valid,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "stream_session")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub broadcaster_id: String,
+    pub twitch_stream_id: String,
+    pub started_at: ChronoDateTimeUtc,
+    /// `None` while the stream is live
+    pub ended_at: Option<ChronoDateTimeUtc>,
+    /// Highest viewer count seen while the stream was live, sampled every few minutes
+    pub peak_viewers: i32,
+    pub chat_messages: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+    #[sea_orm(has_many = "super::stream_session_change::Entity")]
+    Change,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl Related<super::stream_session_change::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Change.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,319 @@
+use crate::models::{stream_session, stream_session_change};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::{Alias, Expr, Func};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::collections::HashMap;
+
+/// The title and category of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub title: String,
+    pub category_id: String,
+    pub category_name: String,
+}
+
+/// The session of the stream a channel is live with right now.
+pub async fn find_live(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<stream_session::Model>> {
+    Ok(stream_session::Entity::find()
+        .filter(stream_session::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(stream_session::Column::EndedAt.is_null())
+        .one(db)
+        .await?)
+}
+
+/// The session of the stream of a channel that ended most recently.
+pub async fn find_last_ended(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<stream_session::Model>> {
+    Ok(stream_session::Entity::find()
+        .filter(stream_session::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(stream_session::Column::EndedAt.is_not_null())
+        .order_by_desc(stream_session::Column::EndedAt)
+        .one(db)
+        .await?)
+}
+
+/// Start the session of a stream, or continue it if it was recorded before, e.g. because the bot
+/// restarted while the channel was live. Other sessions of the channel that are still open are
+/// ended, since their end was missed. Should happen in a transaction.
+pub async fn start(
+    broadcaster_id: &str,
+    twitch_stream_id: &str,
+    started_at: DateTime<Utc>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<stream_session::Model> {
+    stream_session::Entity::update_many()
+        .col_expr(stream_session::Column::EndedAt, Expr::value(Utc::now()))
+        .filter(stream_session::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(stream_session::Column::TwitchStreamId.ne(twitch_stream_id))
+        .filter(stream_session::Column::EndedAt.is_null())
+        .exec(db)
+        .await?;
+
+    let existing = stream_session::Entity::find()
+        .filter(stream_session::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(stream_session::Column::TwitchStreamId.eq(twitch_stream_id))
+        .one(db)
+        .await?;
+    if let Some(session) = existing {
+        if session.ended_at.is_none() {
+            return Ok(session);
+        }
+        // it was ended by mistake, e.g. because Twitch did not list the stream for a moment
+        let reopened = stream_session::Entity::update_many()
+            .col_expr(
+                stream_session::Column::EndedAt,
+                Expr::value(None::<DateTime<Utc>>),
+            )
+            .filter(stream_session::Column::Id.eq(session.id))
+            .exec_with_returning(db)
+            .await?;
+        return reopened
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Stream session {} disappeared", session.id));
+    }
+
+    Ok(stream_session::Entity::insert(stream_session::ActiveModel {
+        id: NotSet,
+        broadcaster_id: Set(broadcaster_id.to_owned()),
+        twitch_stream_id: Set(twitch_stream_id.to_owned()),
+        started_at: Set(started_at),
+        ended_at: Set(None),
+        peak_viewers: NotSet,
+        chat_messages: NotSet,
+    })
+    .exec_with_returning(db)
+    .await?)
+}
+
+/// Mark a session as ended, unless it already is.
+pub async fn end(
+    session_id: i64,
+    ended_at: DateTime<Utc>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<()> {
+    stream_session::Entity::update_many()
+        .col_expr(stream_session::Column::EndedAt, Expr::value(ended_at))
+        .filter(stream_session::Column::Id.eq(session_id))
+        .filter(stream_session::Column::EndedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Count chat messages sent during a session, and raise its peak viewer count to `viewers` if
+/// that is higher.
+pub async fn add_stats(
+    session_id: i64,
+    chat_messages: i64,
+    viewers: Option<i32>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<()> {
+    let mut update = stream_session::Entity::update_many()
+        .col_expr(
+            stream_session::Column::ChatMessages,
+            Expr::col(stream_session::Column::ChatMessages).add(chat_messages),
+        )
+        .filter(stream_session::Column::Id.eq(session_id));
+    if let Some(viewers) = viewers {
+        update = update.col_expr(
+            stream_session::Column::PeakViewers,
+            Func::cust(Alias::new("GREATEST"))
+                .args([
+                    Expr::col(stream_session::Column::PeakViewers).into(),
+                    Expr::value(viewers),
+                ])
+                .into(),
+        );
+    }
+    update.exec(db).await?;
+    Ok(())
+}
+
+/// Record the title and category of a session, unless they did not change since they were
+/// last recorded.
+pub async fn record_change(
+    session_id: i64,
+    info: StreamInfo,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<()> {
+    let latest = stream_session_change::Entity::find()
+        .filter(stream_session_change::Column::SessionId.eq(session_id))
+        .order_by_desc(stream_session_change::Column::Id)
+        .one(db)
+        .await?;
+    if latest
+        .is_some_and(|latest| latest.title == info.title && latest.category_id == info.category_id)
+    {
+        return Ok(());
+    }
+
+    stream_session_change::Entity::insert(stream_session_change::ActiveModel {
+        id: NotSet,
+        session_id: Set(session_id),
+        title: Set(info.title),
+        category_id: Set(info.category_id),
+        category_name: Set(info.category_name),
+        changed_at: NotSet,
+    })
+    .exec_without_returning(db)
+    .await?;
+    Ok(())
+}
+
+/// The title and category a session had last.
+pub async fn latest_change(
+    session_id: i64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<stream_session_change::Model>> {
+    Ok(stream_session_change::Entity::find()
+        .filter(stream_session_change::Column::SessionId.eq(session_id))
+        .order_by_desc(stream_session_change::Column::Id)
+        .one(db)
+        .await?)
+}
+
+/// Up to `limit` sessions of a channel with their changes, newest first. Only sessions older than
+/// the session with the ID `before` are returned, if given.
+pub async fn find_page(
+    broadcaster_id: &str,
+    before: Option<i64>,
+    limit: u64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<(stream_session::Model, Vec<stream_session_change::Model>)>> {
+    let mut query = stream_session::Entity::find()
+        .filter(stream_session::Column::BroadcasterId.eq(broadcaster_id));
+    if let Some(before) = before {
+        query = query.filter(stream_session::Column::Id.lt(before));
+    }
+    let sessions = query
+        .order_by_desc(stream_session::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    // loaded separately, since the limit applies to sessions and not to changes
+    let mut changes: HashMap<i64, Vec<stream_session_change::Model>> = HashMap::new();
+    for change in stream_session_change::Entity::find()
+        .filter(
+            stream_session_change::Column::SessionId
+                .is_in(sessions.iter().map(|session| session.id)),
+        )
+        .order_by_asc(stream_session_change::Column::Id)
+        .all(db)
+        .await?
+    {
+        changes.entry(change.session_id).or_default().push(change);
+    }
+    Ok(sessions
+        .into_iter()
+        .map(|session| {
+            let changes = changes.remove(&session.id).unwrap_or_default();
+            (session, changes)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+
+    fn session(id: i64, twitch_stream_id: &str, ended: bool) -> stream_session::Model {
+        let started_at = "2024-05-26T18:00:00Z".parse().unwrap();
+        stream_session::Model {
+            id,
+            broadcaster_id: "1000".to_owned(),
+            twitch_stream_id: twitch_stream_id.to_owned(),
+            started_at,
+            ended_at: ended.then(|| "2024-05-26T20:00:00Z".parse().unwrap()),
+            peak_viewers: 0,
+            chat_messages: 0,
+        }
+    }
+
+    fn ended_sessions(rows_affected: u64) -> MockExecResult {
+        MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        }
+    }
+
+    /// The SQL of the statements that were run, in order.
+    fn statements(db: sea_orm::DatabaseConnection) -> Vec<String> {
+        db.into_transaction_log()
+            .iter()
+            .map(|transaction| format!("{transaction:?}"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn restart_while_live_continues_the_session() {
+        let live = session(1, "stream-a", false);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([ended_sessions(0)])
+            .append_query_results([[live.clone()]])
+            .into_connection();
+
+        let started = start("1000", "stream-a", live.started_at, &db)
+            .await
+            .unwrap();
+        assert_eq!(started, live);
+        let statements = statements(db);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].contains("UPDATE"));
+        assert!(statements[1].contains("SELECT"));
+    }
+
+    #[tokio::test]
+    async fn missed_offline_ends_the_previous_session() {
+        let new = session(2, "stream-b", false);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([ended_sessions(1)])
+            .append_query_results([Vec::<stream_session::Model>::new()])
+            .append_query_results([[new.clone()]])
+            .into_connection();
+
+        let started = start("1000", "stream-b", new.started_at, &db)
+            .await
+            .unwrap();
+        assert_eq!(started, new);
+        let statements = statements(db);
+        assert_eq!(statements.len(), 3);
+        // every other open session of the channel is ended
+        assert!(statements[0].contains(
+            r#"WHERE \"stream_session\".\"broadcaster_id\" = $2 AND \"stream_session\".\"twitch_stream_id\" <> $3 AND \"stream_session\".\"ended_at\" IS NULL"#
+        ));
+        assert!(statements[2].contains("INSERT INTO"));
+    }
+
+    #[tokio::test]
+    async fn reopens_session_ended_during_helix_gap() {
+        let ended = session(1, "stream-a", true);
+        let reopened = session(1, "stream-a", false);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results([ended_sessions(0)])
+            .append_query_results([[ended.clone()]])
+            .append_query_results([[reopened.clone()]])
+            .into_connection();
+
+        let started = start("1000", "stream-a", ended.started_at, &db)
+            .await
+            .unwrap();
+        assert_eq!(started, reopened);
+        let statements = statements(db);
+        assert_eq!(statements.len(), 3);
+        assert!(
+            statements[2].contains(r#"SET \"ended_at\" = $1 WHERE \"stream_session\".\"id\" = $2"#)
+        );
+        assert!(!statements
+            .iter()
+            .any(|statement| statement.contains("INSERT")));
+    }
+}
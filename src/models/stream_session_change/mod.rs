@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "stream_session_change")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(skip)]
+    pub session_id: i64,
+    pub title: String,
+    pub category_id: String,
+    pub category_name: String,
+    pub changed_at: ChronoDateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::stream_session::Entity",
+        from = "Column::SessionId",
+        to = "super::stream_session::Column::Id"
+    )]
+    Session,
+}
+
+impl Related<super::stream_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
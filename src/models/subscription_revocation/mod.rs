@@ -0,0 +1,71 @@
+mod op;
+
+pub use op::*;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "subscription_revocation")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub broadcaster_id: String,
+    pub subscription_type: String,
+    pub subscription_version: String,
+    pub reason: Reason,
+    pub revoked_at: ChronoDateTimeUtc,
+    pub resolved_at: Option<ChronoDateTimeUtc>,
+}
+
+/// Why Twitch revoked a subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum Reason {
+    /// A user the subscription depends on removed the authorization of the application
+    #[sea_orm(string_value = "authorization_revoked")]
+    AuthorizationRevoked,
+    /// A user the subscription depends on was banned or deleted their account
+    #[sea_orm(string_value = "user_removed")]
+    UserRemoved,
+    /// The moderator the subscription was created with is no moderator anymore
+    #[sea_orm(string_value = "moderator_removed")]
+    ModeratorRemoved,
+    /// Twitch no longer supports the subscription type or version
+    #[sea_orm(string_value = "version_removed")]
+    VersionRemoved,
+    #[sea_orm(string_value = "other")]
+    Other,
+}
+
+impl Reason {
+    /// Classify the status of a revoked subscription.
+    pub fn from_status(status: &str) -> Self {
+        match status {
+            "authorization_revoked" => Reason::AuthorizationRevoked,
+            "user_removed" => Reason::UserRemoved,
+            "moderator_removed" => Reason::ModeratorRemoved,
+            "version_removed" => Reason::VersionRemoved,
+            _ => Reason::Other,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::BroadcasterId",
+        to = "super::user::Column::Id"
+    )]
+    Broadcaster,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Broadcaster.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
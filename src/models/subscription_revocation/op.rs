@@ -0,0 +1,41 @@
+use crate::models::subscription_revocation;
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+
+/// Revocations in the channel of `broadcaster_id` that the bot has not recovered from yet,
+/// newest first.
+pub async fn find_unresolved(
+    broadcaster_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<subscription_revocation::Model>> {
+    Ok(subscription_revocation::Entity::find()
+        .filter(subscription_revocation::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(subscription_revocation::Column::ResolvedAt.is_null())
+        .order_by_desc(subscription_revocation::Column::Id)
+        .all(db)
+        .await?)
+}
+
+/// Mark the unresolved revocations of the given subscription types in the channel of
+/// `broadcaster_id` as resolved, after subscribing to them again.
+pub async fn resolve(
+    broadcaster_id: &str,
+    subscription_types: &[&str],
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<()> {
+    subscription_revocation::Entity::update_many()
+        .set(subscription_revocation::ActiveModel {
+            resolved_at: Set(Some(Utc::now())),
+            ..Default::default()
+        })
+        .filter(subscription_revocation::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(
+            subscription_revocation::Column::SubscriptionType
+                .is_in(subscription_types.iter().copied()),
+        )
+        .filter(subscription_revocation::Column::ResolvedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::models::{timer, timer_message};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+
+/// A message of a timer, as configured through the API.
+#[derive(Debug)]
+pub struct NewMessage {
+    pub message: String,
+    pub stream_state: timer_message::StreamState,
+}
+
+/// The timers of a channel with their messages, oldest first. Disabled timers are only included
+/// if `include_disabled` is set.
+pub async fn find_with_messages(
+    broadcaster_id: &str,
+    include_disabled: bool,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<(timer::Model, Vec<timer_message::Model>)>> {
+    let mut query = timer::Entity::find().filter(timer::Column::BroadcasterId.eq(broadcaster_id));
+    if !include_disabled {
+        query = query.filter(timer::Column::Enabled.eq(true));
+    }
+    Ok(query
+        .order_by_asc(timer::Column::Id)
+        .find_with_related(timer_message::Entity)
+        .order_by_asc(timer_message::Column::Id)
+        .all(db)
+        .await?)
+}
+
+/// Replace all messages of a timer. Should happen in the same transaction as the change of the
+/// timer itself.
+pub async fn replace_messages(
+    timer_id: i32,
+    messages: Vec<NewMessage>,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<timer_message::Model>> {
+    timer_message::Entity::delete_many()
+        .filter(timer_message::Column::TimerId.eq(timer_id))
+        .exec(db)
+        .await?;
+    let mut inserted = Vec::with_capacity(messages.len());
+    for message in messages {
+        inserted.push(
+            timer_message::Entity::insert(timer_message::ActiveModel {
+                id: NotSet,
+                timer_id: Set(timer_id),
+                message: Set(message.message),
+                stream_state: Set(message.stream_state),
+            })
+            .exec_with_returning(db)
+            .await?,
+        );
+    }
+    Ok(inserted)
+}
@@ -0,0 +1,56 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "timer_message")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[serde(skip)]
+    pub timer_id: i32,
+    /// A template like the responses of custom commands
+    pub message: String,
+    pub stream_state: StreamState,
+}
+
+/// Whether the stream has to be live for a message to be posted. Messages are posted while the
+/// stream is online by default, `Offline` and `Any` messages are the way to post while it is not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, DeriveActiveEnum)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum StreamState {
+    #[sea_orm(string_value = "online")]
+    Online,
+    #[sea_orm(string_value = "offline")]
+    Offline,
+    #[sea_orm(string_value = "any")]
+    Any,
+}
+
+impl StreamState {
+    pub fn matches(self, online: bool) -> bool {
+        match self {
+            StreamState::Online => online,
+            StreamState::Offline => !online,
+            StreamState::Any => true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::timer::Entity",
+        from = "Column::TimerId",
+        to = "super::timer::Column::Id"
+    )]
+    Timer,
+}
+
+impl Related<super::timer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Timer.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
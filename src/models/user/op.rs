@@ -1,6 +1,6 @@
 use crate::models::user;
-use sea_orm::sea_query::OnConflict;
-use sea_orm::{ConnectionTrait, EntityTrait};
+use sea_orm::sea_query::{Expr, Func, OnConflict};
+use sea_orm::{ConnectionTrait, EntityTrait, QueryFilter};
 
 pub async fn upsert_user(
     user_basics: impl Into<user::ActiveModel>,
@@ -17,3 +17,14 @@ pub async fn upsert_user(
         .await?;
     Ok(())
 }
+
+/// Find a user by their login name, ignoring case.
+pub async fn find_by_login(
+    login: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<user::Model>> {
+    Ok(user::Entity::find()
+        .filter(Expr::expr(Func::lower(Expr::col(user::Column::Login))).eq(login.to_lowercase()))
+        .one(db)
+        .await?)
+}
@@ -0,0 +1,162 @@
+use crate::models::{user, user_channel_points};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+/// Points of a user in a channel, 0 if they never earned any.
+pub async fn get_points(
+    broadcaster_id: &str,
+    user_id: &str,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<i64> {
+    Ok(
+        user_channel_points::Entity::find_by_id((broadcaster_id.to_owned(), user_id.to_owned()))
+            .one(db)
+            .await?
+            .map_or(0, |model| model.points),
+    )
+}
+
+/// Add `amount` points to the balance of a user, which may be negative to take points away.
+/// Returns the new balance, or `None` if the user does not have enough points, in which case
+/// nothing changed. The user must exist in the `user` table.
+pub async fn add_points(
+    broadcaster_id: &str,
+    user_id: &str,
+    amount: i64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Option<i64>> {
+    if amount < 0 {
+        // nobody can have more points than fit into an i64
+        let Some(required) = amount.checked_neg() else {
+            return Ok(None);
+        };
+        // only an existing balance can be high enough
+        let updated = user_channel_points::Entity::update_many()
+            .col_expr(
+                user_channel_points::Column::Points,
+                Expr::col(user_channel_points::Column::Points).add(amount),
+            )
+            .filter(user_channel_points::Column::BroadcasterId.eq(broadcaster_id))
+            .filter(user_channel_points::Column::UserId.eq(user_id))
+            .filter(user_channel_points::Column::Points.gte(required))
+            .exec_with_returning(db)
+            .await?;
+        return Ok(updated.first().map(|model| model.points));
+    }
+
+    let model = user_channel_points::Entity::insert(user_channel_points::ActiveModel {
+        broadcaster_id: Set(broadcaster_id.to_owned()),
+        user_id: Set(user_id.to_owned()),
+        points: Set(amount),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            user_channel_points::Column::BroadcasterId,
+            user_channel_points::Column::UserId,
+        ])
+        .value(
+            user_channel_points::Column::Points,
+            Expr::col((
+                user_channel_points::Entity,
+                user_channel_points::Column::Points,
+            ))
+            .add(amount),
+        )
+        .to_owned(),
+    )
+    .exec_with_returning(db)
+    .await?;
+    Ok(Some(model.points))
+}
+
+/// The users with the most points in a channel, highest first.
+pub async fn find_leaderboard(
+    broadcaster_id: &str,
+    offset: u64,
+    limit: u64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<Vec<(user_channel_points::Model, user::Model)>> {
+    Ok(user_channel_points::Entity::find()
+        .find_also_related(user::Entity)
+        .filter(user_channel_points::Column::BroadcasterId.eq(broadcaster_id))
+        .order_by_desc(user_channel_points::Column::Points)
+        .order_by_asc(user_channel_points::Column::UserId)
+        .offset(offset)
+        .limit(limit)
+        .all(db)
+        .await?
+        .into_iter()
+        // the foreign key guarantees the user exists
+        .filter_map(|(points, user)| Some((points, user?)))
+        .collect())
+}
+
+/// Position of a balance on the leaderboard of a channel, starting at 1. Users with the same
+/// number of points share a rank.
+pub async fn rank(
+    broadcaster_id: &str,
+    points: i64,
+    db: &impl ConnectionTrait,
+) -> anyhow::Result<u64> {
+    let ahead = user_channel_points::Entity::find()
+        .filter(user_channel_points::Column::BroadcasterId.eq(broadcaster_id))
+        .filter(user_channel_points::Column::Points.gt(points))
+        .count(db)
+        .await?;
+    Ok(ahead + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+
+    fn balance(points: i64) -> user_channel_points::Model {
+        user_channel_points::Model {
+            broadcaster_id: "1000".to_owned(),
+            user_id: "2000".to_owned(),
+            points,
+        }
+    }
+
+    #[tokio::test]
+    async fn takes_points_from_high_enough_balance() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[balance(70)]])
+            .into_connection();
+
+        let points = add_points("1000", "2000", -30, &db).await.unwrap();
+        assert_eq!(points, Some(70));
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"UPDATE "user_channel_points" SET "points" = "points" + $1 WHERE "user_channel_points"."broadcaster_id" = $2 AND "user_channel_points"."user_id" = $3 AND "user_channel_points"."points" >= $4 RETURNING "broadcaster_id", "user_id", "points""#,
+                [(-30i64).into(), "1000".into(), "2000".into(), 30i64.into()],
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_can_not_become_negative() {
+        // the update matches no row if the balance is too low
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<user_channel_points::Model>::new()])
+            .into_connection();
+
+        let points = add_points("1000", "2000", -30, &db).await.unwrap();
+        assert_eq!(points, None);
+    }
+
+    #[tokio::test]
+    async fn taking_i64_min_points_does_not_overflow() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+
+        let points = add_points("1000", "2000", i64::MIN, &db).await.unwrap();
+        assert_eq!(points, None);
+        assert!(db.into_transaction_log().is_empty());
+    }
+}
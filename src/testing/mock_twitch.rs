@@ -0,0 +1,889 @@
+use crate::api::twitch::ApiClientCredentials;
+use crate::bot::chat::{self, ChatQueue};
+use crate::bot::conduit::ConduitManager;
+use crate::bot::BotContext;
+use crate::models::stream_session;
+use crate::Config;
+use axum::body::Bytes;
+use axum::extract::{Query, RawQuery, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, patch, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use http::{HeaderMap, StatusCode};
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::tungstenite;
+use tokio_util::sync::CancellationToken;
+
+pub const CLIENT_ID: &str = "mock-client-id";
+pub const CLIENT_SECRET: &str = "mock-client-secret";
+/// The only authorization code the mock OAuth2 server accepts
+pub const AUTHORIZATION_CODE: &str = "mock-authorization-code";
+pub const REFRESH_TOKEN: &str = "mock-refresh-token";
+pub const USER_ACCESS_TOKEN: &str = "mock-user-access-token";
+pub const APP_ACCESS_TOKEN: &str = "mock-app-access-token";
+/// The user authorizing with [`AUTHORIZATION_CODE`]
+pub const USER_ID: &str = "1000";
+pub const USER_LOGIN: &str = "mockstreamer";
+/// The bot account of the bots made by [`MockTwitch::bot_context`]
+pub const BOT_USER_ID: &str = "2000";
+/// A channel point reward created by another application, whose redemptions can't be updated
+pub const FOREIGN_REWARD_ID: &str = "foreign-reward";
+
+/// Small, so that tests go through more than one page of subscriptions
+const SUBSCRIPTIONS_PAGE_SIZE: usize = 2;
+
+/// Everything the mock server received, for tests to inspect.
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub conduits: Vec<MockConduit>,
+    pub subscriptions: Vec<Value>,
+    pub bans: Vec<MockBan>,
+    pub unbans: Vec<(String, String)>,
+    pub deleted_messages: Vec<String>,
+    pub chat_messages: Vec<MockChatMessage>,
+    pub redemption_updates: Vec<MockRedemptionUpdate>,
+    pub revoked_tokens: Vec<String>,
+    /// Websocket sessions currently connected to the mock EventSub server
+    pub websocket_sessions: Vec<String>,
+    next_id: u64,
+}
+
+impl MockState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}-{}", self.next_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockConduit {
+    pub id: String,
+    pub shard_count: usize,
+    /// Transports of the shards, by shard id
+    pub shards: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockBan {
+    pub broadcaster_id: String,
+    pub moderator_id: String,
+    pub user_id: String,
+    pub duration: Option<u32>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockChatMessage {
+    pub broadcaster_id: String,
+    pub sender_id: String,
+    pub message: String,
+    pub reply_parent_message_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRedemptionUpdate {
+    pub broadcaster_id: String,
+    pub reward_id: String,
+    pub redemption_id: String,
+    pub status: String,
+}
+
+/// A local stand-in for the Helix, OAuth2 and EventSub websocket servers of Twitch, serving on
+/// random ports until it is dropped.
+pub struct MockTwitch {
+    http_url: String,
+    websocket_url: String,
+    state: Arc<Mutex<MockState>>,
+    /// Messages sent to all connected websocket sessions
+    websocket_messages: broadcast::Sender<String>,
+    shutdown: CancellationToken,
+}
+
+#[derive(Clone)]
+struct AppState {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTwitch {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shutdown = CancellationToken::new();
+
+        let http_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_url = format!("http://{}", http_listener.local_addr().unwrap());
+        let router = router(AppState {
+            state: state.clone(),
+        });
+        let http_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            axum::serve(http_listener, router)
+                .with_graceful_shutdown(async move { http_shutdown.cancelled().await })
+                .await
+                .unwrap();
+        });
+
+        let websocket_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let websocket_url = format!("ws://{}/ws", websocket_listener.local_addr().unwrap());
+        let websocket_messages = broadcast::channel(100).0;
+        tokio::spawn(serve_websocket(
+            websocket_listener,
+            state.clone(),
+            websocket_messages.clone(),
+            shutdown.clone(),
+        ));
+
+        Self {
+            http_url,
+            websocket_url,
+            state,
+            websocket_messages,
+            shutdown,
+        }
+    }
+
+    pub fn credentials(&self) -> ApiClientCredentials {
+        ApiClientCredentials {
+            client_id: CLIENT_ID.to_owned(),
+            client_secret: CLIENT_SECRET.to_owned(),
+            redirect_uri: "http://localhost/auth/callback".to_owned(),
+            helix_url: format!("{}/helix", self.http_url),
+            oauth2_url: format!("{}/oauth2", self.http_url),
+            eventsub_websocket_url: self.websocket_url.clone(),
+        }
+    }
+
+    /// A config pointing to this server. The database is never connected to.
+    pub fn config(&self) -> &'static Config {
+        let credentials = self.credentials();
+        let config = toml::from_str(&format!(
+            r#"
+            [database]
+            url = "postgresql:///unused"
+
+            [twitch_api]
+            client_id = "{}"
+            client_secret = "{}"
+            redirect_uri = "{}"
+            helix_url = "{}"
+            oauth2_url = "{}"
+            eventsub_websocket_url = "{}"
+            "#,
+            credentials.client_id,
+            credentials.client_secret,
+            credentials.redirect_uri,
+            credentials.helix_url,
+            credentials.oauth2_url,
+            credentials.eventsub_websocket_url,
+        ))
+        .unwrap();
+        Box::leak(Box::new(config))
+    }
+
+    /// A bot in the channel of [`USER_ID`] talking to this server, with `db` as its database and
+    /// `stream_session` as the live stream. The messages it sends are queued in the returned
+    /// [`ChatQueue`].
+    pub async fn bot_context(
+        &self,
+        db: DatabaseConnection,
+        stream_session: Option<stream_session::Model>,
+    ) -> (Arc<BotContext>, ChatQueue) {
+        let config = self.config();
+        let (chat, chat_queue) = chat::queue();
+        let bot = BotContext {
+            config,
+            db: Box::leak(Box::new(db)),
+            bot_user_id: BOT_USER_ID.into(),
+            broadcaster_user_id: USER_ID.into(),
+            chat,
+            conduits: Box::leak(Box::new(ConduitManager::start(config).await.unwrap())),
+            stream_session: watch::channel(stream_session).1,
+        };
+        (Arc::new(bot), chat_queue)
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Send a raw message to all connected websocket sessions, e.g. to deliver a message again.
+    pub fn send_websocket_message(&self, message: &Value) {
+        let _ = self.websocket_messages.send(message.to_string());
+    }
+
+    /// A notification of an event as sent through a websocket session.
+    pub fn notification(&self, subscription_type: &str, version: &str, event: Value) -> Value {
+        let message_id = self.state().next_id("message");
+        json!({
+            "metadata": {
+                "message_id": message_id,
+                "message_type": "notification",
+                "message_timestamp": Utc::now().to_rfc3339(),
+                "subscription_type": subscription_type,
+                "subscription_version": version,
+            },
+            "payload": {
+                "subscription": {
+                    "id": "mock-subscription",
+                    "status": "enabled",
+                    "type": subscription_type,
+                    "version": version,
+                    "cost": 0,
+                    "condition": {},
+                    "transport": { "method": "websocket", "session_id": "mock-session" },
+                    "created_at": Utc::now().to_rfc3339(),
+                },
+                "event": event,
+            },
+        })
+    }
+
+    /// Send a notification of an event to all connected websocket sessions, returning the message
+    /// that was sent.
+    pub fn send_notification(&self, subscription_type: &str, version: &str, event: Value) -> Value {
+        let message = self.notification(subscription_type, version, event);
+        self.send_websocket_message(&message);
+        message
+    }
+}
+
+impl Drop for MockTwitch {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/oauth2/token", post(token))
+        .route("/oauth2/revoke", post(revoke))
+        .route("/helix/users", get(get_users))
+        .route(
+            "/helix/eventsub/conduits",
+            get(get_conduits).post(create_conduit).patch(update_conduit),
+        )
+        .route("/helix/eventsub/conduits/shards", patch(update_shards))
+        .route(
+            "/helix/eventsub/subscriptions",
+            get(get_subscriptions)
+                .post(create_subscription)
+                .delete(delete_subscription),
+        )
+        .route("/helix/moderation/bans", post(ban_user).delete(unban_user))
+        .route("/helix/moderation/chat", delete(delete_chat_message))
+        .route("/helix/chat/messages", post(send_chat_message))
+        .route(
+            "/helix/channel_points/custom_rewards/redemptions",
+            patch(update_redemption),
+        )
+        .with_state(state)
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": status.canonical_reason(),
+            "status": status.as_u16(),
+            "message": message,
+        })),
+    )
+        .into_response()
+}
+
+/// Like Helix, requests need the client id and a token the mock handed out. Returns the error
+/// response if they are missing.
+fn unauthorized(headers: &HeaderMap) -> Option<Response> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if header("Client-Id") != Some(CLIENT_ID) {
+        return Some(error(StatusCode::UNAUTHORIZED, "Client ID is missing"));
+    }
+    match header("Authorization").and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(APP_ACCESS_TOKEN | USER_ACCESS_TOKEN) => None,
+        _ => Some(error(StatusCode::UNAUTHORIZED, "Invalid OAuth token")),
+    }
+}
+
+fn user_token_response() -> Response {
+    Json(json!({
+        "access_token": USER_ACCESS_TOKEN,
+        "refresh_token": REFRESH_TOKEN,
+        "expires_in": 14400,
+        "scope": ["moderator:manage:banned_users", "user:write:chat"],
+        "token_type": "bearer",
+    }))
+    .into_response()
+}
+
+// POST /oauth2/token
+async fn token(RawQuery(query): RawQuery, body: Bytes) -> Response {
+    // parameters are accepted in the query and the form body, just like Twitch does
+    let params = url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .chain(url::form_urlencoded::parse(&body))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<HashMap<_, _>>();
+    let param = |key: &str| params.get(key).map(String::as_str);
+    if param("client_id") != Some(CLIENT_ID) || param("client_secret") != Some(CLIENT_SECRET) {
+        return error(StatusCode::FORBIDDEN, "invalid client secret");
+    }
+
+    match param("grant_type") {
+        Some("client_credentials") => Json(json!({
+            "access_token": APP_ACCESS_TOKEN,
+            "expires_in": 5_000_000,
+            "token_type": "bearer",
+        }))
+        .into_response(),
+        Some("authorization_code") if param("code") == Some(AUTHORIZATION_CODE) => {
+            user_token_response()
+        }
+        Some("authorization_code") => error(StatusCode::BAD_REQUEST, "Invalid authorization code"),
+        Some("refresh_token") if param("refresh_token") == Some(REFRESH_TOKEN) => {
+            user_token_response()
+        }
+        Some("refresh_token") => error(StatusCode::BAD_REQUEST, "Invalid refresh token"),
+        _ => error(StatusCode::BAD_REQUEST, "unsupported grant type"),
+    }
+}
+
+// POST /oauth2/revoke
+async fn revoke(State(app): State<AppState>, body: Bytes) -> Response {
+    let token = url::form_urlencoded::parse(&body)
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned());
+    match token {
+        Some(token) => {
+            app.state.lock().unwrap().revoked_tokens.push(token);
+            StatusCode::OK.into_response()
+        }
+        None => error(StatusCode::BAD_REQUEST, "missing token"),
+    }
+}
+
+// GET /helix/users
+async fn get_users(headers: HeaderMap) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    Json(json!({
+        "data": [{
+            "id": USER_ID,
+            "login": USER_LOGIN,
+            "display_name": "MockStreamer",
+            "type": "",
+            "broadcaster_type": "",
+            "description": "",
+            "profile_image_url": "",
+            "offline_image_url": "",
+            "view_count": 0,
+            "created_at": "2020-01-01T00:00:00Z",
+        }],
+    }))
+    .into_response()
+}
+
+fn conduit_json(conduit: &MockConduit) -> Value {
+    json!({ "id": conduit.id, "shard_count": conduit.shard_count })
+}
+
+// GET /helix/eventsub/conduits
+async fn get_conduits(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let state = app.state.lock().unwrap();
+    let conduits = state.conduits.iter().map(conduit_json).collect::<Vec<_>>();
+    Json(json!({ "data": conduits })).into_response()
+}
+
+#[derive(Deserialize)]
+struct ConduitBody {
+    id: Option<String>,
+    shard_count: usize,
+}
+
+// POST /helix/eventsub/conduits
+async fn create_conduit(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<ConduitBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let conduit = MockConduit {
+        id: state.next_id("conduit"),
+        shard_count: body.shard_count,
+        shards: BTreeMap::new(),
+    };
+    let response = Json(json!({ "data": [conduit_json(&conduit)] })).into_response();
+    state.conduits.push(conduit);
+    response
+}
+
+// PATCH /helix/eventsub/conduits
+async fn update_conduit(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<ConduitBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let Some(conduit) = state
+        .conduits
+        .iter_mut()
+        .find(|conduit| Some(&conduit.id) == body.id.as_ref())
+    else {
+        return error(StatusCode::NOT_FOUND, "conduit not found");
+    };
+    conduit.shard_count = body.shard_count;
+    conduit
+        .shards
+        .retain(|id, _| id.parse::<usize>().is_ok_and(|id| id < body.shard_count));
+    Json(json!({ "data": [conduit_json(conduit)] })).into_response()
+}
+
+#[derive(Deserialize)]
+struct UpdateShardsBody {
+    conduit_id: String,
+    shards: Vec<ShardBody>,
+}
+
+#[derive(Deserialize)]
+struct ShardBody {
+    id: String,
+    transport: Value,
+}
+
+// PATCH /helix/eventsub/conduits/shards
+async fn update_shards(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<UpdateShardsBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let sessions = state.websocket_sessions.clone();
+    let Some(conduit) = state
+        .conduits
+        .iter_mut()
+        .find(|conduit| conduit.id == body.conduit_id)
+    else {
+        return error(StatusCode::NOT_FOUND, "conduit not found");
+    };
+
+    let mut errors = vec![];
+    for shard in body.shards {
+        let in_range = shard
+            .id
+            .parse::<usize>()
+            .is_ok_and(|id| id < conduit.shard_count);
+        let session_exists = match shard.transport["session_id"].as_str() {
+            Some(session_id) => sessions.iter().any(|session| session == session_id),
+            None => true,
+        };
+        if !in_range {
+            errors
+                .push(json!({ "id": shard.id, "message": "shard not found", "code": "not_found" }));
+        } else if !session_exists {
+            errors.push(json!({
+                "id": shard.id,
+                "message": "websocket session does not exist",
+                "code": "websocket_session_not_found",
+            }));
+        } else {
+            conduit.shards.insert(shard.id, shard.transport);
+        }
+    }
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({ "data": [], "errors": errors })),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct GetSubscriptionsQuery {
+    user_id: Option<String>,
+    after: Option<usize>,
+}
+
+// GET /helix/eventsub/subscriptions
+async fn get_subscriptions(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<GetSubscriptionsQuery>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let state = app.state.lock().unwrap();
+    let matching = state
+        .subscriptions
+        .iter()
+        .filter(|subscription| match &query.user_id {
+            Some(user_id) => subscription["condition"]
+                .as_object()
+                .is_some_and(|condition| condition.values().any(|value| value == user_id)),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    let start = query.after.unwrap_or(0).min(matching.len());
+    let end = (start + SUBSCRIPTIONS_PAGE_SIZE).min(matching.len());
+    let pagination = if end < matching.len() {
+        json!({ "cursor": end.to_string() })
+    } else {
+        json!({})
+    };
+    Json(json!({
+        "data": &matching[start..end],
+        "total": matching.len(),
+        "pagination": pagination,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct CreateSubscriptionBody {
+    #[serde(rename = "type")]
+    kind: String,
+    version: String,
+    condition: Value,
+    transport: Value,
+}
+
+// POST /helix/eventsub/subscriptions
+async fn create_subscription(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<CreateSubscriptionBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let exists = state.subscriptions.iter().any(|subscription| {
+        subscription["type"] == body.kind.as_str()
+            && subscription["version"] == body.version.as_str()
+            && subscription["condition"] == body.condition
+    });
+    if exists {
+        return error(StatusCode::CONFLICT, "subscription already exists");
+    }
+
+    let subscription = json!({
+        "id": state.next_id("subscription"),
+        "status": "enabled",
+        "type": body.kind,
+        "version": body.version,
+        "condition": body.condition,
+        "transport": body.transport,
+        "created_at": Utc::now().to_rfc3339(),
+        "cost": 0,
+    });
+    state.subscriptions.push(subscription.clone());
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({ "data": [subscription] })),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct DeleteSubscriptionQuery {
+    id: String,
+}
+
+// DELETE /helix/eventsub/subscriptions
+async fn delete_subscription(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<DeleteSubscriptionQuery>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let count = state.subscriptions.len();
+    state
+        .subscriptions
+        .retain(|subscription| subscription["id"] != query.id.as_str());
+    if state.subscriptions.len() == count {
+        return error(StatusCode::NOT_FOUND, "subscription not found");
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[derive(Deserialize)]
+struct ModerationQuery {
+    broadcaster_id: String,
+    moderator_id: String,
+    user_id: Option<String>,
+    message_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BanUserBody {
+    data: BanUserData,
+}
+
+#[derive(Deserialize)]
+struct BanUserData {
+    user_id: String,
+    duration: Option<u32>,
+    reason: String,
+}
+
+// POST /helix/moderation/bans
+async fn ban_user(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ModerationQuery>,
+    Json(body): Json<BanUserBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    if state
+        .bans
+        .iter()
+        .any(|ban| ban.user_id == body.data.user_id && ban.duration.is_none())
+    {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "The user specified in the user_id field is already banned.",
+        );
+    }
+    state.bans.push(MockBan {
+        broadcaster_id: query.broadcaster_id.clone(),
+        moderator_id: query.moderator_id,
+        user_id: body.data.user_id.clone(),
+        duration: body.data.duration,
+        reason: body.data.reason,
+    });
+    Json(json!({
+        "data": [{
+            "broadcaster_id": query.broadcaster_id,
+            "user_id": body.data.user_id,
+            "created_at": Utc::now().to_rfc3339(),
+        }],
+    }))
+    .into_response()
+}
+
+// DELETE /helix/moderation/bans
+async fn unban_user(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ModerationQuery>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let Some(user_id) = query.user_id else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Missing required parameter \"user_id\"",
+        );
+    };
+    let mut state = app.state.lock().unwrap();
+    let count = state.bans.len();
+    state.bans.retain(|ban| ban.user_id != user_id);
+    if state.bans.len() == count {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "The user specified in the user_id field is not banned.",
+        );
+    }
+    state.unbans.push((query.broadcaster_id, user_id));
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// DELETE /helix/moderation/chat
+async fn delete_chat_message(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ModerationQuery>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    match query.message_id {
+        Some(message_id) => {
+            app.state.lock().unwrap().deleted_messages.push(message_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => error(StatusCode::NOT_FOUND, "message not found"),
+    }
+}
+
+#[derive(Deserialize)]
+struct SendChatMessageBody {
+    broadcaster_id: String,
+    sender_id: String,
+    message: String,
+    reply_parent_message_id: Option<String>,
+}
+
+// POST /helix/chat/messages
+async fn send_chat_message(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<SendChatMessageBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    let mut state = app.state.lock().unwrap();
+    let message_id = state.next_id("chat-message");
+    state.chat_messages.push(MockChatMessage {
+        broadcaster_id: body.broadcaster_id,
+        sender_id: body.sender_id,
+        message: body.message,
+        reply_parent_message_id: body.reply_parent_message_id,
+    });
+    Json(json!({
+        "data": [{ "message_id": message_id, "is_sent": true, "drop_reason": null }],
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct RedemptionQuery {
+    id: String,
+    broadcaster_id: String,
+    reward_id: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateRedemptionBody {
+    status: String,
+}
+
+// PATCH /helix/channel_points/custom_rewards/redemptions
+async fn update_redemption(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RedemptionQuery>,
+    Json(body): Json<UpdateRedemptionBody>,
+) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    if query.reward_id == FOREIGN_REWARD_ID {
+        return error(
+            StatusCode::FORBIDDEN,
+            "The ID in header Client-Id must match the client ID used to create the custom reward.",
+        );
+    }
+    app.state
+        .lock()
+        .unwrap()
+        .redemption_updates
+        .push(MockRedemptionUpdate {
+            broadcaster_id: query.broadcaster_id.clone(),
+            reward_id: query.reward_id.clone(),
+            redemption_id: query.id.clone(),
+            status: body.status.clone(),
+        });
+    Json(json!({
+        "data": [{
+            "id": query.id,
+            "broadcaster_id": query.broadcaster_id,
+            "reward": { "id": query.reward_id },
+            "status": body.status,
+        }],
+    }))
+    .into_response()
+}
+
+/// Accept websocket connections, greeting each with a welcome message and forwarding everything
+/// sent through `messages` to it.
+async fn serve_websocket(
+    listener: TcpListener,
+    state: Arc<Mutex<MockState>>,
+    messages: broadcast::Sender<String>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let stream = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+        };
+        let state = state.clone();
+        let mut receiver = messages.subscribe();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                return;
+            };
+            let session_id = state.lock().unwrap().next_id("session");
+            let welcome = json!({
+                "metadata": {
+                    "message_id": format!("welcome-{session_id}"),
+                    "message_type": "session_welcome",
+                    "message_timestamp": Utc::now().to_rfc3339(),
+                },
+                "payload": {
+                    "session": {
+                        "id": session_id,
+                        "status": "connected",
+                        "connected_at": Utc::now().to_rfc3339(),
+                        "keepalive_timeout_seconds": 10,
+                        "reconnect_url": null,
+                    },
+                },
+            });
+            state
+                .lock()
+                .unwrap()
+                .websocket_sessions
+                .push(session_id.clone());
+            if socket
+                .send(tungstenite::Message::Text(welcome.to_string()))
+                .await
+                .is_ok()
+            {
+                loop {
+                    tokio::select! {
+                        _ = shutdown.cancelled() => break,
+                        message = receiver.recv() => match message {
+                            Ok(message) => {
+                                if socket.send(tungstenite::Message::Text(message)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(broadcast::error::RecvError::Lagged(_)) => {}
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        },
+                        incoming = socket.next() => match incoming {
+                            Some(Ok(_)) => {}
+                            _ => break,
+                        },
+                    }
+                }
+            }
+            state
+                .lock()
+                .unwrap()
+                .websocket_sessions
+                .retain(|session| *session != session_id);
+        });
+    }
+}
@@ -0,0 +1,5 @@
+//! Helpers for tests that exercise the bot end-to-end against a local mock of Twitch.
+
+mod mock_twitch;
+
+pub use mock_twitch::*;
@@ -7,12 +7,9 @@ use axum::extract::rejection::QueryRejection;
 use axum::extract::{Query, State};
 use axum::Json;
 use chrono::{Duration, Utc};
-use rand::distributions::Standard;
-use rand::Rng;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{ActiveModelTrait, TransactionTrait};
 use serde::Deserialize;
-use std::fmt::Write;
 
 #[derive(Deserialize)]
 pub struct CreateAuthTokenQueryOptions {
@@ -30,16 +27,7 @@ pub async fn create_token(
 
     let (twitch_user_access_token, user_details) = auth::exchange_code(&app_data, code).await?;
 
-    // 512 bit random hex string
-    // thread_rng() is cryptographically safe
-    let access_token = rand::thread_rng().sample_iter(Standard).take(512 / 8).fold(
-        String::with_capacity(512 / 4),
-        |mut s, x: u8| {
-            // format as hex, padded with a leading 0 if needed (e.g. 0x0 -> "00", 0xFF -> "ff")
-            write!(&mut s, "{:02x}", x).unwrap();
-            s
-        },
-    );
+    let access_token = auth::random_token();
 
     let tx = app_data.db.begin().await?;
     user::upsert_user(user_details.basics.clone(), &tx)
@@ -1,55 +1,129 @@
-use crate::models::{special_twitch_authorization, user};
-use crate::web::auth::exchange_code;
+use crate::api::twitch::user::UserDetails;
+use crate::models::special_authorization_state::{self, Kind};
+use crate::models::special_twitch_authorization::{
+    BOT_SCOPE, BOT_SCOPE_VERSION, BROADCASTER_SCOPE, BROADCASTER_SCOPE_VERSION,
+};
+use crate::models::{bot, special_twitch_authorization, user, user_authorization};
+use crate::web::auth::{exchange_code, random_token};
 use crate::web::error::ApiError;
 use crate::web::WebAppData;
+use anyhow::Context;
 use axum::extract::rejection::QueryRejection;
 use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{Duration, Utc};
 use http::StatusCode;
-use lazy_static::lazy_static;
-use maplit::hashset;
 use sea_orm::ActiveValue::Set;
-use sea_orm::TransactionTrait;
-use serde::Deserialize;
-use std::collections::HashSet;
-
-lazy_static! {
-    // TODO review these, make sure we're only requesting what we need
-    static ref BOT_SCOPE: HashSet<&'static str> = hashset! {
-        "moderator:manage:announcements",
-        "moderator:manage:banned_users",
-        "moderator:manage:chat_messages",
-        "user:manage:whispers",
-        "channel:moderate",
-        "chat:edit",
-        "chat:read",
-        "whispers:read",
-        "whispers:edit"
-    };
+use sea_orm::{ActiveModelTrait, EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use url::Url;
 
-    static ref BROADCASTER_SCOPE: HashSet<&'static str> = hashset! {
-        "channel:read:subscriptions",
-        "channel:manage:broadcast",
-        "channel:read:vips",
-        "moderation:read"
-    };
+/// How long a user has to complete the authorization on Twitch
+const STATE_VALIDITY: Duration = Duration::minutes(10);
+
+#[derive(Deserialize)]
+pub struct AuthorizeUrlQueryOptions {
+    kind: Kind,
 }
 
-// Increase these constants whenever a scope gets added to the sets above. No version
-// upgrade necessary when something is removed from the set, though.
-const BOT_SCOPE_VERSION: i16 = 1;
-const BROADCASTER_SCOPE_VERSION: i16 = 1;
+#[derive(Serialize)]
+pub struct AuthorizeUrlResponse {
+    url: String,
+}
+
+// POST /api/v1/auth/special/authorize?kind=bot
+/// Start the special authorization flow for the channel of the logged in user. The returned URL
+/// has to be opened while logged into Twitch with the broadcaster or bot account respectively.
+/// For bots, the broadcaster has to be authorized first.
+pub async fn create_authorize_url(
+    State(app_data): State<WebAppData>,
+    query_options: Result<Query<AuthorizeUrlQueryOptions>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<AuthorizeUrlResponse>, ApiError> {
+    let query = query_options.map_err(|_| ApiError::bad_query_parameters())?;
+
+    if query.kind == Kind::Bot && !has_broadcaster_authorization(&app_data, &user.id).await? {
+        return Err(ApiError::new_detailed(
+            StatusCode::CONFLICT,
+            "broadcaster_not_authorized",
+            "The channel has to be authorized before a bot can be added to it",
+        ));
+    }
+
+    let scope = match query.kind {
+        Kind::Bot => &*BOT_SCOPE,
+        Kind::Broadcaster => &*BROADCASTER_SCOPE,
+    };
+    let mut scope = scope.iter().copied().collect::<Vec<_>>();
+    scope.sort_unstable();
+
+    let state = random_token();
+    special_authorization_state::delete_expired(Utc::now() - STATE_VALIDITY, app_data.db)
+        .await
+        .context("create_authorize_url delete expired states")?;
+    special_authorization_state::ActiveModel {
+        state: Set(state.clone()),
+        kind: Set(query.kind),
+        broadcaster_id: Set(user.id),
+        created_at: Set(Utc::now()),
+    }
+    .insert(app_data.db)
+    .await
+    .context("create_authorize_url insert state")?;
+
+    let url = Url::parse_with_params(
+        &app_data.config.twitch_api.oauth2_endpoint("authorize"),
+        &[
+            ("client_id", app_data.config.twitch_api.client_id.as_str()),
+            (
+                "redirect_uri",
+                app_data.config.twitch_api.redirect_uri.as_str(),
+            ),
+            ("response_type", "code"),
+            ("scope", &scope.join(" ")),
+            ("state", &state),
+            // the bot account is usually not the account the user is logged into Twitch with
+            ("force_verify", "true"),
+        ],
+    )
+    .context("create_authorize_url build url")?;
+
+    Ok(Json(AuthorizeUrlResponse { url: url.into() }))
+}
 
 #[derive(Deserialize)]
 pub struct CreateSpecialAuthQueryOptions {
     code: String,
+    state: String,
 }
 
+#[derive(Serialize)]
+pub struct SpecialAuthorizationResponse {
+    kind: Kind,
+    user_details: UserDetails,
+    /// The bot that was added to the channel, if a bot account was authorized
+    bot: Option<bot::Model>,
+}
+
+// POST /api/v1/auth/special?code=abcdef123456&state=abcdef123456
 pub async fn create_special_twitch_auth(
     State(app_data): State<WebAppData>,
     query_options: Result<Query<CreateSpecialAuthQueryOptions>, QueryRejection>,
-) -> Result<(), ApiError> {
+) -> Result<Json<SpecialAuthorizationResponse>, ApiError> {
     let query = query_options.map_err(|_| ApiError::bad_query_parameters())?;
 
+    let state =
+        special_authorization_state::take(&query.state, Utc::now() - STATE_VALIDITY, app_data.db)
+            .await
+            .context("create_special_twitch_auth take state")?
+            .ok_or_else(|| {
+                ApiError::new_detailed(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_state",
+                    "The authorization was not started here or has expired, please try again",
+                )
+            })?;
+
     let (twitch_user_access_token, user_details) = exchange_code(&app_data, &query.code).await?;
     let is_bot = BOT_SCOPE
         .iter()
@@ -57,15 +131,46 @@ pub async fn create_special_twitch_auth(
     let is_broadcaster = BROADCASTER_SCOPE
         .iter()
         .all(|s| twitch_user_access_token.scope.contains(*s));
-    if !(is_bot || is_broadcaster) {
+    let sufficient_scope = match state.kind {
+        Kind::Bot => is_bot,
+        Kind::Broadcaster => is_broadcaster,
+    };
+    if !sufficient_scope {
         return Err(ApiError::new_detailed(StatusCode::BAD_REQUEST, "auth_insufficient_scope", "Authorization does not provide sufficient scope to be either a valid Bot or Broadcaster authorization"));
     }
 
+    let user_id = user_details.basics.id.clone();
+    match state.kind {
+        Kind::Broadcaster if user_id != state.broadcaster_id => {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "wrong_account",
+                "The channel has to be authorized with the broadcaster's Twitch account",
+            ));
+        }
+        Kind::Bot if user_id == state.broadcaster_id => {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "wrong_account",
+                "The bot has to be authorized with a separate Twitch account",
+            ));
+        }
+        // the broadcaster authorization could have been invalidated in the meantime
+        Kind::Bot if !has_broadcaster_authorization(&app_data, &state.broadcaster_id).await? => {
+            return Err(ApiError::new_detailed(
+                StatusCode::CONFLICT,
+                "broadcaster_not_authorized",
+                "The channel has to be authorized before a bot can be added to it",
+            ));
+        }
+        _ => {}
+    }
+
     let tx = app_data.db.begin().await?;
     user::upsert_user(user_details.basics.clone(), &tx).await?;
 
     let special_twitch_authorization = special_twitch_authorization::ActiveModel {
-        user_id: Set(user_details.basics.id),
+        user_id: Set(user_id.clone()),
         bot_scope_version: Set(if is_bot {
             Some(BOT_SCOPE_VERSION)
         } else {
@@ -79,9 +184,56 @@ pub async fn create_special_twitch_auth(
         twitch_access_token: Set(twitch_user_access_token.access_token),
         twitch_refresh_token: Set(twitch_user_access_token.refresh_token),
         valid_until: Set(twitch_user_access_token.valid_until),
+        invalidated_at: Set(None),
     };
     special_twitch_authorization::upsert(special_twitch_authorization, &tx).await?;
+
+    // both sides have authorized once the bot account did, since the broadcaster has to go first
+    let bot = match state.kind {
+        Kind::Bot => {
+            let command_prefix = bot::Entity::find_by_id(state.broadcaster_id.as_str())
+                .one(&tx)
+                .await
+                .context("create_special_twitch_auth find bot")?
+                .map_or_else(|| "!".to_owned(), |bot| bot.command_prefix);
+            let bot = bot::upsert(
+                bot::ActiveModel {
+                    broadcaster_id: Set(state.broadcaster_id),
+                    bot_id: Set(user_id),
+                    command_prefix: Set(command_prefix),
+                },
+                &tx,
+            )
+            .await
+            .context("create_special_twitch_auth upsert bot")?;
+            Some(bot)
+        }
+        Kind::Broadcaster => None,
+    };
     tx.commit().await?;
 
-    Ok(())
+    if bot.is_some() {
+        app_data.bot_changes.notify_one();
+    }
+    Ok(Json(SpecialAuthorizationResponse {
+        kind: state.kind,
+        user_details,
+        bot,
+    }))
+}
+
+async fn has_broadcaster_authorization(
+    app_data: &WebAppData,
+    broadcaster_id: &str,
+) -> Result<bool, ApiError> {
+    Ok(
+        special_twitch_authorization::Entity::find_by_id(broadcaster_id)
+            .one(app_data.db)
+            .await
+            .context("has_broadcaster_authorization find authorization")?
+            .is_some_and(|authorization| {
+                authorization.broadcaster_scope_version.is_some()
+                    && authorization.invalidated_at.is_none()
+            }),
+    )
 }
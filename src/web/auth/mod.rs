@@ -11,7 +11,10 @@ use crate::web::error::ApiError;
 use crate::web::WebAppData;
 use chrono::{DateTime, Utc};
 use http::StatusCode;
+use rand::distributions::Standard;
+use rand::Rng;
 use serde::Serialize;
+use std::fmt::Write;
 
 #[derive(Serialize)]
 pub struct UserAuthorizationResponse {
@@ -46,3 +49,16 @@ pub async fn exchange_code(
     .await?;
     Ok((twitch_user_access_token, user_details))
 }
+
+/// 512 bit random hex string, for use as an access token or similar secret.
+pub fn random_token() -> String {
+    // thread_rng() is cryptographically safe
+    rand::thread_rng().sample_iter(Standard).take(512 / 8).fold(
+        String::with_capacity(512 / 4),
+        |mut s, x: u8| {
+            // format as hex, padded with a leading 0 if needed (e.g. 0x0 -> "00", 0xFF -> "ff")
+            write!(&mut s, "{:02x}", x).unwrap();
+            s
+        },
+    )
+}
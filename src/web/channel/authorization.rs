@@ -0,0 +1,103 @@
+use crate::models::special_authorization_state::Kind;
+use crate::models::special_twitch_authorization::{self, AuthorizationStatus};
+use crate::models::{bot, user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{Path, State};
+use axum::Json;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct AuthorizationInfo {
+    user_id: String,
+    status: AuthorizationStatus,
+    scope_version: Option<i16>,
+    current_scope_version: i16,
+    /// Scopes the authorization lacks compared to a current one, i.e. what the user is asked
+    /// for when authorizing again
+    missing_scopes: Vec<&'static str>,
+}
+
+impl AuthorizationInfo {
+    fn new(
+        kind: Kind,
+        user_id: String,
+        authorization: Option<&special_twitch_authorization::Model>,
+    ) -> Self {
+        let current_scope_version = special_twitch_authorization::current_scope_version(kind);
+        let mut missing_scopes = special_twitch_authorization::scope(kind, current_scope_version)
+            .into_iter()
+            .filter(|scope| {
+                !authorization.is_some_and(|authorization| authorization.has_scope(kind, scope))
+            })
+            .collect::<Vec<_>>();
+        missing_scopes.sort_unstable();
+        AuthorizationInfo {
+            user_id,
+            status: authorization.map_or(AuthorizationStatus::Missing, |authorization| {
+                authorization.status(kind)
+            }),
+            scope_version: authorization
+                .and_then(|authorization| authorization.scope_version(kind)),
+            current_scope_version,
+            missing_scopes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChannelAuthorizationResponse {
+    broadcaster: AuthorizationInfo,
+    /// Absent if there is no bot in the channel
+    bot: Option<AuthorizationInfo>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/authorization
+/// Whether the broadcaster and the bot of the channel have to authorize (again), for the
+/// dashboard to prompt them.
+pub async fn get_authorization_status(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<ChannelAuthorizationResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let broadcaster_authorization =
+        special_twitch_authorization::Entity::find_by_id(broadcaster_id.as_str())
+            .one(app_data.db)
+            .await
+            .context("get_authorization_status find broadcaster authorization")?;
+    let broadcaster = AuthorizationInfo::new(
+        Kind::Broadcaster,
+        broadcaster_id.clone(),
+        broadcaster_authorization.as_ref(),
+    );
+
+    let bot_id = bot::Entity::find_by_id(broadcaster_id.as_str())
+        .one(app_data.db)
+        .await
+        .context("get_authorization_status find bot")?
+        .map(|bot| bot.bot_id);
+    let bot = match bot_id {
+        Some(bot_id) => {
+            let bot_authorization =
+                special_twitch_authorization::Entity::find_by_id(bot_id.as_str())
+                    .one(app_data.db)
+                    .await
+                    .context("get_authorization_status find bot authorization")?;
+            Some(AuthorizationInfo::new(
+                Kind::Bot,
+                bot_id,
+                bot_authorization.as_ref(),
+            ))
+        }
+        None => None,
+    };
+
+    Ok(Json(ChannelAuthorizationResponse { broadcaster, bot }))
+}
@@ -0,0 +1,289 @@
+use crate::bot::banphrases::CompiledBanphrase;
+use crate::bot::commands::Permission;
+use crate::models::banphrase::{self, Action, Operator};
+use crate::models::{user, user_authorization};
+use crate::web::channel::{find_bot_id, require_channel_access};
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+const MAX_NAME_LENGTH: usize = 100;
+/// Longest message accepted by the public test endpoint. Chat messages can't be longer than 500
+/// characters, this leaves some room for bots testing messages before truncating them.
+const MAX_TEST_MESSAGE_LENGTH: usize = 2000;
+/// Twitch allows timeouts of up to two weeks
+const MAX_TIMEOUT_SECONDS: i32 = 1209600;
+
+#[derive(Deserialize)]
+pub struct BanphraseRequest {
+    name: String,
+    phrase: String,
+    operator: Operator,
+    action: Action,
+    #[serde(default = "default_timeout_seconds")]
+    timeout_seconds: i32,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    remove_accents: bool,
+    #[serde(default = "default_exempt_permission")]
+    exempt_permission: Permission,
+    #[serde(default)]
+    sub_immunity: bool,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_timeout_seconds() -> i32 {
+    300
+}
+
+fn default_exempt_permission() -> Permission {
+    Permission::Moderator
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl BanphraseRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.name.trim().is_empty() || self.name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_name",
+                format!("Name must be between 1 and {MAX_NAME_LENGTH} characters long"),
+            ));
+        }
+        if self.phrase.trim().is_empty() {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_phrase",
+                "Phrase must not be empty",
+            ));
+        }
+        if self.operator == Operator::Regex {
+            CompiledBanphrase::validate_regex(&self.phrase, self.case_sensitive).map_err(|e| {
+                ApiError::new_detailed(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_regex",
+                    format!("Phrase is not a valid regular expression: {e}"),
+                )
+            })?;
+        }
+        if !(1..=MAX_TIMEOUT_SECONDS).contains(&self.timeout_seconds) {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_timeout",
+                format!("Timeout must be between 1 and {MAX_TIMEOUT_SECONDS} seconds"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn into_active_model(self, id: Option<i32>, broadcaster_id: String) -> banphrase::ActiveModel {
+        banphrase::ActiveModel {
+            id: id.map_or(NotSet, Set),
+            broadcaster_id: Set(broadcaster_id),
+            name: Set(self.name),
+            phrase: Set(self.phrase),
+            operator: Set(self.operator),
+            action: Set(self.action),
+            timeout_seconds: Set(self.timeout_seconds),
+            case_sensitive: Set(self.case_sensitive),
+            remove_accents: Set(self.remove_accents),
+            exempt_permission: Set(self.exempt_permission),
+            sub_immunity: Set(self.sub_immunity),
+            enabled: Set(self.enabled),
+        }
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/banphrases
+pub async fn list_banphrases(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<Vec<banphrase::Model>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let banphrases = banphrase::Entity::find()
+        .filter(banphrase::Column::BroadcasterId.eq(broadcaster_id))
+        .order_by_asc(banphrase::Column::Id)
+        .all(app_data.db)
+        .await
+        .context("list_banphrases find banphrases")?;
+    Ok(Json(banphrases))
+}
+
+// GET /api/v1/channel/:broadcaster_id/banphrases/:banphrase_id
+pub async fn get_banphrase(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<banphrase::Model>, ApiError> {
+    let Path((broadcaster_id, banphrase_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let banphrase = banphrase::Entity::find_by_id(banphrase_id)
+        .filter(banphrase::Column::BroadcasterId.eq(broadcaster_id))
+        .one(app_data.db)
+        .await
+        .context("get_banphrase find banphrase")?
+        .ok_or_else(ApiError::not_found)?;
+    Ok(Json(banphrase))
+}
+
+// POST /api/v1/channel/:broadcaster_id/banphrases
+pub async fn create_banphrase(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<BanphraseRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<banphrase::Model>), ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let banphrase = request
+        .into_active_model(None, broadcaster_id)
+        .insert(app_data.db)
+        .await
+        .context("create_banphrase insert banphrase")?;
+    Ok((StatusCode::CREATED, Json(banphrase)))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/banphrases/:banphrase_id
+pub async fn update_banphrase(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<BanphraseRequest>, JsonRejection>,
+) -> Result<Json<banphrase::Model>, ApiError> {
+    let Path((broadcaster_id, banphrase_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    banphrase::Entity::find_by_id(banphrase_id)
+        .filter(banphrase::Column::BroadcasterId.eq(broadcaster_id.as_str()))
+        .one(app_data.db)
+        .await
+        .context("update_banphrase find banphrase")?
+        .ok_or_else(ApiError::not_found)?;
+
+    let banphrase = request
+        .into_active_model(Some(banphrase_id), broadcaster_id)
+        .update(app_data.db)
+        .await
+        .context("update_banphrase update banphrase")?;
+    Ok(Json(banphrase))
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/banphrases/:banphrase_id
+pub async fn delete_banphrase(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path((broadcaster_id, banphrase_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let result = banphrase::Entity::delete_many()
+        .filter(banphrase::Column::Id.eq(banphrase_id))
+        .filter(banphrase::Column::BroadcasterId.eq(broadcaster_id))
+        .exec(app_data.db)
+        .await
+        .context("delete_banphrase delete banphrase")?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct TestMessageRequest {
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct TestMessageResponse {
+    banned: bool,
+    input_message: String,
+    banphrase_data: Option<BanphraseData>,
+}
+
+/// The parts of a banphrase that are public through the test endpoint
+#[derive(Serialize)]
+pub struct BanphraseData {
+    id: i32,
+    name: String,
+    phrase: String,
+    operator: Operator,
+    action: Action,
+    timeout_seconds: i32,
+    case_sensitive: bool,
+    remove_accents: bool,
+    sub_immunity: bool,
+}
+
+impl From<&banphrase::Model> for BanphraseData {
+    fn from(banphrase: &banphrase::Model) -> BanphraseData {
+        BanphraseData {
+            id: banphrase.id,
+            name: banphrase.name.clone(),
+            phrase: banphrase.phrase.clone(),
+            operator: banphrase.operator,
+            action: banphrase.action,
+            timeout_seconds: banphrase.timeout_seconds,
+            case_sensitive: banphrase.case_sensitive,
+            remove_accents: banphrase.remove_accents,
+            sub_immunity: banphrase.sub_immunity,
+        }
+    }
+}
+
+// POST /api/v1/channel/:broadcaster_id/banphrases/test
+// Public, so other bots can check whether a message would be banned before sending it.
+pub async fn test_message(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    body: Result<Json<TestMessageRequest>, JsonRejection>,
+) -> Result<Json<TestMessageResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    if request.message.chars().count() > MAX_TEST_MESSAGE_LENGTH {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "message_too_long",
+            format!("Message must not be longer than {MAX_TEST_MESSAGE_LENGTH} characters"),
+        ));
+    }
+
+    find_bot_id(&app_data, &broadcaster_id).await?;
+
+    let banphrases = app_data
+        .banphrase_cache
+        .get(&broadcaster_id, app_data.db)
+        .await
+        .context("test_message load banphrases")?;
+    let banphrase_data = banphrases
+        .find_match(&request.message)
+        .map(BanphraseData::from);
+
+    Ok(Json(TestMessageResponse {
+        banned: banphrase_data.is_some(),
+        input_message: request.message,
+        banphrase_data,
+    }))
+}
@@ -0,0 +1,145 @@
+use crate::models::{bot, subscription_revocation, user, user_authorization};
+use crate::web::channel::{find_bot_id, require_channel_access};
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+const MAX_COMMAND_PREFIX_LENGTH: usize = 10;
+
+/// Settings of the bot of a channel. The bot account itself can only be chosen through the
+/// onboarding flow, which needs its consent.
+#[derive(Deserialize)]
+pub struct BotRequest {
+    command_prefix: String,
+}
+
+impl BotRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.command_prefix.is_empty()
+            || self.command_prefix.chars().count() > MAX_COMMAND_PREFIX_LENGTH
+            || self.command_prefix.contains(char::is_whitespace)
+        {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_command_prefix",
+                format!("Command prefix must be between 1 and {MAX_COMMAND_PREFIX_LENGTH} characters long and must not contain whitespace"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/bot
+pub async fn get_bot(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<bot::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let bot = bot::Entity::find_by_id(broadcaster_id)
+        .one(app_data.db)
+        .await
+        .context("get_bot find bot")?
+        .ok_or_else(ApiError::not_found)?;
+    Ok(Json(bot))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotHealthStatus {
+    Healthy,
+    /// Some events are not received, because Twitch revoked their subscriptions
+    Degraded,
+}
+
+#[derive(Serialize)]
+pub struct BotHealth {
+    status: BotHealthStatus,
+    /// Revoked subscriptions the bot has not been able to subscribe to again yet
+    revocations: Vec<subscription_revocation::Model>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/bot/health
+pub async fn get_bot_health(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<BotHealth>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    find_bot_id(&app_data, &broadcaster_id).await?;
+
+    let revocations = subscription_revocation::find_unresolved(&broadcaster_id, app_data.db)
+        .await
+        .context("get_bot_health find revocations")?;
+    let status = if revocations.is_empty() {
+        BotHealthStatus::Healthy
+    } else {
+        BotHealthStatus::Degraded
+    };
+    Ok(Json(BotHealth {
+        status,
+        revocations,
+    }))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/bot
+pub async fn put_bot(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<BotRequest>, JsonRejection>,
+) -> Result<Json<bot::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let bot = bot::Entity::update_many()
+        .col_expr(bot::Column::CommandPrefix, Expr::value(request.command_prefix))
+        .filter(bot::Column::BroadcasterId.eq(broadcaster_id))
+        .exec_with_returning(app_data.db)
+        .await
+        .context("put_bot update bot")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            ApiError::new_detailed(
+                StatusCode::NOT_FOUND,
+                "no_bot",
+                "The channel has no bot yet, it is added once the broadcaster and the bot account have authorized",
+            )
+        })?;
+    app_data.bot_changes.notify_one();
+    Ok(Json(bot))
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/bot
+pub async fn delete_bot(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let result = bot::Entity::delete_by_id(broadcaster_id)
+        .exec(app_data.db)
+        .await
+        .context("delete_bot delete bot")?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+    app_data.bot_changes.notify_one();
+
+    Ok(StatusCode::NO_CONTENT)
+}
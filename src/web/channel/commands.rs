@@ -0,0 +1,217 @@
+use crate::bot::commands::{CommandRegistry, Permission};
+use crate::models::{command, user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, SqlErr};
+use serde::Deserialize;
+
+/// Twitch rejects chat messages longer than this
+const MAX_RESPONSE_LENGTH: usize = 500;
+const MAX_TRIGGER_LENGTH: usize = 50;
+
+#[derive(Deserialize)]
+pub struct CommandRequest {
+    trigger: String,
+    response: String,
+    #[serde(default = "default_global_cooldown_seconds")]
+    global_cooldown_seconds: i32,
+    #[serde(default = "default_user_cooldown_seconds")]
+    user_cooldown_seconds: i32,
+    #[serde(default = "default_min_permission")]
+    min_permission: Permission,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_global_cooldown_seconds() -> i32 {
+    5
+}
+
+fn default_user_cooldown_seconds() -> i32 {
+    15
+}
+
+fn default_min_permission() -> Permission {
+    Permission::Everyone
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl CommandRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.trigger.is_empty()
+            || self.trigger.chars().count() > MAX_TRIGGER_LENGTH
+            || self.trigger.contains(char::is_whitespace)
+        {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_trigger",
+                format!("Trigger must be between 1 and {MAX_TRIGGER_LENGTH} characters long and must not contain whitespace"),
+            ));
+        }
+        if CommandRegistry::with_builtins()?
+            .resolve(&self.trigger)
+            .is_some()
+        {
+            return Err(ApiError::new_detailed(
+                StatusCode::CONFLICT,
+                "trigger_reserved",
+                "Trigger is already used by a built-in command",
+            ));
+        }
+        if self.response.trim().is_empty() || self.response.chars().count() > MAX_RESPONSE_LENGTH {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_response",
+                format!("Response must be between 1 and {MAX_RESPONSE_LENGTH} characters long"),
+            ));
+        }
+        if self.global_cooldown_seconds < 0 || self.user_cooldown_seconds < 0 {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_cooldown",
+                "Cooldowns must not be negative",
+            ));
+        }
+        Ok(())
+    }
+
+    fn into_active_model(self, id: Option<i32>, broadcaster_id: String) -> command::ActiveModel {
+        command::ActiveModel {
+            id: id.map_or(NotSet, Set),
+            broadcaster_id: Set(broadcaster_id),
+            trigger: Set(self.trigger),
+            response: Set(self.response),
+            global_cooldown_seconds: Set(self.global_cooldown_seconds),
+            user_cooldown_seconds: Set(self.user_cooldown_seconds),
+            min_permission: Set(self.min_permission),
+            enabled: Set(self.enabled),
+            use_count: NotSet,
+        }
+    }
+}
+
+fn map_write_error(err: DbErr) -> ApiError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ApiError::new_detailed(
+            StatusCode::CONFLICT,
+            "trigger_in_use",
+            "Another command in this channel already uses this trigger",
+        ),
+        _ => ApiError::from(err),
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/commands
+pub async fn list_commands(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<Vec<command::Model>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let commands = command::Entity::find()
+        .filter(command::Column::BroadcasterId.eq(broadcaster_id))
+        .order_by_asc(command::Column::Trigger)
+        .all(app_data.db)
+        .await
+        .context("list_commands find commands")?;
+    Ok(Json(commands))
+}
+
+// GET /api/v1/channel/:broadcaster_id/commands/:command_id
+pub async fn get_command(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<command::Model>, ApiError> {
+    let Path((broadcaster_id, command_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let command = command::Entity::find_by_id(command_id)
+        .filter(command::Column::BroadcasterId.eq(broadcaster_id))
+        .one(app_data.db)
+        .await
+        .context("get_command find command")?
+        .ok_or_else(ApiError::not_found)?;
+    Ok(Json(command))
+}
+
+// POST /api/v1/channel/:broadcaster_id/commands
+pub async fn create_command(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<CommandRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<command::Model>), ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let command = request
+        .into_active_model(None, broadcaster_id)
+        .insert(app_data.db)
+        .await
+        .map_err(map_write_error)?;
+    Ok((StatusCode::CREATED, Json(command)))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/commands/:command_id
+pub async fn update_command(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<CommandRequest>, JsonRejection>,
+) -> Result<Json<command::Model>, ApiError> {
+    let Path((broadcaster_id, command_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    command::Entity::find_by_id(command_id)
+        .filter(command::Column::BroadcasterId.eq(broadcaster_id.as_str()))
+        .one(app_data.db)
+        .await
+        .context("update_command find command")?
+        .ok_or_else(ApiError::not_found)?;
+
+    let command = request
+        .into_active_model(Some(command_id), broadcaster_id)
+        .update(app_data.db)
+        .await
+        .map_err(map_write_error)?;
+    Ok(Json(command))
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/commands/:command_id
+pub async fn delete_command(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path((broadcaster_id, command_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let result = command::Entity::delete_many()
+        .filter(command::Column::Id.eq(command_id))
+        .filter(command::Column::BroadcasterId.eq(broadcaster_id))
+        .exec(app_data.db)
+        .await
+        .context("delete_command delete command")?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,242 @@
+use crate::models::minigame_round::{self, Game, RoundFilter};
+use crate::models::minigame_settings::{self, HeistPayouts};
+use crate::models::{minigame_participant, user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_HISTORY_PAGE_SIZE: u64 = 50;
+const MAX_HISTORY_PAGE_SIZE: u64 = 200;
+const MAX_BET: i64 = 1_000_000_000;
+const MAX_HEIST_TIERS: usize = 20;
+
+#[derive(Deserialize)]
+pub struct MinigameSettingsRequest {
+    roulette_enabled: bool,
+    roulette_min_bet: i64,
+    roulette_max_bet: i64,
+    roulette_cooldown_seconds: i32,
+    roulette_win_percent: i32,
+    duel_enabled: bool,
+    duel_min_bet: i64,
+    duel_max_bet: i64,
+    duel_cooldown_seconds: i32,
+    duel_accept_seconds: i32,
+    slots_enabled: bool,
+    slots_min_bet: i64,
+    slots_max_bet: i64,
+    slots_cooldown_seconds: i32,
+    heist_enabled: bool,
+    heist_min_bet: i64,
+    heist_max_bet: i64,
+    heist_cooldown_seconds: i32,
+    heist_join_seconds: i32,
+    heist_payouts: HeistPayouts,
+}
+
+fn invalid_settings(message: String) -> ApiError {
+    ApiError::new_detailed(StatusCode::BAD_REQUEST, "invalid_settings", message)
+}
+
+impl MinigameSettingsRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let bets = [
+            ("roulette", self.roulette_min_bet, self.roulette_max_bet),
+            ("duel", self.duel_min_bet, self.duel_max_bet),
+            ("slots", self.slots_min_bet, self.slots_max_bet),
+            ("heist", self.heist_min_bet, self.heist_max_bet),
+        ];
+        for (game, min, max) in bets {
+            if min < 1 || max < min || max > MAX_BET {
+                return Err(invalid_settings(format!(
+                    "`{game}_min_bet` must be at least 1 and `{game}_max_bet` between it and {MAX_BET}"
+                )));
+            }
+        }
+        // same bounds as the database constraints
+        let ranges = [
+            (
+                "roulette_cooldown_seconds",
+                self.roulette_cooldown_seconds,
+                0,
+                86_400,
+            ),
+            ("roulette_win_percent", self.roulette_win_percent, 0, 100),
+            (
+                "duel_cooldown_seconds",
+                self.duel_cooldown_seconds,
+                0,
+                86_400,
+            ),
+            ("duel_accept_seconds", self.duel_accept_seconds, 10, 600),
+            (
+                "slots_cooldown_seconds",
+                self.slots_cooldown_seconds,
+                0,
+                86_400,
+            ),
+            (
+                "heist_cooldown_seconds",
+                self.heist_cooldown_seconds,
+                0,
+                86_400,
+            ),
+            ("heist_join_seconds", self.heist_join_seconds, 10, 600),
+        ];
+        for (name, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(invalid_settings(format!(
+                    "`{name}` must be between {min} and {max}"
+                )));
+            }
+        }
+        let tiers = &self.heist_payouts.0;
+        if tiers.len() > MAX_HEIST_TIERS {
+            return Err(invalid_settings(format!(
+                "There can be at most {MAX_HEIST_TIERS} heist payout tiers"
+            )));
+        }
+        for tier in tiers {
+            if tier.min_participants < 1 || tier.win_percent > 100 || tier.payout_percent > 10_000 {
+                return Err(invalid_settings(
+                    "Heist payout tiers need at least 1 participant, a win chance of at most 100 percent and a payout of at most 10000 percent".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn into_active_model(self, broadcaster_id: String) -> minigame_settings::ActiveModel {
+        minigame_settings::ActiveModel {
+            broadcaster_id: Set(broadcaster_id),
+            roulette_enabled: Set(self.roulette_enabled),
+            roulette_min_bet: Set(self.roulette_min_bet),
+            roulette_max_bet: Set(self.roulette_max_bet),
+            roulette_cooldown_seconds: Set(self.roulette_cooldown_seconds),
+            roulette_win_percent: Set(self.roulette_win_percent),
+            duel_enabled: Set(self.duel_enabled),
+            duel_min_bet: Set(self.duel_min_bet),
+            duel_max_bet: Set(self.duel_max_bet),
+            duel_cooldown_seconds: Set(self.duel_cooldown_seconds),
+            duel_accept_seconds: Set(self.duel_accept_seconds),
+            slots_enabled: Set(self.slots_enabled),
+            slots_min_bet: Set(self.slots_min_bet),
+            slots_max_bet: Set(self.slots_max_bet),
+            slots_cooldown_seconds: Set(self.slots_cooldown_seconds),
+            heist_enabled: Set(self.heist_enabled),
+            heist_min_bet: Set(self.heist_min_bet),
+            heist_max_bet: Set(self.heist_max_bet),
+            heist_cooldown_seconds: Set(self.heist_cooldown_seconds),
+            heist_join_seconds: Set(self.heist_join_seconds),
+            heist_payouts: Set(self.heist_payouts),
+        }
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/minigames/settings
+pub async fn get_settings(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<minigame_settings::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let settings = minigame_settings::find_or_default(&broadcaster_id, app_data.db)
+        .await
+        .context("get_settings find settings")?;
+    Ok(Json(settings))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/minigames/settings
+pub async fn put_settings(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<MinigameSettingsRequest>, JsonRejection>,
+) -> Result<Json<minigame_settings::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let settings =
+        minigame_settings::upsert(request.into_active_model(broadcaster_id), app_data.db)
+            .await
+            .context("put_settings upsert settings")?;
+    Ok(Json(settings))
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    game: Option<Game>,
+    /// Only rounds this user took part in
+    user_id: Option<String>,
+    /// Only return rounds older than this round, i.e. the `next_before` of the previous page
+    before: Option<i64>,
+    limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryRound {
+    #[serde(flatten)]
+    round: minigame_round::Model,
+    participants: Vec<minigame_participant::Model>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    rounds: Vec<HistoryRound>,
+    /// Pass this as `before` to get the next page, absent if this is the last page
+    next_before: Option<i64>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/minigames/history
+pub async fn get_history(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<HistoryResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+    if !(1..=MAX_HISTORY_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_limit",
+            format!("Limit must be between 1 and {MAX_HISTORY_PAGE_SIZE}"),
+        ));
+    }
+
+    let filter = RoundFilter {
+        game: query.game,
+        user_id: query.user_id,
+    };
+    let rounds =
+        minigame_round::find_page(&broadcaster_id, filter, query.before, limit, app_data.db)
+            .await
+            .context("get_history find rounds")?;
+    let next_before = match rounds.last() {
+        Some((last, _)) if rounds.len() as u64 == limit => Some(last.id),
+        _ => None,
+    };
+    Ok(Json(HistoryResponse {
+        rounds: rounds
+            .into_iter()
+            .map(|(round, participants)| HistoryRound {
+                round,
+                participants,
+            })
+            .collect(),
+        next_before,
+    }))
+}
@@ -0,0 +1,45 @@
+pub mod authorization;
+pub mod banphrases;
+pub mod bot;
+pub mod commands;
+pub mod minigames;
+pub mod moderation;
+pub mod points;
+pub mod rewards;
+pub mod streams;
+pub mod timers;
+
+use crate::models::{self, user};
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use http::StatusCode;
+use sea_orm::EntityTrait;
+
+/// Ensures the logged in user is allowed to manage the channel of `broadcaster_id`.
+pub fn require_channel_access(user: &user::Model, broadcaster_id: &str) -> Result<(), ApiError> {
+    if user.id != broadcaster_id {
+        return Err(ApiError::new_detailed(
+            StatusCode::FORBIDDEN,
+            "channel_access_denied",
+            "You are not allowed to manage this channel",
+        ));
+    }
+    Ok(())
+}
+
+/// User ID of the bot in the channel of `broadcaster_id`, failing with `unknown_channel` if there is none.
+pub async fn find_bot_id(app_data: &WebAppData, broadcaster_id: &str) -> Result<String, ApiError> {
+    models::bot::Entity::find_by_id(broadcaster_id)
+        .one(app_data.db)
+        .await
+        .context("find_bot_id find bot")?
+        .map(|bot| bot.bot_id)
+        .ok_or_else(|| {
+            ApiError::new_detailed(
+                StatusCode::NOT_FOUND,
+                "unknown_channel",
+                "There is no bot in this channel",
+            )
+        })
+}
@@ -0,0 +1,212 @@
+use crate::bot::moderation::{ModerationError, ModerationExecutor};
+use crate::models::moderation_log::{self, LogFilter, Source};
+use crate::models::{user, user_authorization};
+use crate::web::channel::{find_bot_id, require_channel_access};
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Twitch allows timeouts of up to two weeks
+const MAX_TIMEOUT_SECONDS: u32 = 1209600;
+/// Twitch rejects ban reasons longer than this
+const MAX_REASON_LENGTH: usize = 500;
+const DEFAULT_LOG_PAGE_SIZE: u64 = 50;
+const MAX_LOG_PAGE_SIZE: u64 = 200;
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationRequest {
+    Timeout {
+        user_id: String,
+        duration_seconds: u32,
+        #[serde(default)]
+        reason: String,
+    },
+    Ban {
+        user_id: String,
+        #[serde(default)]
+        reason: String,
+    },
+    Unban {
+        user_id: String,
+    },
+    Delete {
+        message_id: String,
+        /// Author of the message, only used for the moderation log
+        #[serde(default)]
+        user_id: Option<String>,
+    },
+}
+
+impl ModerationRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let reason = match self {
+            ModerationRequest::Timeout {
+                duration_seconds,
+                reason,
+                ..
+            } => {
+                if !(1..=MAX_TIMEOUT_SECONDS).contains(duration_seconds) {
+                    return Err(ApiError::new_detailed(
+                        StatusCode::BAD_REQUEST,
+                        "invalid_duration",
+                        format!(
+                            "Timeout duration must be between 1 and {MAX_TIMEOUT_SECONDS} seconds"
+                        ),
+                    ));
+                }
+                reason
+            }
+            ModerationRequest::Ban { reason, .. } => reason,
+            ModerationRequest::Unban { .. } | ModerationRequest::Delete { .. } => return Ok(()),
+        };
+        if reason.chars().count() > MAX_REASON_LENGTH {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_reason",
+                format!("Reason must not be longer than {MAX_REASON_LENGTH} characters"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn map_moderation_error(e: ModerationError) -> ApiError {
+    match e {
+        ModerationError::NotAuthorized => ApiError::new_detailed(
+            StatusCode::CONFLICT,
+            "bot_not_authorized",
+            "The bot account of this channel needs to be authorized again",
+        ),
+        ModerationError::Rejected(message) => {
+            ApiError::new_detailed(StatusCode::BAD_REQUEST, "action_rejected", message)
+        }
+        ModerationError::Forbidden(_) => ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "bot_not_moderator",
+            "The bot is not a moderator in this channel",
+        ),
+        ModerationError::NotFound(message) => {
+            ApiError::new_detailed(StatusCode::NOT_FOUND, "not_found", message)
+        }
+        ModerationError::RateLimited => ApiError::new_detailed(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            "Too many moderation actions, try again later",
+        ),
+        ModerationError::Other(e) => e.into(),
+    }
+}
+
+// POST /api/v1/channel/:broadcaster_id/moderation
+pub async fn moderate(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<ModerationRequest>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let bot_id = find_bot_id(&app_data, &broadcaster_id).await?;
+    let moderation = ModerationExecutor::new(
+        app_data.config,
+        app_data.db,
+        broadcaster_id,
+        bot_id,
+        Source::Manual,
+        user.id,
+    );
+    let result = match &request {
+        ModerationRequest::Timeout {
+            user_id,
+            duration_seconds,
+            reason,
+        } => {
+            moderation
+                .timeout(
+                    user_id,
+                    Duration::from_secs(u64::from(*duration_seconds)),
+                    reason,
+                )
+                .await
+        }
+        ModerationRequest::Ban { user_id, reason } => moderation.ban(user_id, reason).await,
+        ModerationRequest::Unban { user_id } => moderation.unban(user_id).await,
+        ModerationRequest::Delete {
+            message_id,
+            user_id,
+        } => {
+            moderation
+                .delete_message(message_id, user_id.as_deref())
+                .await
+        }
+    };
+    result.map_err(map_moderation_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct ModerationLogQuery {
+    action: Option<moderation_log::Action>,
+    source: Option<Source>,
+    actor_id: Option<String>,
+    target_user_id: Option<String>,
+    /// Only return entries older than this entry, i.e. the `next_before` of the previous page
+    before: Option<i64>,
+    limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ModerationLogResponse {
+    entries: Vec<moderation_log::Model>,
+    /// Pass this as `before` to get the next page, absent if this is the last page
+    next_before: Option<i64>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/moderation/log
+pub async fn get_moderation_log(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<ModerationLogQuery>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<ModerationLogResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_PAGE_SIZE);
+    if !(1..=MAX_LOG_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_limit",
+            format!("Limit must be between 1 and {MAX_LOG_PAGE_SIZE}"),
+        ));
+    }
+
+    let filter = LogFilter {
+        action: query.action,
+        source: query.source,
+        actor_id: query.actor_id,
+        target_user_id: query.target_user_id,
+    };
+    let entries =
+        moderation_log::find_page(&broadcaster_id, filter, query.before, limit, app_data.db)
+            .await
+            .context("get_moderation_log find entries")?;
+    let next_before = match entries.last() {
+        Some(last) if entries.len() as u64 == limit => Some(last.id),
+        _ => None,
+    };
+    Ok(Json(ModerationLogResponse {
+        entries,
+        next_before,
+    }))
+}
@@ -0,0 +1,252 @@
+use crate::models::{points_settings, user, user_authorization, user_channel_points};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{EntityTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LEADERBOARD_PAGE_SIZE: u64 = 10;
+const MAX_LEADERBOARD_PAGE_SIZE: u64 = 100;
+/// The database still has to walk past every skipped balance, and nobody pages this far through
+/// a public leaderboard
+const MAX_LEADERBOARD_OFFSET: u64 = 10_000;
+/// Same bound as the bets of minigames
+const MAX_ADJUSTMENT: i64 = 1_000_000_000;
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    offset: Option<u64>,
+    limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardUser {
+    id: String,
+    login: String,
+    display_name: String,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    /// Users with the same number of points share a rank
+    rank: u64,
+    user: LeaderboardUser,
+    points: i64,
+}
+
+// GET /api/v1/channel/:broadcaster_id/points/leaderboard
+// Public, so it can be shown on the website of the channel.
+pub async fn get_leaderboard(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<LeaderboardQuery>, QueryRejection>,
+) -> Result<Json<Vec<LeaderboardEntry>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_PAGE_SIZE);
+    if !(1..=MAX_LEADERBOARD_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_limit",
+            format!("Limit must be between 1 and {MAX_LEADERBOARD_PAGE_SIZE}"),
+        ));
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset > MAX_LEADERBOARD_OFFSET {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_offset",
+            format!("Offset must not be greater than {MAX_LEADERBOARD_OFFSET}"),
+        ));
+    }
+
+    let page = user_channel_points::find_leaderboard(&broadcaster_id, offset, limit, app_data.db)
+        .await
+        .context("get_leaderboard find leaderboard")?;
+
+    let mut entries = Vec::with_capacity(page.len());
+    for (points, user) in page {
+        let rank = match entries.last() {
+            // the page is sorted, so only the first entry and point changes need a query
+            Some(LeaderboardEntry {
+                rank,
+                points: previous,
+                ..
+            }) if *previous == points.points => *rank,
+            _ => user_channel_points::rank(&broadcaster_id, points.points, app_data.db)
+                .await
+                .context("get_leaderboard rank")?,
+        };
+        entries.push(LeaderboardEntry {
+            rank,
+            user: LeaderboardUser {
+                id: user.id,
+                login: user.login,
+                display_name: user.display_name,
+            },
+            points: points.points,
+        });
+    }
+    Ok(Json(entries))
+}
+
+#[derive(Deserialize)]
+pub struct AdjustPointsRequest {
+    user_id: String,
+    /// Negative to take points away
+    amount: i64,
+}
+
+#[derive(Serialize)]
+pub struct AdjustPointsResponse {
+    user_id: String,
+    points: i64,
+}
+
+// POST /api/v1/channel/:broadcaster_id/points/adjust
+pub async fn adjust_points(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<AdjustPointsRequest>, JsonRejection>,
+) -> Result<Json<AdjustPointsResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    if !(-MAX_ADJUSTMENT..=MAX_ADJUSTMENT).contains(&request.amount) {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_amount",
+            format!("Amount must be between -{MAX_ADJUSTMENT} and {MAX_ADJUSTMENT}"),
+        ));
+    }
+
+    let tx = app_data
+        .db
+        .begin()
+        .await
+        .context("adjust_points begin transaction")?;
+    // points can only be stored for users the bot has seen before
+    if user::Entity::find_by_id(request.user_id.clone())
+        .one(&tx)
+        .await
+        .context("adjust_points find user")?
+        .is_none()
+    {
+        return Err(ApiError::new_detailed(
+            StatusCode::NOT_FOUND,
+            "unknown_user",
+            "This user has never chatted or logged in",
+        ));
+    }
+    let points =
+        user_channel_points::add_points(&broadcaster_id, &request.user_id, request.amount, &tx)
+            .await
+            .context("adjust_points add points")?
+            .ok_or_else(|| {
+                ApiError::new_detailed(
+                    StatusCode::BAD_REQUEST,
+                    "insufficient_points",
+                    "The user does not have enough points to take this many away",
+                )
+            })?;
+    tx.commit().await.context("adjust_points commit")?;
+
+    Ok(Json(AdjustPointsResponse {
+        user_id: request.user_id,
+        points,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct PointsSettingsRequest {
+    enabled: bool,
+    points_per_interval: i32,
+    interval_seconds: i32,
+    active_seconds: i32,
+    subscriber_multiplier_percent: i32,
+    vip_multiplier_percent: i32,
+}
+
+impl PointsSettingsRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let checks = [
+            ("points_per_interval", self.points_per_interval, 0, 100_000),
+            ("interval_seconds", self.interval_seconds, 30, 86_400),
+            ("active_seconds", self.active_seconds, 30, 86_400),
+            (
+                "subscriber_multiplier_percent",
+                self.subscriber_multiplier_percent,
+                0,
+                10_000,
+            ),
+            (
+                "vip_multiplier_percent",
+                self.vip_multiplier_percent,
+                0,
+                10_000,
+            ),
+        ];
+        // same bounds as the database constraints
+        for (name, value, min, max) in checks {
+            if !(min..=max).contains(&value) {
+                return Err(ApiError::new_detailed(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_settings",
+                    format!("`{name}` must be between {min} and {max}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/points/settings
+pub async fn get_settings(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<points_settings::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let settings = points_settings::find_or_default(&broadcaster_id, app_data.db)
+        .await
+        .context("get_settings find settings")?;
+    Ok(Json(settings))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/points/settings
+pub async fn put_settings(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<PointsSettingsRequest>, JsonRejection>,
+) -> Result<Json<points_settings::Model>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let settings = points_settings::upsert(
+        points_settings::ActiveModel {
+            broadcaster_id: Set(broadcaster_id),
+            enabled: Set(request.enabled),
+            points_per_interval: Set(request.points_per_interval),
+            interval_seconds: Set(request.interval_seconds),
+            active_seconds: Set(request.active_seconds),
+            subscriber_multiplier_percent: Set(request.subscriber_multiplier_percent),
+            vip_multiplier_percent: Set(request.vip_multiplier_percent),
+        },
+        app_data.db,
+    )
+    .await
+    .context("put_settings upsert settings")?;
+    Ok(Json(settings))
+}
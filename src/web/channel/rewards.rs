@@ -0,0 +1,329 @@
+use crate::api::twitch::channel_points::RedemptionStatus;
+use crate::bot::redemptions;
+use crate::models::reward_action::{self, Action};
+use crate::models::{redemption_queue_entry, user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, SqlErr};
+use serde::Deserialize;
+
+/// Twitch rejects chat messages longer than this
+const MAX_MESSAGE_LENGTH: usize = 500;
+const MAX_QUEUE_NAME_LENGTH: usize = 50;
+/// Twitch allows timeouts of up to two weeks
+const MAX_TIMEOUT_SECONDS: i32 = 1_209_600;
+
+#[derive(Deserialize)]
+pub struct RewardActionRequest {
+    reward_id: String,
+    action: Action,
+    /// Required if `action` is `command`
+    command_trigger: Option<String>,
+    /// Required if `action` is `message`
+    message: Option<String>,
+    /// Required if `action` is `timeout`
+    timeout_seconds: Option<i32>,
+    /// Required if `action` is `queue`
+    queue_name: Option<String>,
+    #[serde(default)]
+    update_redemption: bool,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn invalid_action(message: &'static str) -> ApiError {
+    ApiError::new_detailed(StatusCode::BAD_REQUEST, "invalid_action", message)
+}
+
+impl RewardActionRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.reward_id.trim().is_empty() {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_reward_id",
+                "Reward ID must not be empty",
+            ));
+        }
+        match self.action {
+            Action::Command => {
+                if self
+                    .command_trigger
+                    .as_ref()
+                    .is_none_or(|trigger| trigger.trim().is_empty())
+                {
+                    return Err(invalid_action(
+                        "A `command_trigger` is required to run a command",
+                    ));
+                }
+            }
+            Action::Message => {
+                if self.message.as_ref().is_none_or(|message| {
+                    message.trim().is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH
+                }) {
+                    return Err(invalid_action(
+                        "A `message` of 1 to 500 characters is required to send a message",
+                    ));
+                }
+            }
+            Action::Timeout => {
+                if self
+                    .timeout_seconds
+                    .is_none_or(|seconds| !(1..=MAX_TIMEOUT_SECONDS).contains(&seconds))
+                {
+                    return Err(invalid_action(
+                        "`timeout_seconds` between 1 and 1209600 is required to time out the redeemer",
+                    ));
+                }
+            }
+            Action::Queue => {
+                if self.queue_name.as_ref().is_none_or(|name| {
+                    name.trim().is_empty() || name.chars().count() > MAX_QUEUE_NAME_LENGTH
+                }) {
+                    return Err(invalid_action(
+                        "A `queue_name` of 1 to 50 characters is required to add the redeemer to a queue",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_active_model(
+        self,
+        id: Option<i32>,
+        broadcaster_id: String,
+    ) -> reward_action::ActiveModel {
+        // only the setting of the chosen action is kept
+        let only_for = |action| self.action == action;
+        reward_action::ActiveModel {
+            id: id.map_or(NotSet, Set),
+            broadcaster_id: Set(broadcaster_id),
+            reward_id: Set(self.reward_id),
+            action: Set(self.action),
+            command_trigger: Set(self.command_trigger.filter(|_| only_for(Action::Command))),
+            message: Set(self.message.filter(|_| only_for(Action::Message))),
+            timeout_seconds: Set(self.timeout_seconds.filter(|_| only_for(Action::Timeout))),
+            queue_name: Set(self.queue_name.filter(|_| only_for(Action::Queue))),
+            update_redemption: Set(self.update_redemption),
+            enabled: Set(self.enabled),
+        }
+    }
+}
+
+fn map_write_error(err: DbErr) -> ApiError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ApiError::new_detailed(
+            StatusCode::CONFLICT,
+            "reward_in_use",
+            "Another action in this channel is already bound to this reward",
+        ),
+        _ => ApiError::from(err),
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/rewards
+pub async fn list_reward_actions(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<Vec<reward_action::Model>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let actions = reward_action::Entity::find()
+        .filter(reward_action::Column::BroadcasterId.eq(broadcaster_id))
+        .order_by_asc(reward_action::Column::Id)
+        .all(app_data.db)
+        .await
+        .context("list_reward_actions find actions")?;
+    Ok(Json(actions))
+}
+
+// GET /api/v1/channel/:broadcaster_id/rewards/:reward_action_id
+pub async fn get_reward_action(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<reward_action::Model>, ApiError> {
+    let Path((broadcaster_id, action_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let action = reward_action::Entity::find_by_id(action_id)
+        .filter(reward_action::Column::BroadcasterId.eq(broadcaster_id))
+        .one(app_data.db)
+        .await
+        .context("get_reward_action find action")?
+        .ok_or_else(ApiError::not_found)?;
+    Ok(Json(action))
+}
+
+// POST /api/v1/channel/:broadcaster_id/rewards
+pub async fn create_reward_action(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<RewardActionRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<reward_action::Model>), ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let action = request
+        .into_active_model(None, broadcaster_id)
+        .insert(app_data.db)
+        .await
+        .map_err(map_write_error)?;
+    Ok((StatusCode::CREATED, Json(action)))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/rewards/:reward_action_id
+pub async fn update_reward_action(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<RewardActionRequest>, JsonRejection>,
+) -> Result<Json<reward_action::Model>, ApiError> {
+    let Path((broadcaster_id, action_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    reward_action::Entity::find_by_id(action_id)
+        .filter(reward_action::Column::BroadcasterId.eq(broadcaster_id.as_str()))
+        .one(app_data.db)
+        .await
+        .context("update_reward_action find action")?
+        .ok_or_else(ApiError::not_found)?;
+
+    let action = request
+        .into_active_model(Some(action_id), broadcaster_id)
+        .update(app_data.db)
+        .await
+        .map_err(map_write_error)?;
+    Ok(Json(action))
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/rewards/:reward_action_id
+pub async fn delete_reward_action(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path((broadcaster_id, action_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let result = reward_action::Entity::delete_many()
+        .filter(reward_action::Column::Id.eq(action_id))
+        .filter(reward_action::Column::BroadcasterId.eq(broadcaster_id))
+        .exec(app_data.db)
+        .await
+        .context("delete_reward_action delete action")?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct QueueQuery {
+    /// Only entries of this queue, all queues of the channel if absent
+    queue_name: Option<String>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/redemptions/queue
+pub async fn list_queue(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<QueueQuery>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<Vec<redemption_queue_entry::Model>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+
+    let mut find = redemption_queue_entry::Entity::find()
+        .filter(redemption_queue_entry::Column::BroadcasterId.eq(broadcaster_id));
+    if let Some(queue_name) = query.queue_name {
+        find = find.filter(redemption_queue_entry::Column::QueueName.eq(queue_name));
+    }
+    let entries = find
+        .order_by_asc(redemption_queue_entry::Column::Id)
+        .all(app_data.db)
+        .await
+        .context("list_queue find entries")?;
+    Ok(Json(entries))
+}
+
+#[derive(Deserialize)]
+pub struct RemoveQueueEntryQuery {
+    /// Cancel the redemption to refund the points, instead of marking it as fulfilled
+    #[serde(default)]
+    refund: bool,
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/redemptions/queue/:entry_id
+pub async fn remove_queue_entry(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i64)>, PathRejection>,
+    query: Result<Query<RemoveQueueEntryQuery>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path((broadcaster_id, entry_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+
+    let entry = redemption_queue_entry::Entity::find_by_id(entry_id)
+        .filter(redemption_queue_entry::Column::BroadcasterId.eq(broadcaster_id.as_str()))
+        .one(app_data.db)
+        .await
+        .context("remove_queue_entry find entry")?
+        .ok_or_else(ApiError::not_found)?;
+
+    // completed first, so the entry stays in the queue if Twitch refuses
+    if entry.update_redemption {
+        let status = if query.refund {
+            RedemptionStatus::Canceled
+        } else {
+            RedemptionStatus::Fulfilled
+        };
+        if let Err(e) = redemptions::update_status(
+            app_data.config,
+            app_data.db,
+            &broadcaster_id,
+            &entry.reward_id,
+            &entry.redemption_id,
+            status,
+        )
+        .await
+        {
+            tracing::warn!(
+                "Failed to mark redemption {} as {status:?}: {e:#}",
+                entry.redemption_id
+            );
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_GATEWAY,
+                "redemption_update_failed",
+                "Twitch did not allow to complete the redemption",
+            ));
+        }
+    }
+
+    redemption_queue_entry::Entity::delete_by_id(entry.id)
+        .exec(app_data.db)
+        .await
+        .context("remove_queue_entry delete entry")?;
+    Ok(StatusCode::NO_CONTENT)
+}
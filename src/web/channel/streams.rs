@@ -0,0 +1,70 @@
+use crate::models::{stream_session, stream_session_change, user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_HISTORY_PAGE_SIZE: u64 = 20;
+const MAX_HISTORY_PAGE_SIZE: u64 = 100;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Only return streams older than this stream, i.e. the `next_before` of the previous page
+    before: Option<i64>,
+    limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryStream {
+    #[serde(flatten)]
+    session: stream_session::Model,
+    /// Title and category of the stream whenever one of them changed, oldest first
+    changes: Vec<stream_session_change::Model>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    streams: Vec<HistoryStream>,
+    /// Pass this as `before` to get the next page, absent if this is the last page
+    next_before: Option<i64>,
+}
+
+// GET /api/v1/channel/:broadcaster_id/streams
+pub async fn get_history(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<HistoryResponse>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Query(query) = query.map_err(|_| ApiError::bad_query_parameters())?;
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+    if !(1..=MAX_HISTORY_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "invalid_limit",
+            format!("Limit must be between 1 and {MAX_HISTORY_PAGE_SIZE}"),
+        ));
+    }
+
+    let sessions = stream_session::find_page(&broadcaster_id, query.before, limit, app_data.db)
+        .await
+        .context("get_history find sessions")?;
+    let next_before = match sessions.last() {
+        Some((last, _)) if sessions.len() as u64 == limit => Some(last.id),
+        _ => None,
+    };
+    Ok(Json(HistoryResponse {
+        streams: sessions
+            .into_iter()
+            .map(|(session, changes)| HistoryStream { session, changes })
+            .collect(),
+        next_before,
+    }))
+}
@@ -0,0 +1,261 @@
+use crate::models::timer::{self, NewMessage};
+use crate::models::timer_message::{self, StreamState};
+use crate::models::{user, user_authorization};
+use crate::web::channel::require_channel_access;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, State};
+use axum::Json;
+use http::StatusCode;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, ModelTrait, QueryFilter, SqlErr,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+/// Twitch rejects chat messages longer than this
+const MAX_MESSAGE_LENGTH: usize = 500;
+const MAX_NAME_LENGTH: usize = 50;
+const MAX_MESSAGES: usize = 20;
+const MAX_INTERVAL_MINUTES: i32 = 1440;
+const MAX_MIN_CHAT_MESSAGES: i32 = 10_000;
+
+#[derive(Deserialize)]
+pub struct TimerMessageRequest {
+    message: String,
+    #[serde(default = "default_stream_state")]
+    stream_state: StreamState,
+}
+
+fn default_stream_state() -> StreamState {
+    StreamState::Online
+}
+
+#[derive(Deserialize)]
+pub struct TimerRequest {
+    name: String,
+    interval_minutes: i32,
+    #[serde(default)]
+    min_chat_messages: i32,
+    /// Each post picks one of the messages matching the stream status at random
+    messages: Vec<TimerMessageRequest>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TimerRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.name.trim().is_empty() || self.name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_name",
+                format!("Name must be between 1 and {MAX_NAME_LENGTH} characters long"),
+            ));
+        }
+        if !(1..=MAX_INTERVAL_MINUTES).contains(&self.interval_minutes) {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_interval",
+                format!("Interval must be between 1 and {MAX_INTERVAL_MINUTES} minutes"),
+            ));
+        }
+        if !(0..=MAX_MIN_CHAT_MESSAGES).contains(&self.min_chat_messages) {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_min_chat_messages",
+                format!("Minimum chat messages must be between 0 and {MAX_MIN_CHAT_MESSAGES}"),
+            ));
+        }
+        if self.messages.is_empty()
+            || self.messages.len() > MAX_MESSAGES
+            || self.messages.iter().any(|message| {
+                message.message.trim().is_empty()
+                    || message.message.chars().count() > MAX_MESSAGE_LENGTH
+            })
+        {
+            return Err(ApiError::new_detailed(
+                StatusCode::BAD_REQUEST,
+                "invalid_messages",
+                format!("A timer needs 1 to {MAX_MESSAGES} messages, each between 1 and {MAX_MESSAGE_LENGTH} characters long"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The timer itself and its messages, which are stored separately.
+    fn into_parts(
+        self,
+        id: Option<i32>,
+        broadcaster_id: String,
+    ) -> (timer::ActiveModel, Vec<NewMessage>) {
+        let timer = timer::ActiveModel {
+            id: id.map_or(NotSet, Set),
+            broadcaster_id: Set(broadcaster_id),
+            name: Set(self.name),
+            interval_minutes: Set(self.interval_minutes),
+            min_chat_messages: Set(self.min_chat_messages),
+            enabled: Set(self.enabled),
+            created_at: NotSet,
+        };
+        let messages = self
+            .messages
+            .into_iter()
+            .map(|message| NewMessage {
+                message: message.message,
+                stream_state: message.stream_state,
+            })
+            .collect();
+        (timer, messages)
+    }
+}
+
+#[derive(Serialize)]
+pub struct TimerResponse {
+    #[serde(flatten)]
+    timer: timer::Model,
+    messages: Vec<timer_message::Model>,
+}
+
+fn map_write_error(err: DbErr) -> ApiError {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ApiError::new_detailed(
+            StatusCode::CONFLICT,
+            "timer_name_in_use",
+            "A timer with this name already exists in this channel",
+        ),
+        _ => ApiError::from(err),
+    }
+}
+
+// GET /api/v1/channel/:broadcaster_id/timers
+pub async fn list_timers(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<Vec<TimerResponse>>, ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let timers = timer::find_with_messages(&broadcaster_id, true, app_data.db)
+        .await
+        .context("list_timers find timers")?;
+    Ok(Json(
+        timers
+            .into_iter()
+            .map(|(timer, messages)| TimerResponse { timer, messages })
+            .collect(),
+    ))
+}
+
+// GET /api/v1/channel/:broadcaster_id/timers/:timer_id
+pub async fn get_timer(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<Json<TimerResponse>, ApiError> {
+    let Path((broadcaster_id, timer_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    let timer = timer::Entity::find_by_id(timer_id)
+        .filter(timer::Column::BroadcasterId.eq(broadcaster_id))
+        .one(app_data.db)
+        .await
+        .context("get_timer find timer")?
+        .ok_or_else(ApiError::not_found)?;
+    let mut messages = timer
+        .find_related(timer_message::Entity)
+        .all(app_data.db)
+        .await
+        .context("get_timer find messages")?;
+    messages.sort_by_key(|message| message.id);
+    Ok(Json(TimerResponse { timer, messages }))
+}
+
+// POST /api/v1/channel/:broadcaster_id/timers
+pub async fn create_timer(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<String>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<TimerRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<TimerResponse>), ApiError> {
+    let Path(broadcaster_id) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let (timer, messages) = request.into_parts(None, broadcaster_id);
+    let tx = app_data
+        .db
+        .begin()
+        .await
+        .context("create_timer begin transaction")?;
+    let timer = timer.insert(&tx).await.map_err(map_write_error)?;
+    let messages = timer::replace_messages(timer.id, messages, &tx)
+        .await
+        .context("create_timer insert messages")?;
+    tx.commit().await.context("create_timer commit")?;
+    Ok((StatusCode::CREATED, Json(TimerResponse { timer, messages })))
+}
+
+// PUT /api/v1/channel/:broadcaster_id/timers/:timer_id
+pub async fn update_timer(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+    body: Result<Json<TimerRequest>, JsonRejection>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let Path((broadcaster_id, timer_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+    let Json(request) = body.map_err(|_| ApiError::bad_request_body())?;
+    request.validate()?;
+
+    let tx = app_data
+        .db
+        .begin()
+        .await
+        .context("update_timer begin transaction")?;
+    timer::Entity::find_by_id(timer_id)
+        .filter(timer::Column::BroadcasterId.eq(broadcaster_id.as_str()))
+        .one(&tx)
+        .await
+        .context("update_timer find timer")?
+        .ok_or_else(ApiError::not_found)?;
+
+    let (timer, messages) = request.into_parts(Some(timer_id), broadcaster_id);
+    let timer = timer.update(&tx).await.map_err(map_write_error)?;
+    let messages = timer::replace_messages(timer.id, messages, &tx)
+        .await
+        .context("update_timer replace messages")?;
+    tx.commit().await.context("update_timer commit")?;
+    Ok(Json(TimerResponse { timer, messages }))
+}
+
+// DELETE /api/v1/channel/:broadcaster_id/timers/:timer_id
+pub async fn delete_timer(
+    State(app_data): State<WebAppData>,
+    path: Result<Path<(String, i32)>, PathRejection>,
+    (_, user): (user_authorization::Model, user::Model),
+) -> Result<StatusCode, ApiError> {
+    let Path((broadcaster_id, timer_id)) = path.map_err(|_| ApiError::bad_path_parameters())?;
+    require_channel_access(&user, &broadcaster_id)?;
+
+    // messages are deleted along with the timer
+    let result = timer::Entity::delete_many()
+        .filter(timer::Column::Id.eq(timer_id))
+        .filter(timer::Column::BroadcasterId.eq(broadcaster_id))
+        .exec(app_data.db)
+        .await
+        .context("delete_timer delete timer")?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
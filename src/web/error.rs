@@ -49,6 +49,30 @@ impl ApiError {
         )
     }
 
+    pub fn bad_path_parameters() -> ApiError {
+        ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "bad_path_parameters",
+            "Invalid path parameters",
+        )
+    }
+
+    pub fn bad_request_body() -> ApiError {
+        ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "bad_request_body",
+            "Invalid or missing request body",
+        )
+    }
+
+    pub fn not_found() -> ApiError {
+        ApiError::new_detailed(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            "The requested resource does not exist",
+        )
+    }
+
     pub fn method_not_allowed() -> ApiError {
         ApiError::new_basic(StatusCode::METHOD_NOT_ALLOWED)
     }
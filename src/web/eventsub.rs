@@ -0,0 +1,101 @@
+use crate::api::twitch::eventsub::Subscription;
+use crate::bot::notifications::{self, skip, EventError};
+use crate::config::eventsub::EventSubTransport;
+use crate::web::error::ApiError;
+use crate::web::WebAppData;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use twitch_api::eventsub::Event;
+
+#[derive(Deserialize)]
+struct VerificationRequest {
+    challenge: String,
+}
+
+#[derive(Deserialize)]
+struct RevocationRequest {
+    subscription: Subscription,
+}
+
+// POST /api/v1/eventsub/webhook
+pub async fn receive_webhook(
+    State(app_data): State<WebAppData>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let EventSubTransport::Webhook { secret, .. } = &app_data.config.eventsub.transport else {
+        return Err(ApiError::not_found());
+    };
+
+    let mut request = http::Request::new(body);
+    *request.headers_mut() = headers;
+    if !Event::verify_payload(&request, secret.as_bytes()) {
+        return Err(ApiError::new_detailed(
+            StatusCode::FORBIDDEN,
+            "invalid_signature",
+            "The message signature is invalid",
+        ));
+    }
+
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let message_id = header("Twitch-Eventsub-Message-Id").ok_or_else(|| {
+        ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "missing_message_id",
+            "The message has no Twitch-Eventsub-Message-Id header",
+        )
+    })?;
+    let timestamp = header("Twitch-Eventsub-Message-Timestamp").ok_or_else(|| {
+        ApiError::new_detailed(
+            StatusCode::BAD_REQUEST,
+            "missing_message_timestamp",
+            "The message has no Twitch-Eventsub-Message-Timestamp header",
+        )
+    })?;
+    let message_type = header("Twitch-Eventsub-Message-Type");
+    // Twitch only needs to know that the message arrived, it was handled the first time already.
+    // A verification is answered every time, in case the previous answer never reached Twitch.
+    if message_type != Some("webhook_callback_verification") {
+        if let Err(e) = app_data.events.accept_message(message_id, timestamp) {
+            skip(e.into());
+            return Ok(StatusCode::NO_CONTENT.into_response());
+        }
+    }
+
+    let result = match message_type {
+        Some("webhook_callback_verification") => {
+            match serde_json::from_slice::<VerificationRequest>(request.body()) {
+                Ok(verification) => return Ok(verification.challenge.into_response()),
+                Err(e) => Err(EventError::from(e)),
+            }
+        }
+        Some("revocation") => serde_json::from_slice::<RevocationRequest>(request.body())
+            .map_err(EventError::from)
+            .and_then(|revocation| {
+                notifications::publish_revocation(&app_data.events, revocation.subscription)
+            }),
+        Some("notification") => Event::parse_http(&request)
+            .map_err(EventError::from)
+            .and_then(|event| {
+                notifications::publish(
+                    &app_data.events,
+                    event,
+                    header("Twitch-Eventsub-Subscription-Type").unwrap_or("unknown"),
+                )
+            }),
+        _ => Err(EventError::UnknownMessage),
+    };
+    // acknowledged anyway, Twitch would only deliver the same message again
+    if let Err(e) = result {
+        skip(e);
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
@@ -0,0 +1,10 @@
+use crate::events::{EventMetricsSnapshot, EVENT_METRICS};
+use crate::models::{user, user_authorization};
+use axum::Json;
+
+// GET /api/v1/metrics/events
+pub async fn get_event_metrics(
+    _: (user_authorization::Model, user::Model),
+) -> Json<EventMetricsSnapshot> {
+    Json(EVENT_METRICS.snapshot())
+}
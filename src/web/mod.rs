@@ -1,37 +1,57 @@
 pub mod auth;
+pub mod channel;
 pub mod error;
+pub mod eventsub;
+pub mod metrics;
 
 use std::future::IntoFuture;
+use std::sync::Arc;
 
+use crate::bot::banphrases::BanphraseCache;
 use crate::config::web::ListenAddr;
 use crate::web::error::ApiError;
-use crate::Config;
+use crate::{Config, Events};
 use anyhow::Context;
 use axum::http::{header, Method};
+use axum::routing::delete;
 use axum::routing::get;
 use axum::routing::post;
 use axum::Router;
 use futures::future::BoxFuture;
 use sea_orm::DatabaseConnection;
 use tokio::net::TcpListener;
+use tokio::sync::Notify;
 use tokio_util::sync::CancellationToken;
 use tower_http::cors::{self, CorsLayer};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct WebAppData {
     config: &'static Config,
     db: &'static DatabaseConnection,
+    banphrase_cache: &'static BanphraseCache,
+    /// Notified after the bot table was modified
+    bot_changes: &'static Notify,
+    /// Receives the notifications of the EventSub webhook
+    events: Arc<Events>,
 }
 
 pub async fn run(
     config: &'static Config,
     db: &'static DatabaseConnection,
+    events: Arc<Events>,
+    bot_changes: &'static Notify,
     shutdown_signal: CancellationToken,
 ) -> anyhow::Result<BoxFuture<'static, std::io::Result<()>>> {
-    let shared_state = WebAppData { config, db };
+    let shared_state = WebAppData {
+        config,
+        db,
+        banphrase_cache: Box::leak(Box::new(BanphraseCache::new())),
+        bot_changes,
+        events,
+    };
 
     let cors = CorsLayer::new()
-        .allow_methods(vec![Method::GET, Method::POST])
+        .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_headers(vec![
             header::AUTHORIZATION,
             header::ACCEPT,
@@ -52,6 +72,137 @@ pub async fn run(
             "/auth/revoke",
             post(auth::revoke_login::revoke_token).fallback(method_fallback()),
         )
+        .route(
+            "/auth/special",
+            post(auth::create_special::create_special_twitch_auth).fallback(method_fallback()),
+        )
+        .route(
+            "/auth/special/authorize",
+            post(auth::create_special::create_authorize_url).fallback(method_fallback()),
+        )
+        .route(
+            "/eventsub/webhook",
+            post(eventsub::receive_webhook).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/authorization",
+            get(channel::authorization::get_authorization_status).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/banphrases",
+            get(channel::banphrases::list_banphrases)
+                .post(channel::banphrases::create_banphrase)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/banphrases/test",
+            post(channel::banphrases::test_message).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/banphrases/:banphrase_id",
+            get(channel::banphrases::get_banphrase)
+                .put(channel::banphrases::update_banphrase)
+                .delete(channel::banphrases::delete_banphrase)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/moderation",
+            post(channel::moderation::moderate).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/moderation/log",
+            get(channel::moderation::get_moderation_log).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/bot",
+            get(channel::bot::get_bot)
+                .put(channel::bot::put_bot)
+                .delete(channel::bot::delete_bot)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/bot/health",
+            get(channel::bot::get_bot_health).fallback(method_fallback()),
+        )
+        .route(
+            "/metrics/events",
+            get(metrics::get_event_metrics).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/commands",
+            get(channel::commands::list_commands)
+                .post(channel::commands::create_command)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/commands/:command_id",
+            get(channel::commands::get_command)
+                .put(channel::commands::update_command)
+                .delete(channel::commands::delete_command)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/points/leaderboard",
+            get(channel::points::get_leaderboard).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/points/adjust",
+            post(channel::points::adjust_points).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/points/settings",
+            get(channel::points::get_settings)
+                .put(channel::points::put_settings)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/minigames/settings",
+            get(channel::minigames::get_settings)
+                .put(channel::minigames::put_settings)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/minigames/history",
+            get(channel::minigames::get_history).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/rewards",
+            get(channel::rewards::list_reward_actions)
+                .post(channel::rewards::create_reward_action)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/rewards/:reward_action_id",
+            get(channel::rewards::get_reward_action)
+                .put(channel::rewards::update_reward_action)
+                .delete(channel::rewards::delete_reward_action)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/redemptions/queue",
+            get(channel::rewards::list_queue).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/redemptions/queue/:entry_id",
+            delete(channel::rewards::remove_queue_entry).fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/timers",
+            get(channel::timers::list_timers)
+                .post(channel::timers::create_timer)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/timers/:timer_id",
+            get(channel::timers::get_timer)
+                .put(channel::timers::update_timer)
+                .delete(channel::timers::delete_timer)
+                .fallback(method_fallback()),
+        )
+        .route(
+            "/channel/:broadcaster_id/streams",
+            get(channel::streams::get_history).fallback(method_fallback()),
+        )
         .layer(cors);
 
     let app = Router::new()